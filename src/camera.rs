@@ -1,98 +1,725 @@
+use std::{
+    f64::consts::PI,
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use crate::{
-    canvas::Canvas, core::matrices::Matrix, core::tuples::Tuple, rays::Ray, scenarios::world::World,
+    canvas::Canvas,
+    core::matrices::Matrix,
+    core::sampler::{ApertureShape, Sampler, SamplingStrategy},
+    core::transformations::ViewTransform,
+    core::tuples::Tuple,
+    rays::Ray,
+    scenarios::lights::PointLight,
+    scenarios::world::{RenderStats, World},
+    shapes::intersections::DEFAULT_EPSILON,
 };
 
+// Tuning of a render, shared by every render method of the camera
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderSettings {
+    // Bounces allowed to reflected and refracted rays
+    pub max_depth: usize,
+    // Offset of the points used to cast secondary rays off a surface
+    pub epsilon: f64,
+    // Rays averaged per pixel, 1 casts a single ray through the center
+    pub samples: usize,
+    pub seed: u64,
+    // Whether large groups may be intersected on several threads
+    pub parallel: bool,
+    // Checked before every row, a cancelled render returns the rows done so
+    // far and leaves the others black
+    pub cancel: CancelToken,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            max_depth: 5,
+            epsilon: DEFAULT_EPSILON,
+            samples: 1,
+            seed: 0,
+            parallel: true,
+            cancel: CancelToken::default(),
+        }
+    }
+}
+
+// Shared flag to stop a render from another thread, or once a deadline has
+// passed. Clones refer to the same flag, a new token is never cancelled.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    // Token that cancels itself when `timeout` has passed from now
+    pub fn with_timeout(timeout: Duration) -> CancelToken {
+        CancelToken {
+            deadline: Some(Instant::now() + timeout),
+            ..CancelToken::default()
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl PartialEq for CancelToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+// Snapshot of the camera parameters, meant to be displayed
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraDescription {
+    pub hsize: usize,
+    pub vsize: usize,
+    pub field_of_view: f64,
+    // None when the transform was set directly with set_transform
+    pub look_at: Option<ViewTransform>,
+}
+
+// How the pixels of the image are turned into ray directions
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Projection {
+    #[default]
+    Perspective,
+    // Longitude across the width and latitude across the height, the image
+    // covers the whole sphere around the eye as in 360 panoramas. The field
+    // of view is ignored.
+    Equirectangular,
+}
+
+// How the hits seen by the camera are shaded
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RenderMode {
+    #[default]
+    Lit,
+    // The bare color of the materials and their patterns, without any light,
+    // handy to check textures independently of the lighting
+    Unlit,
+}
+
+#[derive(Clone)]
 pub struct Camera {
     hsize: usize,
     vsize: usize,
-    _field_of_view: f64,
+    field_of_view: f64,
     transform: Matrix,
-    inverse_transform: Option<Matrix>,
+    inverse_transform: Matrix,
+    look_at: Option<ViewTransform>,
     half_width: f64,
     half_height: f64,
     pixel_size: f64,
+    sampling_strategy: SamplingStrategy,
+    headlamp: bool,
+    // Camera rays only see the hits at a distance in (near, far]
+    near: f64,
+    far: f64,
+    projection: Projection,
+    render_mode: RenderMode,
+    // Thin lens, 0 is a pinhole with everything in focus. The rays of the
+    // samples of a pixel leave from points of the aperture and meet on the
+    // plane in focus, `focal_distance` in front of the eye.
+    aperture_radius: f64,
+    aperture_shape: ApertureShape,
+    focal_distance: f64,
 }
 
 impl Camera {
     pub fn new(hsize: usize, vsize: usize, field_of_view: f64) -> Camera {
-        let half_view = (field_of_view / 2.0).tan();
-        let aspect = hsize as f64 / vsize as f64;
+        let mut camera = Camera {
+            hsize,
+            vsize,
+            field_of_view,
+            transform: Matrix::identity(4),
+            inverse_transform: Matrix::identity(4),
+            look_at: None,
+            half_height: 0.0,
+            half_width: 0.0,
+            pixel_size: 0.0,
+            sampling_strategy: SamplingStrategy::default(),
+            headlamp: false,
+            near: 0.0,
+            far: f64::INFINITY,
+            projection: Projection::default(),
+            render_mode: RenderMode::default(),
+            aperture_radius: 0.0,
+            aperture_shape: ApertureShape::default(),
+            focal_distance: 1.0,
+        };
+        camera.compute_pixel_size();
 
-        let (half_width, half_height) = if aspect >= 1.0 {
+        camera
+    }
+
+    fn compute_pixel_size(&mut self) {
+        let half_view = (self.field_of_view / 2.0).tan();
+        let aspect = self.hsize as f64 / self.vsize as f64;
+
+        (self.half_width, self.half_height) = if aspect >= 1.0 {
             (half_view, half_view / aspect)
         } else {
             (half_view * aspect, half_view)
         };
 
-        let pixel_size = (half_width * 2.0) / hsize as f64;
+        self.pixel_size = (self.half_width * 2.0) / self.hsize as f64;
+    }
 
-        Camera {
-            hsize,
-            vsize,
-            _field_of_view: field_of_view,
-            transform: Matrix::identity(4),
-            inverse_transform: None,
-            half_height,
-            half_width,
-            pixel_size,
-        }
+    pub fn get_field_of_view(&self) -> f64 {
+        self.field_of_view
     }
 
-    fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
-        let xoffset = (px as f64 + 0.5) * self.pixel_size;
-        let yoffset = (py as f64 + 0.5) * self.pixel_size;
+    pub fn set_field_of_view(&mut self, field_of_view: f64) {
+        self.field_of_view = field_of_view;
+        self.compute_pixel_size();
+    }
 
-        let world_x = self.half_width - xoffset;
-        let world_y = self.half_height - yoffset;
+    // The origin is the same for every ray, see get_origin
+    fn ray_for_pixel(&self, origin: &Tuple, px: usize, py: usize) -> Ray {
+        self.ray_for_subpixel(origin, px, py, 0.5, 0.5)
+    }
 
-        let inverse_transform = match &self.inverse_transform {
-            Some(matrix) => matrix.clone(),
-            None => self.transform.invert(),
-        };
+    // The sub-pixel offsets are in [0, 1), with 0.5 being the pixel center
+    fn ray_for_subpixel(&self, origin: &Tuple, px: usize, py: usize, dx: f64, dy: f64) -> Ray {
+        if self.projection == Projection::Equirectangular {
+            return self.ray_for_direction(origin, px as f64 + dx, py as f64 + dy);
+        }
+
+        let (world_x, world_y) = self.image_point(px, py, dx, dy);
 
         // Remember that canvas is at z = -1
-        let pixel = &inverse_transform * &Tuple::new_point(world_x, world_y, -1.0);
-        let origin = &inverse_transform * &Tuple::new_point(0.0, 0.0, 0.0);
-        let direction = (&pixel - &origin).normalize();
+        let pixel = &self.inverse_transform * &Tuple::new_point(world_x, world_y, -1.0);
+        let direction = (&pixel - origin).normalize();
+
+        Ray::new(origin.clone(), direction)
+    }
+
+    // Same as ray_for_subpixel, leaving from the point (u, v) of the aperture
+    // of radius 1 instead of from the eye
+    fn ray_through_lens(&self, px: usize, py: usize, dx: f64, dy: f64, (u, v): (f64, f64)) -> Ray {
+        let (world_x, world_y) = self.image_point(px, py, dx, dy);
+
+        let f = self.focal_distance;
+        let focus = &self.inverse_transform * &Tuple::new_point(world_x * f, world_y * f, -f);
+        let r = self.aperture_radius;
+        let origin = &self.inverse_transform * &Tuple::new_point(u * r, v * r, 0.0);
+        let direction = (&focus - &origin).normalize();
 
         Ray::new(origin, direction)
     }
 
-    pub fn render(&self, world: &mut World) -> Canvas {
+    // Position on the canvas, at z = -1 in camera space, of an offset inside
+    // a pixel
+    fn image_point(&self, px: usize, py: usize, dx: f64, dy: f64) -> (f64, f64) {
+        let xoffset = (px as f64 + dx) * self.pixel_size;
+        let yoffset = (py as f64 + dy) * self.pixel_size;
+
+        (self.half_width - xoffset, self.half_height - yoffset)
+    }
+
+    // The center of the image looks towards -z, longitudes grow to the right
+    // and the left and right edges meet behind the eye
+    fn ray_for_direction(&self, origin: &Tuple, x: f64, y: f64) -> Ray {
+        let longitude = (x / self.hsize as f64 - 0.5) * 2.0 * PI;
+        let latitude = (0.5 - y / self.vsize as f64) * PI;
+
+        let direction = Tuple::new_vector(
+            -longitude.sin() * latitude.cos(),
+            latitude.sin(),
+            -longitude.cos() * latitude.cos(),
+        );
+        let direction = (&self.inverse_transform * &direction).normalize();
+
+        Ray::new(origin.clone(), direction)
+    }
+
+    // Position of the eye in world space
+    pub fn get_origin(&self) -> Tuple {
+        &self.inverse_transform * &Tuple::new_point(0.0, 0.0, 0.0)
+    }
+
+    // For every object of the world, whether its bounding box can be seen by
+    // the camera. A box is culled when its corners all lie behind the eye or
+    // outside the same side of the image, unbounded objects are always kept.
+    pub fn visible_objects(&self, world: &World) -> Vec<bool> {
+        // Rays leaving the lens off its center see around the edges of the
+        // image as seen from the eye
+        if self.projection == Projection::Equirectangular || self.thin_lens() {
+            return vec![true; world.objects().count()];
+        }
+
+        world
+            .objects()
+            .map(|object| match object.bounds() {
+                None => true,
+                Some(bounds) => {
+                    let corners = bounds.corners().map(|corner| &self.transform * &corner);
+
+                    // The camera looks towards -z, depth grows in front of it
+                    let outside = |plane: &dyn Fn(&Tuple) -> f64| {
+                        corners.iter().all(|corner| plane(corner) <= 0.0)
+                    };
+
+                    !(outside(&|c| -c.z)
+                        || outside(&|c| -self.half_width * c.z - c.x)
+                        || outside(&|c| -self.half_width * c.z + c.x)
+                        || outside(&|c| -self.half_height * c.z - c.y)
+                        || outside(&|c| -self.half_height * c.z + c.y))
+                }
+            })
+            .collect()
+    }
+
+    // Id of the object seen through the center of the pixel, the one returned
+    // by World::add_shape. None where the ray misses, or only hits outside of
+    // the clip range, or hits the world group.
+    pub fn object_at_pixel(&self, world: &World, x: usize, y: usize) -> Option<usize> {
+        let ray = self.ray_for_pixel(&self.get_origin(), x, y);
+
+        world
+            .cast_between(&ray, self.near, self.far)?
+            .get_object_id()
+    }
+
+    // Position on the image of a point in world space, in pixels and not
+    // rounded. None when the point is not in front of the eye, and always None
+    // for panoramas where straight edges do not stay straight on the image.
+    pub fn project(&self, point: &Tuple) -> Option<(f64, f64)> {
+        let view_point = &self.transform * point;
+        if view_point.z > -1e-9 || self.projection != Projection::Perspective {
+            return None;
+        }
+
+        // The image plane is at z = -1
+        let depth = -view_point.z;
+        let x = (self.half_width - view_point.x / depth) / self.pixel_size;
+        let y = (self.half_height - view_point.y / depth) / self.pixel_size;
+
+        Some((x, y))
+    }
+
+    // Draws the edges of the world space bounding box of every object, and of
+    // the world group, over a render. Edges with an end behind the eye are
+    // skipped, unbounded objects have no box.
+    pub fn draw_bounds_overlay(&self, world: &World, canvas: &mut Canvas, color: &Tuple) {
+        let boxes = world
+            .objects()
+            .filter_map(|object| object.bounds())
+            .chain(world.get_group_ref().bounds());
+
+        for bounds in boxes {
+            let corners = bounds.corners();
+
+            // Corners are indexed by three bits, one per axis, edges join the
+            // corners that differ in a single bit
+            for from in 0..corners.len() {
+                for bit in [1, 2, 4] {
+                    if from & bit != 0 {
+                        continue;
+                    }
+                    let to = from | bit;
+
+                    let projected = (self.project(&corners[from]), self.project(&corners[to]));
+                    if let (Some(start), Some(end)) = projected {
+                        if let Some((start, end)) = self.clip_to_image(start, end) {
+                            canvas.draw_line(start, end, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Liang-Barsky clipping of a segment to the image, so that edges running
+    // far outside of it do not walk millions of pixels
+    fn clip_to_image(
+        &self,
+        start: (f64, f64),
+        end: (f64, f64),
+    ) -> Option<((isize, isize), (isize, isize))> {
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+
+        for (p, q) in [
+            (-dx, start.0),
+            (dx, self.hsize as f64 - start.0),
+            (-dy, start.1),
+            (dy, self.vsize as f64 - start.1),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+                continue;
+            }
+
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+
+        if t0 > t1 {
+            return None;
+        }
+
+        let pixel = |t: f64| {
+            (
+                (start.0 + t * dx).floor() as isize,
+                (start.1 + t * dy).floor() as isize,
+            )
+        };
+
+        Some((pixel(t0), pixel(t1)))
+    }
+
+    pub fn render(&self, world: &mut World, settings: &RenderSettings) -> Canvas {
         let mut image = Canvas::new(self.hsize, self.vsize);
+        self.render_into(world, settings, &mut image);
+
+        image
+    }
+
+    // Same as render, but every pixel of the given canvas is overwritten
+    // instead of allocating a new one, e.g. for the frames of an animation.
+    // When cancelled, the rows that were not rendered are cleared to black.
+    pub fn render_into(&self, world: &mut World, settings: &RenderSettings, image: &mut Canvas) {
+        assert!(
+            image.get_width() == self.hsize && image.get_height() == self.vsize,
+            "Cannot render a {}x{} image into a {}x{} canvas",
+            self.hsize,
+            self.vsize,
+            image.get_width(),
+            image.get_height()
+        );
+
+        self.render_pixels(world, settings, 0..self.hsize, 0..self.vsize, None, image);
+    }
+
+    // Renders only the pixels with x in [x0, x1) and y in [y0, y1), the result
+    // has the size of the region and matches the same pixels of a full render.
+    pub fn render_region(
+        &self,
+        world: &mut World,
+        settings: &RenderSettings,
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+    ) -> Canvas {
+        assert!(
+            x0 <= x1 && x1 <= self.hsize && y0 <= y1 && y1 <= self.vsize,
+            "Region ({}, {}) - ({}, {}) is outside of the {}x{} image",
+            x0,
+            y0,
+            x1,
+            y1,
+            self.hsize,
+            self.vsize
+        );
+
+        let mut image = Canvas::new(x1 - x0, y1 - y0);
+        self.render_pixels(world, settings, x0..x1, y0..y1, None, &mut image);
+
+        image
+    }
+
+    // Besides the image, returns the distance t of the nearest hit seen by the
+    // samples of each pixel, row by row, f64::INFINITY where they all miss.
+    // Hits outside of the clip range are not seen.
+    pub fn render_with_depth(
+        &self,
+        world: &mut World,
+        settings: &RenderSettings,
+    ) -> (Canvas, Vec<f64>) {
+        let mut depth = vec![f64::INFINITY; self.hsize * self.vsize];
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        self.render_pixels(
+            world,
+            settings,
+            0..self.hsize,
+            0..self.vsize,
+            Some(&mut depth),
+            &mut image,
+        );
+
+        (image, depth)
+    }
+
+    // Besides the image, returns how many rays and intersection tests the
+    // render took, e.g. to compare the cost of two scenes
+    pub fn render_with_stats(
+        &self,
+        world: &mut World,
+        settings: &RenderSettings,
+    ) -> (Canvas, RenderStats) {
+        world.start_stats();
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        self.render_pixels(
+            world,
+            settings,
+            0..self.hsize,
+            0..self.vsize,
+            None,
+            &mut image,
+        );
+        let stats = world.take_stats().unwrap_or_default();
+
+        (image, stats)
+    }
+
+    // Sets the world up for the render and puts it back as it was afterwards,
+    // so that the same world can be rendered by cameras with other settings
+    fn render_pixels(
+        &self,
+        world: &mut World,
+        settings: &RenderSettings,
+        columns: Range<usize>,
+        rows: Range<usize>,
+        depth: Option<&mut Vec<f64>>,
+        image: &mut Canvas,
+    ) {
+        let light = self.headlamp.then(|| {
+            world.replace_light(Some(Box::new(PointLight::new(
+                Tuple::white(),
+                self.get_origin(),
+            ))))
+        });
+        let epsilon = world.get_epsilon();
+        let parallel = world.get_parallel();
+        let unlit = world.get_unlit();
+        world.set_epsilon(settings.epsilon);
+        world.set_parallel(settings.parallel);
+        world.set_unlit(self.render_mode == RenderMode::Unlit);
+
+        self.trace_pixels(world, settings, columns, rows, depth, image);
+
+        world.set_epsilon(epsilon);
+        world.set_parallel(parallel);
+        world.set_unlit(unlit);
+        if let Some(light) = light {
+            world.replace_light(light);
+        }
+    }
+
+    fn trace_pixels(
+        &self,
+        world: &mut World,
+        settings: &RenderSettings,
+        columns: Range<usize>,
+        rows: Range<usize>,
+        mut depth: Option<&mut Vec<f64>>,
+        image: &mut Canvas,
+    ) {
         let mut pixels = vec![];
 
-        for y in 0..self.vsize {
-            for x in 0..self.hsize {
+        for y in rows.clone() {
+            for x in columns.clone() {
                 pixels.push((x, y));
             }
         }
 
-        for (x, y) in pixels {
-            let ray = self.ray_for_pixel(x, y);
-            let color = world.color_at(&ray, 5);
+        let origin = self.get_origin();
+        let visible = self.visible_objects(world);
+        let clip = (self.near, self.far);
+
+        for (i, &(x, y)) in pixels.iter().enumerate() {
+            if x == columns.start && settings.cancel.is_cancelled() {
+                // The canvas may hold a previous frame, the rows left out
+                // must not show it
+                for &(x, y) in &pixels[i..] {
+                    let (image_x, image_y) = (x - columns.start, y - rows.start);
+                    image.write_pixel(Tuple::black(), image_x as isize, image_y as isize);
+                    if let Some(depth) = depth.as_mut() {
+                        depth[image_y * columns.len() + image_x] = f64::INFINITY;
+                    }
+                }
+                break;
+            }
+
+            let (image_x, image_y) = (x - columns.start, y - rows.start);
+
+            let mut nearest = f64::INFINITY;
+            let color = if settings.samples <= 1 {
+                let ray = self.ray_for_pixel(&origin, x, y);
+                let (color, t) = world.color_at_visible(&ray, settings.max_depth, &visible, clip);
+                nearest = t.unwrap_or(nearest);
+                color
+            } else {
+                // Every pixel has its own sequence, so that it does not depend
+                // on which other pixels are rendered before it
+                let pixel_index = (y * self.hsize + x) as u64;
+                let mut sampler =
+                    Sampler::new(settings.seed ^ pixel_index.wrapping_mul(0x9E37_79B9_7F4A_7C15));
 
-            image.write_pixel(color, x as isize, y as isize);
+                let mut color = Tuple::black();
+                let offsets = sampler.pixel_offsets(self.sampling_strategy, settings.samples);
+                // Samples are spread evenly over the exposure, without jitter,
+                // so that moving shapes blur without adding noise
+                let times = sampler.exposure_times(settings.samples);
+                for ((dx, dy), time) in offsets.into_iter().zip(times) {
+                    let ray = if self.thin_lens() {
+                        let lens = sampler.sample_aperture(self.aperture_shape);
+                        self.ray_through_lens(x, y, dx, dy, lens)
+                    } else {
+                        self.ray_for_subpixel(&origin, x, y, dx, dy)
+                    };
+                    let ray = ray.with_time(time);
+                    let (sample, t) =
+                        world.color_at_visible(&ray, settings.max_depth, &visible, clip);
+                    color = color + sample;
+                    nearest = nearest.min(t.unwrap_or(f64::INFINITY));
+                }
+                color / settings.samples as f64
+            };
+
+            image.write_pixel(color, image_x as isize, image_y as isize);
+
+            if let Some(depth) = depth.as_mut() {
+                depth[image_y * columns.len() + image_x] = nearest;
+            }
         }
+    }
 
-        image
+    // When set, rendering replaces the light of the world with a white light
+    // placed at the eye, handy to preview scenes without positioning a light
+    pub fn set_headlamp(&mut self, headlamp: bool) {
+        self.headlamp = headlamp;
+    }
+
+    // Hides what is closer than `near` or farther than `far` from the eye, a
+    // near clip cuts the shapes open for cutaway views
+    pub fn set_clip(&mut self, near: f64, far: f64) {
+        assert!(
+            0.0 <= near && near <= far,
+            "Invalid clip range {} - {}",
+            near,
+            far
+        );
+
+        self.near = near;
+        self.far = far;
+    }
+
+    pub fn get_projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        self.render_mode = render_mode;
+    }
+
+    pub fn set_sampling_strategy(&mut self, sampling_strategy: SamplingStrategy) {
+        self.sampling_strategy = sampling_strategy;
+    }
+
+    // Depth of field: what is `focal_distance` in front of the eye is sharp,
+    // the rest blurs more the wider the aperture. Only supersampled renders
+    // spread the rays over the aperture, a single sample looks through its
+    // center. Panoramas always use a pinhole.
+    pub fn set_aperture(&mut self, radius: f64, shape: ApertureShape, focal_distance: f64) {
+        assert!(
+            radius >= 0.0 && focal_distance > 0.0,
+            "Invalid aperture of radius {} focused at {}",
+            radius,
+            focal_distance
+        );
+
+        self.aperture_radius = radius;
+        self.aperture_shape = shape;
+        self.focal_distance = focal_distance;
+    }
+
+    fn thin_lens(&self) -> bool {
+        self.aperture_radius > 0.0 && self.projection == Projection::Perspective
     }
 
     pub fn set_transform(&mut self, transform: Matrix) {
+        self.inverse_transform = transform.invert();
         self.transform = transform;
+        self.look_at = None;
     }
 
-    pub fn precompute_inverse_transform(&mut self) {
-        self.inverse_transform = Some(self.transform.invert());
+    // Same as setting the view transform, but the parameters are kept so that
+    // describe can report them
+    pub fn look_at(&mut self, from: Tuple, to: Tuple, up: Tuple) {
+        let view = ViewTransform::new(from, to, up);
+        self.set_transform(view.matrix());
+        self.look_at = Some(view);
+    }
+
+    pub fn describe(&self) -> CameraDescription {
+        CameraDescription {
+            hsize: self.hsize,
+            vsize: self.vsize,
+            field_of_view: self.field_of_view,
+            look_at: self.look_at.clone(),
+        }
     }
 }
 
+// Renders `frames` images of the same world. Every frame starts from a copy of
+// the base camera that `update` can move around, e.g. for a turntable.
+pub fn render_frames<F>(
+    world: &mut World,
+    camera: &Camera,
+    settings: &RenderSettings,
+    frames: usize,
+    mut update: F,
+) -> Vec<Canvas>
+where
+    F: FnMut(usize, &mut Camera),
+{
+    (0..frames)
+        .map(|frame| {
+            let mut frame_camera = camera.clone();
+            update(frame, &mut frame_camera);
+            frame_camera.render(world, settings)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
 
-    use std::f64::consts::PI;
+    use std::{any::Any, f64::consts::PI};
+
+    use std::sync::{Arc, Mutex};
 
-    use crate::{core::transformations::Transformation, margin::Margin};
+    use crate::{
+        core::transformations::{Animated, Transformation},
+        margin::Margin,
+        materials::Material,
+        shapes::cubes::Cube,
+        shapes::planes::Plane,
+        shapes::spheres::Sphere,
+        shapes::{MockPolygon, Shape},
+    };
     use float_cmp::ApproxEq;
 
     use super::*;
@@ -107,7 +734,7 @@ mod tests {
 
         assert_eq!(c.hsize, hsize);
         assert_eq!(c.vsize, vsize);
-        assert_eq!(c._field_of_view, field_of_view);
+        assert_eq!(c.get_field_of_view(), field_of_view);
         assert_eq!(c.transform, Matrix::identity(4));
     }
 
@@ -129,7 +756,7 @@ mod tests {
     fn build_a_ray_through_the_center_of_the_canvas() {
         let c = Camera::new(201, 101, PI / 2.0);
 
-        let r: Ray = c.ray_for_pixel(100, 50);
+        let r: Ray = c.ray_for_pixel(&c.get_origin(), 100, 50);
         assert_eq!(r.get_origin(), Tuple::new_point(0.0, 0.0, 0.0));
         assert_eq!(r.get_direction(), Tuple::new_vector(0.0, 0.0, -1.0));
     }
@@ -138,7 +765,7 @@ mod tests {
     fn build_a_ray_through_a_corner_of_the_canvas() {
         let c = Camera::new(201, 101, PI / 2.0);
 
-        let r: Ray = c.ray_for_pixel(0, 0);
+        let r: Ray = c.ray_for_pixel(&c.get_origin(), 0, 0);
         assert_eq!(r.get_origin(), Tuple::new_point(0.0, 0.0, 0.0));
 
         assert_eq!(
@@ -150,9 +777,10 @@ mod tests {
     #[test]
     fn build_a_ray_when_the_camera_is_transformed() {
         let mut c = Camera::new(201, 101, PI / 2.0);
-        c.transform =
-            Transformation::rotation_y(PI / 4.0) * Transformation::translation(0.0, -2.0, 5.0);
-        let r: Ray = c.ray_for_pixel(100, 50);
+        c.set_transform(
+            Transformation::rotation_y(PI / 4.0) * Transformation::translation(0.0, -2.0, 5.0),
+        );
+        let r: Ray = c.ray_for_pixel(&c.get_origin(), 100, 50);
         assert_eq!(r.get_origin(), Tuple::new_point(0.0, 2.0, -5.0));
 
         assert_eq!(
@@ -161,6 +789,79 @@ mod tests {
         );
     }
 
+    #[test]
+    fn equirectangular_rays_wrap_around_the_eye() {
+        let mut c = Camera::new(360, 180, PI / 2.0);
+        c.set_projection(Projection::Equirectangular);
+        let origin = c.get_origin();
+        let direction = |x: usize, y: usize| c.ray_for_subpixel(&origin, x, y, 0.0, 0.0);
+
+        assert_eq!(
+            direction(180, 90).get_direction(),
+            Tuple::new_vector(0.0, 0.0, -1.0)
+        );
+        assert_eq!(
+            direction(90, 90).get_direction(),
+            Tuple::new_vector(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            direction(270, 90).get_direction(),
+            Tuple::new_vector(-1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            direction(0, 90).get_direction(),
+            Tuple::new_vector(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            direction(180, 0).get_direction(),
+            Tuple::new_vector(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn equirectangular_panoramas_see_behind_the_eye() {
+        let mut w = World::new();
+        w.add_shape(Shape::default(Arc::new(Mutex::new(Sphere::default()))));
+        let mut c = Camera::new(8, 4, PI / 2.0);
+        c.set_transform(Transformation::translation(0.0, 0.0, 5.0));
+        c.set_projection(Projection::Equirectangular);
+
+        assert_eq!(c.visible_objects(&w), vec![true]);
+        assert_eq!(c.project(&Tuple::new_point(0.0, 0.0, -10.0)), None);
+    }
+
+    #[test]
+    fn an_unlit_render_shows_the_bare_color_of_the_materials() {
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere
+            .material_mut()
+            .set_color(Tuple::new_color(1.0, 0.0, 0.0));
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+        w.add_shape(sphere);
+
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Transformation::translation(0.0, 0.0, -2.5));
+        c.set_render_mode(RenderMode::Unlit);
+        let image = c.render(&mut w, &RenderSettings::default());
+
+        let mut hits = 0;
+        for y in 0..11 {
+            for x in 0..11 {
+                if c.object_at_pixel(&w, x, y).is_some() {
+                    hits += 1;
+                    assert_eq!(image.pixel_at(x, y), Tuple::new_color(1.0, 0.0, 0.0));
+                } else {
+                    assert_eq!(image.pixel_at(x, y), Tuple::black());
+                }
+            }
+        }
+        assert!(hits > 0 && hits < 121);
+    }
+
     #[test]
     fn rendering_a_world_with_a_camera() {
         let mut w = World::default();
@@ -170,8 +871,8 @@ mod tests {
         let to = Tuple::new_point(0.0, 0.0, 0.0);
         let up = Tuple::new_vector(0.0, 1.0, 0.0);
 
-        c.transform = Transformation::view_transform(from, to, up);
-        let image: Canvas = c.render(&mut w);
+        c.set_transform(Transformation::view_transform(from, to, up));
+        let image: Canvas = c.render(&mut w, &RenderSettings::default());
 
         assert_eq!(
             image.pixel_at(5, 5),
@@ -182,4 +883,734 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn a_near_clip_reveals_the_inside_of_a_sphere() {
+        let mut w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+
+        // The outer sphere is hit at t = 4 and the inner one at t = 4.5, in
+        // the shadow of the outer one
+        c.set_clip(4.2, f64::INFINITY);
+        let image = c.render(&mut w, &RenderSettings::default());
+        assert_eq!(image.pixel_at(5, 5), Tuple::new_color(0.1, 0.1, 0.1));
+
+        c.set_clip(0.0, 3.0);
+        let image = c.render(&mut w, &RenderSettings::default());
+        assert_eq!(image.pixel_at(5, 5), Tuple::black());
+    }
+
+    #[test]
+    fn rendering_into_a_canvas_overwrites_every_pixel() {
+        let mut w = World::default();
+        let mut c = Camera::new(5, 4, PI / 2.0);
+        let mut image = Canvas::new(5, 4);
+        for y in 0..4 {
+            for x in 0..5 {
+                image.write_pixel(Tuple::new_color(1.0, 0.0, 0.0), x, y);
+            }
+        }
+
+        // A first frame from the side, then the one to compare
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(-5.0, 0.0, 0.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+        c.render_into(&mut w, &RenderSettings::default(), &mut image);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 1.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+        c.render_into(&mut w, &RenderSettings::default(), &mut image);
+
+        let fresh = c.render(&mut w, &RenderSettings::default());
+        assert_eq!(image.to_ppm(), fresh.to_ppm());
+
+        // A cancelled frame leaves black what it did not render
+        let cancelled = RenderSettings::default();
+        cancelled.cancel.cancel();
+        c.render_into(&mut w, &cancelled, &mut image);
+        assert_eq!(image.to_ppm(), Canvas::new(5, 4).to_ppm());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot render a 5x4 image into a 4x5 canvas")]
+    fn rendering_into_a_canvas_of_another_size_panics() {
+        let c = Camera::new(5, 4, PI / 2.0);
+
+        c.render_into(
+            &mut World::default(),
+            &RenderSettings::default(),
+            &mut Canvas::new(4, 5),
+        );
+    }
+
+    #[test]
+    fn the_rays_through_a_lens_meet_on_the_plane_in_focus() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.set_transform(
+            Transformation::rotation_y(PI / 4.0) * Transformation::translation(0.0, -2.0, 5.0),
+        );
+        c.set_aperture(0.5, ApertureShape::Disk, 4.0);
+
+        let pinhole = c.ray_for_subpixel(&c.get_origin(), 30, 70, 0.2, 0.7);
+        assert_eq!(c.ray_through_lens(30, 70, 0.2, 0.7, (0.0, 0.0)), pinhole);
+
+        // The pinhole ray travels 4 units along the view axis, -z in camera space
+        let view_axis = -(&c.transform * &pinhole.get_direction()).z;
+        let focus = pinhole.position(4.0 / view_axis);
+        for lens in [(1.0, 0.0), (0.0, -1.0), (-0.3, 0.6)] {
+            let ray = c.ray_through_lens(30, 70, 0.2, 0.7, lens);
+            let to_focus = &focus - &ray.get_origin();
+            assert_eq!(ray.get_direction(), to_focus.normalize());
+            assert_ne!(ray.get_origin(), c.get_origin());
+        }
+    }
+
+    #[test]
+    fn a_wide_aperture_blurs_what_is_out_of_focus() {
+        let mut w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+        let settings = RenderSettings {
+            samples: 16,
+            ..RenderSettings::default()
+        };
+        let pinhole = c.render(&mut w, &settings);
+
+        // A tiny aperture keeps the image, a wide one focused far behind the
+        // spheres blurs their edges
+        c.set_aperture(1e-9, ApertureShape::Polygon(6), 4.0);
+        let sharp = c.render(&mut w, &settings);
+        c.set_aperture(0.5, ApertureShape::Polygon(6), 50.0);
+        let blurred = c.render(&mut w, &settings);
+
+        let difference = |a: &Canvas, b: &Canvas| {
+            (0..11)
+                .flat_map(|y| (0..11).map(move |x| (x, y)))
+                .map(|(x, y)| (a.pixel_at(x, y) - b.pixel_at(x, y)).magnitude())
+                .sum::<f64>()
+        };
+        assert!(difference(&pinhole, &sharp) < 1e-6);
+        assert!(difference(&pinhole, &blurred) > 0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid aperture")]
+    fn an_aperture_needs_a_positive_focal_distance() {
+        Camera::new(11, 11, PI / 2.0).set_aperture(0.1, ApertureShape::Disk, 0.0);
+    }
+
+    fn render_anti_aliased_default_world(seed: u64) -> String {
+        let mut w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+        let settings = RenderSettings {
+            samples: 8,
+            seed,
+            ..RenderSettings::default()
+        };
+
+        c.render(&mut w, &settings).base64()
+    }
+
+    #[test]
+    fn renders_with_the_same_seed_are_identical() {
+        assert_eq!(
+            render_anti_aliased_default_world(3),
+            render_anti_aliased_default_world(3)
+        );
+    }
+
+    #[test]
+    fn renders_with_different_seeds_differ_on_anti_aliased_edges() {
+        assert_ne!(
+            render_anti_aliased_default_world(3),
+            render_anti_aliased_default_world(4)
+        );
+    }
+
+    #[test]
+    fn rendering_frames_of_a_rotating_camera() {
+        let mut w = World::default();
+        let c = Camera::new(11, 11, PI / 2.0);
+
+        let frames = render_frames(
+            &mut w,
+            &c,
+            &RenderSettings::default(),
+            4,
+            |frame, camera| {
+                let angle = frame as f64 * PI / 2.0;
+                camera.set_transform(Transformation::view_transform(
+                    Tuple::new_point(-5.0 * angle.sin(), 1.0, -5.0 * angle.cos()),
+                    Tuple::new_point(0.0, 0.0, 0.0),
+                    Tuple::new_vector(0.0, 1.0, 0.0),
+                ));
+            },
+        );
+
+        assert_eq!(frames.len(), 4);
+
+        let images: Vec<String> = frames.iter().map(|f| f.base64()).collect();
+        for i in 0..images.len() {
+            for j in (i + 1)..images.len() {
+                assert_ne!(images[i], images[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn the_headlamp_is_placed_at_the_camera_origin() {
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        let from = Tuple::new_point(1.0, 2.0, -5.0);
+        c.set_transform(Transformation::view_transform(
+            from.clone(),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+
+        let mut lit = World::default();
+        lit.set_light(PointLight::new(Tuple::white(), c.get_origin()));
+        let expected = c.render(&mut lit, &RenderSettings::default());
+
+        c.set_headlamp(true);
+        let image = c.render(&mut World::default(), &RenderSettings::default());
+
+        assert_eq!(image.base64(), expected.base64());
+    }
+
+    #[test]
+    fn rendering_leaves_the_world_unchanged() {
+        let mut w = World::default();
+        w.set_epsilon(1e-3);
+        w.set_parallel(false);
+        let light = w.get_light_ref().get_intensity();
+        let position = (w.get_light_ref() as &dyn Any)
+            .downcast_ref::<PointLight>()
+            .unwrap()
+            .get_position_ref()
+            .clone();
+
+        let mut c = Camera::new(5, 5, PI / 2.0);
+        c.set_headlamp(true);
+        c.set_render_mode(RenderMode::Unlit);
+        let settings = RenderSettings {
+            epsilon: 1e-6,
+            parallel: true,
+            ..RenderSettings::default()
+        };
+        c.render(&mut w, &settings);
+        c.render_with_depth(&mut w, &settings);
+
+        assert_eq!(w.get_epsilon(), 1e-3);
+        assert!(!w.get_parallel());
+        assert!(!w.get_unlit());
+        assert_eq!(w.get_light_ref().get_intensity(), light);
+        let light = (w.get_light_ref() as &dyn Any)
+            .downcast_ref::<PointLight>()
+            .unwrap();
+        assert_eq!(*light.get_position_ref(), position);
+    }
+
+    #[test]
+    fn changing_the_field_of_view_recomputes_the_pixel_size() {
+        let mut c = Camera::new(200, 125, 0.2);
+        let pixel_size = c.pixel_size;
+
+        c.set_field_of_view(0.1);
+
+        assert_eq!(c.get_field_of_view(), 0.1);
+        assert_eq!(c.pixel_size, Camera::new(200, 125, 0.1).pixel_size);
+        assert!((c.pixel_size / pixel_size - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn rendering_with_depth_records_the_nearest_hit() {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+        let mut wall = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        wall.set_transformation(
+            Transformation::translation(0.0, 0.0, 5.0) * Transformation::rotation_x(PI / 2.0),
+        );
+        let ball = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        w.add_shapes(&[wall, ball]);
+
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+
+        let (image, depth) = c.render_with_depth(&mut w, &RenderSettings::default());
+
+        let center = depth[5 * 11 + 5];
+        let corner = depth[0];
+        assert_eq!(depth.len(), 11 * 11);
+        assert!(center.approx_eq(4.0, Margin::default_f64()));
+        assert!(corner > center);
+        assert!(corner.is_finite());
+        assert_eq!(
+            image.pixel_at(5, 5),
+            c.render(&mut w, &RenderSettings::default()).pixel_at(5, 5)
+        );
+
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, -10.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+        let (_, depth) = c.render_with_depth(&mut w, &RenderSettings::default());
+
+        assert!(depth.iter().all(|t| *t == f64::INFINITY));
+    }
+
+    #[test]
+    fn the_depth_and_the_picked_object_follow_the_clip() {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+        let mut wall = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        wall.set_transformation(
+            Transformation::translation(0.0, 0.0, 5.0) * Transformation::rotation_x(PI / 2.0),
+        );
+        let ball = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        w.add_shapes(&[wall, ball]);
+
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+        let center = |c: &Camera, w: &mut World, settings: &RenderSettings| {
+            c.render_with_depth(w, settings).1[5 * 11 + 5]
+        };
+
+        // The near clip opens the ball, the far side is seen from inside
+        c.set_clip(4.5, f64::INFINITY);
+        assert!(
+            center(&c, &mut w, &RenderSettings::default()).approx_eq(6.0, Margin::default_f64())
+        );
+        assert_eq!(c.object_at_pixel(&w, 5, 5), Some(1));
+
+        // Past the far clip nothing is seen
+        c.set_clip(0.0, 3.0);
+        assert_eq!(
+            center(&c, &mut w, &RenderSettings::default()),
+            f64::INFINITY
+        );
+        assert_eq!(c.object_at_pixel(&w, 5, 5), None);
+
+        // Supersampled pixels keep the nearest hit of their samples
+        c.set_clip(0.0, f64::INFINITY);
+        let settings = RenderSettings {
+            samples: 4,
+            ..RenderSettings::default()
+        };
+        let t = center(&c, &mut w, &settings);
+        assert!((4.0 - 1e-9..4.1).contains(&t), "{t}");
+    }
+
+    #[test]
+    fn setting_the_transform_stores_its_inverse_once() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        let transform =
+            Transformation::rotation_y(PI / 4.0) * Transformation::translation(0.0, -2.0, 5.0);
+
+        c.set_transform(transform.clone());
+
+        assert_eq!(c.inverse_transform, transform.invert());
+        assert_eq!(c.get_origin(), Tuple::new_point(0.0, 2.0, -5.0));
+
+        // Every ray of the image starts from the shared origin
+        let origin = c.get_origin();
+        for (x, y) in [(0, 0), (100, 50), (200, 100)] {
+            assert_eq!(c.ray_for_pixel(&origin, x, y).get_origin(), origin);
+        }
+    }
+
+    #[test]
+    fn describing_a_camera_placed_with_look_at() {
+        let mut c = Camera::new(160, 120, PI / 3.0);
+        let from = Tuple::new_point(1.0, 3.0, 2.0);
+        let to = Tuple::new_point(4.0, -2.0, 8.0);
+        let up = Tuple::new_vector(1.0, 1.0, 0.0);
+
+        c.look_at(from.clone(), to.clone(), up.clone());
+
+        assert_eq!(
+            c.describe(),
+            CameraDescription {
+                hsize: 160,
+                vsize: 120,
+                field_of_view: PI / 3.0,
+                look_at: Some(ViewTransform::new(from, to, up)),
+            }
+        );
+
+        // A transform set directly has no look at parameters to report
+        c.set_transform(Transformation::translation(0.0, 0.0, -5.0));
+        assert_eq!(c.describe().look_at, None);
+    }
+
+    #[test]
+    fn rendering_the_default_world_with_statistics() {
+        let mut w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+
+        let (image, stats) = c.render_with_stats(&mut w, &RenderSettings::default());
+
+        assert_eq!(
+            image.pixel_at(5, 5),
+            c.render(&mut w, &RenderSettings::default()).pixel_at(5, 5)
+        );
+        // At least one camera ray per pixel, each one tested against both spheres
+        assert!(stats.get_rays() >= 121);
+        assert_eq!(stats.get_intersection_tests(), stats.get_rays() * 2);
+        assert_eq!(stats.get_max_depth(), 0);
+        assert!(w.take_stats().is_none());
+    }
+
+    #[test]
+    fn statistics_count_the_shapes_each_ray_is_tested_against() {
+        let mut w = World::default();
+        w.shape_mut(1)
+            .unwrap()
+            .material_mut()
+            .set_casts_shadow(false);
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+
+        let (_, stats) = c.render_with_stats(&mut w, &RenderSettings::default());
+
+        // Camera rays test both spheres, shadow rays skip the inner one
+        let camera_rays = 121;
+        let shadow_rays = stats.get_rays() - camera_rays;
+        assert!(shadow_rays > 0);
+        assert_eq!(
+            stats.get_intersection_tests(),
+            camera_rays * 2 + shadow_rays
+        );
+    }
+
+    #[test]
+    fn statistics_report_the_depth_of_reflected_rays() {
+        let mut w = World::default();
+        let mut floor = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        floor.set_transformation(Transformation::translation(0.0, -1.0, 0.0));
+        let mut material = Material::default();
+        material.set_reflective(0.5);
+        floor.set_material(material);
+        w.add_shape(floor);
+
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+
+        let (_, stats) = c.render_with_stats(&mut w, &RenderSettings::default());
+
+        assert!(stats.get_max_depth() >= 1);
+        assert_eq!(stats.get_intersection_tests(), stats.get_rays() * 3);
+    }
+
+    #[test]
+    fn rough_mirrors_facing_each_other_fan_out_once() {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(0.0, 5.0, 0.0),
+        ));
+        for z in [-2.0, 2.0] {
+            let mut mirror = Shape::default(Arc::new(Mutex::new(Plane::new())));
+            mirror.set_transformation(
+                Transformation::translation(0.0, 0.0, z) * Transformation::rotation_x(PI / 2.0),
+            );
+            let mut material = Material::mirror();
+            material.set_roughness(0.05);
+            mirror.set_material(material);
+            w.add_shape(mirror);
+        }
+
+        let c = Camera::new(1, 1, PI / 2.0);
+        let (_, stats) = c.render_with_stats(&mut w, &RenderSettings::default());
+
+        // The camera ray and its 16 reflections, each one followed by a chain
+        // of single bounces down to the depth limit, with a shadow ray per hit.
+        // Fanning out at every bounce would cast about 16^5 rays.
+        assert_eq!(stats.get_max_depth(), 5);
+        assert!(stats.get_rays() <= 2 + 16 * 5 * 2);
+    }
+
+    #[test]
+    fn a_lower_depth_limit_cuts_the_reflections_between_mirrors() {
+        let mut w = World::default();
+        let mut floor = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        floor.set_transformation(Transformation::translation(0.0, -1.0, 0.0));
+        floor.set_material(Material::mirror());
+        w.add_shape(floor);
+        w.shape_mut(0)
+            .unwrap()
+            .set_material(Material::metal(Tuple::new_color(0.8, 1.0, 0.6)));
+
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.look_at(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+
+        let shallow = RenderSettings {
+            max_depth: 1,
+            ..RenderSettings::default()
+        };
+
+        assert_ne!(
+            c.render(&mut w, &shallow).base64(),
+            c.render(&mut w, &RenderSettings::default()).base64()
+        );
+    }
+
+    #[test]
+    fn a_moving_sphere_blurs_across_the_pixels_it_sweeps() {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(0.0, 0.0, -10.0),
+        ));
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere.set_transformation(
+            &Transformation::translation(-1.0, 0.0, 0.0) * &Transformation::scaling(0.5, 0.5, 0.5),
+        );
+        let mut material = Material::default();
+        material.set_ambient(1.0);
+        material.set_diffuse(0.0);
+        material.set_specular(0.0);
+        sphere.set_material(material);
+        sphere.set_motion(Animated::translation(2.0, 0.0, 0.0));
+        w.add_shape(sphere);
+
+        let mut c = Camera::new(21, 1, PI / 3.0);
+        c.look_at(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        let still = c.render(&mut w, &RenderSettings::default());
+        let blurred = c.render(
+            &mut w,
+            &RenderSettings {
+                samples: 16,
+                ..RenderSettings::default()
+            },
+        );
+
+        let covered = |image: &Canvas| (0..21).filter(|x| image.pixel_at(*x, 0).x > 0.0).count();
+        let partial = (0..21)
+            .filter(|x| (0.0..1.0).contains(&blurred.pixel_at(*x, 0).x))
+            .filter(|x| blurred.pixel_at(*x, 0).x > 0.0)
+            .count();
+
+        // A single sample sees the sphere where it starts, sharp
+        assert_eq!(still.pixel_at(5, 0), Tuple::white());
+        assert_eq!(still.pixel_at(15, 0), Tuple::black());
+        assert!(covered(&blurred) > 2 * covered(&still));
+        assert!(partial > covered(&still));
+    }
+
+    #[test]
+    fn the_bounds_overlay_draws_the_corners_of_a_cube() {
+        let mut w = World::new();
+        w.add_shape(Shape::default(Arc::new(Mutex::new(Cube::new()))));
+
+        let mut c = Camera::new(41, 41, PI / 2.0);
+        c.look_at(
+            Tuple::new_point(3.0, 2.0, -6.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+
+        let mut canvas = Canvas::new(41, 41);
+        let red = Tuple::new_color(1.0, 0.0, 0.0);
+        c.draw_bounds_overlay(&w, &mut canvas, &red);
+
+        let corners = w.objects().next().unwrap().bounds().unwrap().corners();
+        for corner in corners {
+            let (x, y) = c.project(&corner).unwrap();
+            assert_eq!(canvas.pixel_at(x as usize, y as usize), red);
+        }
+        assert_eq!(canvas.pixel_at(0, 0), Tuple::black());
+        assert_eq!(canvas.pixel_at(20, 20), Tuple::black());
+    }
+
+    #[test]
+    fn a_region_render_matches_the_same_pixels_of_a_full_render() {
+        let mut w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+        let settings = RenderSettings {
+            samples: 4,
+            seed: 9,
+            ..RenderSettings::default()
+        };
+
+        let full = c.render(&mut w, &settings);
+        let region = c.render_region(&mut w, &settings, 3, 4, 8, 6);
+
+        assert_eq!(region.pixel_at(0, 0), full.pixel_at(3, 4));
+        assert_eq!(region.pixel_at(2, 1), full.pixel_at(5, 5));
+        assert_eq!(region.pixel_at(4, 1), full.pixel_at(7, 5));
+    }
+
+    #[test]
+    fn objects_outside_the_view_are_culled() {
+        let mut w = World::default();
+        let mut behind = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        behind.set_transformation(Transformation::translation(0.0, 0.0, -10.0));
+        w.add_shape(behind);
+        w.add_shape(Shape::default(Arc::new(Mutex::new(Plane::new()))));
+
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.look_at(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+
+        // The plane is unbounded and is never culled
+        assert_eq!(c.visible_objects(&w), vec![true, true, false, true]);
+
+        let image = c.render(&mut w, &RenderSettings::default());
+        let origin = c.get_origin();
+        for (x, y) in [(0, 0), (5, 5), (10, 3)] {
+            let ray = c.ray_for_pixel(&origin, x, y);
+            assert_eq!(image.pixel_at(x, y), w.color_at(&ray, 5));
+        }
+    }
+
+    #[test]
+    fn picking_the_object_seen_through_a_pixel() {
+        let mut w = World::new();
+        w.add_shape(Shape::default(Arc::new(Mutex::new(Plane::new()))));
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere.set_transformation(Transformation::translation(0.0, 2.0, 0.0));
+        let sphere_id = w.add_shape(sphere);
+
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 2.0, -5.0),
+            Tuple::new_point(0.0, 2.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+
+        assert_eq!(c.object_at_pixel(&w, 5, 5), Some(sphere_id));
+        assert_eq!(c.object_at_pixel(&w, 5, 10), Some(0));
+        assert_eq!(c.object_at_pixel(&w, 0, 0), None);
+    }
+
+    #[test]
+    fn a_cancelled_render_leaves_the_remaining_rows_black() {
+        let mut c = Camera::new(11, 11, PI / 4.0);
+        c.set_transform(Transformation::view_transform(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        ));
+        let full = c.render(&mut World::default(), &RenderSettings::default());
+
+        // Cancels as soon as a ray points below the center of the image, that
+        // is while rendering row 6
+        let settings = RenderSettings::default();
+        let token = settings.cancel.clone();
+        let mut tripwire = MockPolygon::default();
+        tripwire.expect_bounds().returning(|| None);
+        tripwire.expect_intersect_faces().returning(move |ray| {
+            if ray.get_direction().y < -1e-9 {
+                token.cancel();
+            }
+            vec![]
+        });
+        let mut w = World::default();
+        w.add_shape(Shape::default(Arc::new(Mutex::new(tripwire))));
+
+        let partial = c.render(&mut w, &settings);
+
+        assert!(settings.cancel.is_cancelled());
+        for y in 0..11 {
+            for x in 0..11 {
+                if y <= 6 {
+                    assert_eq!(partial.pixel_at(x, y), full.pixel_at(x, y));
+                } else {
+                    assert_eq!(partial.pixel_at(x, y), Tuple::black());
+                }
+            }
+        }
+        assert!((7..11).any(|y| full.pixel_at(5, y) != Tuple::black()));
+    }
+
+    #[test]
+    fn a_new_token_is_not_cancelled_and_clones_share_it() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+
+        assert!(!token.is_cancelled());
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert_ne!(token, CancelToken::new());
+    }
+
+    #[test]
+    fn a_token_with_a_timeout_cancels_itself_past_the_deadline() {
+        assert!(!CancelToken::with_timeout(Duration::from_secs(60)).is_cancelled());
+
+        let token = CancelToken::with_timeout(Duration::ZERO);
+        assert!(token.is_cancelled());
+        assert!(token.clone().is_cancelled());
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of the 11x11 image")]
+    fn a_region_must_lie_inside_the_image() {
+        let mut w = World::default();
+        let c = Camera::new(11, 11, PI / 2.0);
+
+        c.render_region(&mut w, &RenderSettings::default(), 5, 5, 12, 6);
+    }
 }
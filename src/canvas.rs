@@ -1,54 +1,428 @@
-use std::io::Cursor;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Cursor, Write},
+    path::Path,
+};
 
 use image::{ImageBuffer, ImageOutputFormat, Rgb, RgbImage};
 
 use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
 
 use crate::core::tuples::Tuple;
 
+// Pixels row by row in a single allocation
+enum Pixels {
+    Full(Vec<Tuple>),
+    // Less than half the memory of Full, for large renders
+    Compact(Vec<[f32; 3]>),
+}
+
+// Layout of Canvas::to_json, pixels row by row
+#[derive(Deserialize, Serialize)]
+struct CanvasJson {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f64; 3]>,
+}
+
 pub struct Canvas {
     width: usize,
     height: usize,
-    // TODO: This has bad data locality since the column vectors could be scattered
-    // accross the heap. Some library to better handle this could already exists. Is needed
-    // to evaluate the alternatives. https://www.reddit.com/r/rust/comments/nfoi4j/how_can_i_create_a_2d_array/
-    state: Vec<Vec<Tuple>>,
+    pixels: Pixels,
+    // Sum of the weights accumulated in each pixel, row by row. Only allocated
+    // by the first call to accumulate, most canvases never need it.
+    weights: Option<Vec<f64>>,
 }
 
 impl Canvas {
     pub fn new(width: usize, height: usize) -> Canvas {
-        let state = vec![vec![Tuple::black(); width]; height];
         Canvas {
             width,
             height,
-            state,
+            pixels: Pixels::Full(vec![Tuple::black(); width * height]),
+            weights: None,
+        }
+    }
+
+    // Same interface as new, but the colors are stored as three f32 values.
+    // The precision is more than enough for the final image.
+    pub fn new_compact(width: usize, height: usize) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: Pixels::Compact(vec![[0.0; 3]; width * height]),
+            weights: None,
         }
     }
 
-    #[cfg(test)]
+    // Decodes the 8 bit sRGB pixels of an image to linear colors, the inverse
+    // of saving a canvas after encode_srgb
+    pub fn from_image(img: &RgbImage) -> Canvas {
+        let mut canvas = Canvas::new(img.width() as usize, img.height() as usize);
+        for (x, y, Rgb([r, g, b])) in img.enumerate_pixels() {
+            let color = Tuple::from_srgb(*r as f64 / 255.0, *g as f64 / 255.0, *b as f64 / 255.0);
+            canvas.set_pixel(x as usize, y as usize, color);
+        }
+
+        canvas
+    }
+
+    // Empty canvas with the same storage as this one
+    fn blank(&self, width: usize, height: usize) -> Canvas {
+        match self.pixels {
+            Pixels::Full(_) => Canvas::new(width, height),
+            Pixels::Compact(_) => Canvas::new_compact(width, height),
+        }
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    // Position of the pixel in the storage, one row after the other
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "Pixel ({}, {}) is outside of the {}x{} canvas",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+
+        y * self.width + x
+    }
+
     pub fn pixel_at(&self, x: usize, y: usize) -> Tuple {
-        self.state[y][x].clone()
+        let index = self.index(x, y);
+
+        match &self.pixels {
+            Pixels::Full(pixels) => pixels[index].clone(),
+            Pixels::Compact(pixels) => {
+                let [r, g, b] = pixels[index];
+                Tuple::new_color(r as f64, g as f64, b as f64)
+            }
+        }
     }
 
     pub fn write_pixel(&mut self, color: Tuple, x: isize, y: isize) {
         if y < self.height as isize && y >= 0 && x < self.width as isize && x >= 0 {
-            self.state[y as usize][x as usize] = color
+            self.set_pixel(x as usize, y as usize, color)
+        }
+    }
+
+    // Bresenham line between two pixels, both ends included. Pixels outside
+    // of the canvas are skipped.
+    pub fn draw_line(&mut self, from: (isize, isize), to: (isize, isize), color: &Tuple) {
+        let (mut x, mut y) = from;
+        let dx = (to.0 - x).abs();
+        let dy = -(to.1 - y).abs();
+        let step_x = if x < to.0 { 1 } else { -1 };
+        let step_y = if y < to.1 { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        loop {
+            self.write_pixel(color.clone(), x, y);
+            if (x, y) == to {
+                break;
+            }
+
+            let double_error = 2 * error;
+            if double_error >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if double_error <= dx {
+                error += dx;
+                y += step_y;
+            }
         }
     }
 
+    fn set_pixel(&mut self, x: usize, y: usize, color: Tuple) {
+        let index = self.index(x, y);
+
+        match &mut self.pixels {
+            Pixels::Full(pixels) => pixels[index] = color,
+            Pixels::Compact(pixels) => {
+                pixels[index] = [color.x as f32, color.y as f32, color.z as f32]
+            }
+        }
+    }
+
+    fn map_pixels<F>(&mut self, operation: F)
+    where
+        F: Fn(usize, usize, Tuple) -> Tuple,
+    {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let color = operation(x, y, self.pixel_at(x, y));
+                self.set_pixel(x, y, color);
+            }
+        }
+    }
+
+    // Adds a weighted sample to the pixel containing (x, y), samples falling
+    // outside of the canvas are dropped. See normalize_by_weights.
+    pub fn accumulate(&mut self, color: Tuple, x: f64, y: f64, weight: f64) {
+        let (x, y) = (x.floor(), y.floor());
+        if x < 0.0 || y < 0.0 || x >= self.width as f64 || y >= self.height as f64 {
+            return;
+        }
+
+        let (x, y) = (x as usize, y as usize);
+        let sum = &self.pixel_at(x, y) + &(color * weight);
+        self.set_pixel(x, y, sum);
+        let (size, index) = (self.width * self.height, self.index(x, y));
+        self.weights.get_or_insert_with(|| vec![0.0; size])[index] += weight;
+    }
+
+    // Turns the accumulated sums into weighted averages. Pixels that received
+    // no weight are left as they are, the weights start again from zero.
+    pub fn normalize_by_weights(&mut self) {
+        let Some(weights) = self.weights.take() else {
+            return;
+        };
+        let width = self.width;
+        self.map_pixels(|x, y, pixel| {
+            let weight = weights[y * width + x];
+            if weight > 0.0 {
+                pixel / weight
+            } else {
+                pixel
+            }
+        });
+    }
+
+    // Multiplies every pixel by 2^stops, like the exposure of a camera. Apply
+    // it before tone mapping, values above 1 are kept.
+    pub fn apply_exposure(&mut self, stops: f64) {
+        let factor = 2.0_f64.powf(stops);
+        self.map_pixels(|_, _, pixel| pixel * factor);
+    }
+
+    // Reinhard operator, compresses unbounded intensities into [0, 1) instead of
+    // letting the final clamp flatten every bright highlight to white.
+    pub fn tone_map_reinhard(&mut self) {
+        self.map_pixels(|_, _, pixel| {
+            Tuple::new_color(
+                pixel.x / (1.0 + pixel.x),
+                pixel.y / (1.0 + pixel.y),
+                pixel.z / (1.0 + pixel.z),
+            )
+        });
+    }
+
+    // Encodes every pixel with the sRGB transfer function, the renderer works
+    // with linear colors while image viewers expect sRGB ones. Apply it last,
+    // after tone mapping.
+    pub fn encode_srgb(&mut self) {
+        self.map_pixels(|_, _, pixel| pixel.to_srgb());
+    }
+
+    // Averages each factor x factor block into a single pixel. Blocks on the
+    // right and bottom borders are clamped to the canvas when its size is not
+    // a multiple of the factor.
+    pub fn downscale(&self, factor: usize) -> Canvas {
+        assert!(factor > 0, "Downscale factor must be positive");
+
+        let width = self.width.div_ceil(factor);
+        let height = self.height.div_ceil(factor);
+        let mut canvas = self.blank(width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let rows = (y * factor)..((y + 1) * factor).min(self.height);
+                let columns = (x * factor)..((x + 1) * factor).min(self.width);
+                let count = rows.len() * columns.len();
+
+                let mut color = Tuple::black();
+                for row in rows {
+                    for column in columns.clone() {
+                        color = color + self.pixel_at(column, row);
+                    }
+                }
+
+                canvas.write_pixel(color / count as f64, x as isize, y as isize);
+            }
+        }
+
+        canvas
+    }
+
+    // Sum of the two canvases, e.g. to layer an emissive pass over a render
+    pub fn add(&self, other: &Canvas) -> Canvas {
+        self.combine(other, |a, b| a + b)
+    }
+
+    pub fn multiply(&self, other: &Canvas) -> Canvas {
+        self.combine(other, |a, b| a.hadamard_product(b))
+    }
+
+    // Lays this canvas over `other`, alpha is the opacity of this canvas
+    pub fn over(&self, other: &Canvas, alpha: f64) -> Canvas {
+        self.combine(other, |a, b| a * alpha + b * (1.0 - alpha))
+    }
+
+    fn combine<F>(&self, other: &Canvas, operation: F) -> Canvas
+    where
+        F: Fn(&Tuple, &Tuple) -> Tuple,
+    {
+        assert!(
+            self.width == other.width && self.height == other.height,
+            "Cannot combine a {}x{} canvas with a {}x{} one",
+            self.width,
+            self.height,
+            other.width,
+            other.height
+        );
+
+        let mut canvas = self.blank(self.width, self.height);
+        canvas.map_pixels(|x, y, _| operation(&self.pixel_at(x, y), &other.pixel_at(x, y)));
+
+        canvas
+    }
+
     pub fn base64(&self) -> String {
+        let img = self.to_image();
+        let mut image_data: Vec<u8> = Vec::new();
+        img.write_to(&mut Cursor::new(&mut image_data), ImageOutputFormat::Png)
+            .unwrap();
+
+        general_purpose::STANDARD.encode(image_data)
+    }
+
+    // Usable as is as the src of an <img>
+    pub fn to_data_uri(&self) -> String {
+        format!("data:image/png;base64,{}", self.base64())
+    }
+
+    // Plain PPM (P3), lines are kept within 70 characters
+    pub fn to_ppm(&self) -> String {
+        let mut ppm = format!("P3\n{} {}\n255\n", self.width, self.height);
+
+        for y in 0..self.height {
+            let mut line = String::new();
+            for x in 0..self.width {
+                for value in Canvas::format_pixel(self.pixel_at(x, y)) {
+                    let value = value.to_string();
+                    if !line.is_empty() && line.len() + 1 + value.len() > 70 {
+                        ppm.push_str(&line);
+                        ppm.push('\n');
+                        line.clear();
+                    }
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(&value);
+                }
+            }
+            ppm.push_str(&line);
+            ppm.push('\n');
+        }
+
+        ppm
+    }
+
+    // Linear colors with full precision, for tools that post-process the
+    // image. See from_json.
+    pub fn to_json(&self) -> String {
+        let pixels = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let pixel = self.pixel_at(x, y);
+                [pixel.x, pixel.y, pixel.z]
+            })
+            .collect();
+
+        let json = CanvasJson {
+            width: self.width,
+            height: self.height,
+            pixels,
+        };
+
+        serde_json::to_string(&json).expect("A canvas is always serializable")
+    }
+
+    pub fn from_json(json: &str) -> io::Result<Canvas> {
+        let json: CanvasJson = serde_json::from_str(json)?;
+
+        if json.pixels.len() != json.width * json.height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Expected {} pixels for a {}x{} canvas, got {}",
+                    json.width * json.height,
+                    json.width,
+                    json.height,
+                    json.pixels.len()
+                ),
+            ));
+        }
+
+        let mut canvas = Canvas::new(json.width, json.height);
+        for (index, [r, g, b]) in json.pixels.into_iter().enumerate() {
+            canvas.set_pixel(
+                index % json.width,
+                index / json.width,
+                Tuple::new_color(r, g, b),
+            );
+        }
+
+        Ok(canvas)
+    }
+
+    // Writes the canvas at the given path, creating the missing directories.
+    // The format follows the extension: png, ppm, jpg or jpeg.
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+
+        let format = match extension.as_deref() {
+            Some("ppm") => None,
+            Some("png") => Some(ImageOutputFormat::Png),
+            Some("jpg") | Some("jpeg") => Some(ImageOutputFormat::Jpeg(90)),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unsupported image format for {}", path.display()),
+                ))
+            }
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = BufWriter::new(File::create(path)?);
+        match format {
+            None => file.write_all(self.to_ppm().as_bytes())?,
+            Some(format) => self
+                .to_image()
+                .write_to(&mut file, format)
+                .map_err(io::Error::other)?,
+        }
+
+        file.flush()
+    }
+
+    fn to_image(&self) -> RgbImage {
         let mut img: RgbImage = ImageBuffer::new(self.width as u32, self.height as u32);
         for x in 0..self.height {
             for y in 0..self.width {
-                let pixel = self.state[x][y].clone();
+                let pixel = self.pixel_at(y, x);
                 img.put_pixel(y as u32, x as u32, Rgb(Canvas::format_pixel(pixel)))
             }
         }
-        let mut image_data: Vec<u8> = Vec::new();
-        img.write_to(&mut Cursor::new(&mut image_data), ImageOutputFormat::Png)
-            .unwrap();
 
-        general_purpose::STANDARD.encode(image_data)
+        img
     }
 
     fn format_pixel(pixel: Tuple) -> [u8; 3] {
@@ -91,4 +465,279 @@ mod tests {
 
         assert_eq!(canvas.pixel_at(2, 3), color);
     }
+
+    #[test]
+    fn a_compact_canvas_keeps_colors_within_f32_precision() {
+        let color = Tuple::new_color(0.1, 0.123456789123, 1.3);
+        let mut canvas = Canvas::new_compact(5, 3);
+
+        canvas.write_pixel(color.clone(), 4, 2);
+        let stored = canvas.pixel_at(4, 2);
+
+        assert!((stored.x - color.x).abs() < 1e-7);
+        assert!((stored.y - color.y).abs() < 1e-7);
+        assert!((stored.z - color.z).abs() < 1e-7);
+        assert_eq!(canvas.pixel_at(0, 0), Tuple::black());
+
+        let mut full = Canvas::new(5, 3);
+        full.write_pixel(color, 4, 2);
+        assert_eq!(canvas.to_ppm(), full.to_ppm());
+        assert!(matches!(canvas.downscale(2).pixels, Pixels::Compact(_)));
+    }
+
+    #[test]
+    fn reinhard_tone_mapping_compresses_bright_pixels() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.write_pixel(Tuple::new_color(4.0, 1.0, 0.0), 0, 0);
+
+        canvas.tone_map_reinhard();
+
+        assert_eq!(canvas.pixel_at(0, 0), Tuple::new_color(0.8, 0.5, 0.0));
+        assert_eq!(canvas.pixel_at(1, 0), Tuple::black());
+    }
+
+    #[test]
+    fn downscaling_averages_blocks_of_pixels() {
+        let mut canvas = Canvas::new(4, 4);
+        for y in 0..4 {
+            for x in 0..3 {
+                canvas.write_pixel(Tuple::white(), x, y);
+            }
+        }
+
+        let small = canvas.downscale(2);
+
+        assert_eq!(small.width, 2);
+        assert_eq!(small.height, 2);
+        assert_eq!(small.pixel_at(0, 0), Tuple::white());
+        assert_eq!(small.pixel_at(0, 1), Tuple::white());
+        assert_eq!(small.pixel_at(1, 0), Tuple::new_color(0.5, 0.5, 0.5));
+        assert_eq!(small.pixel_at(1, 1), Tuple::new_color(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn downscaling_clamps_blocks_on_the_borders() {
+        let mut canvas = Canvas::new(3, 3);
+        canvas.write_pixel(Tuple::white(), 2, 2);
+
+        let small = canvas.downscale(2);
+
+        assert_eq!(small.width, 2);
+        assert_eq!(small.height, 2);
+        assert_eq!(small.pixel_at(0, 0), Tuple::black());
+        assert_eq!(small.pixel_at(1, 1), Tuple::white());
+    }
+
+    fn filled(width: usize, height: usize, color: Tuple) -> Canvas {
+        let mut canvas = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                canvas.write_pixel(color.clone(), x as isize, y as isize);
+            }
+        }
+        canvas
+    }
+
+    #[test]
+    fn adding_red_and_green_canvases_gives_yellow() {
+        let red = filled(3, 2, Tuple::new_color(1.0, 0.0, 0.0));
+        let green = filled(3, 2, Tuple::new_color(0.0, 1.0, 0.0));
+
+        let yellow = red.add(&green);
+
+        assert_eq!(yellow.pixel_at(0, 0), Tuple::new_color(1.0, 1.0, 0.0));
+        assert_eq!(yellow.pixel_at(2, 1), Tuple::new_color(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn multiplying_canvases_filters_the_colors() {
+        let a = filled(2, 2, Tuple::new_color(1.0, 0.5, 0.2));
+        let b = filled(2, 2, Tuple::new_color(0.5, 0.5, 0.0));
+
+        assert_eq!(
+            a.multiply(&b).pixel_at(1, 1),
+            Tuple::new_color(0.5, 0.25, 0.0)
+        );
+    }
+
+    #[test]
+    fn blending_a_canvas_over_another() {
+        let white = filled(2, 2, Tuple::white());
+        let black = filled(2, 2, Tuple::black());
+
+        assert_eq!(
+            white.over(&black, 0.5).pixel_at(0, 1),
+            Tuple::new_color(0.5, 0.5, 0.5)
+        );
+        assert_eq!(white.over(&black, 1.0).pixel_at(0, 1), Tuple::white());
+        assert_eq!(white.over(&black, 0.0).pixel_at(0, 1), Tuple::black());
+    }
+
+    #[test]
+    #[should_panic(expected = "Pixel (3, 0) is outside of the 3x2 canvas")]
+    fn reading_a_pixel_past_the_end_of_a_row_panics() {
+        Canvas::new(3, 2).pixel_at(3, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot combine a 2x2 canvas with a 3x2 one")]
+    fn combining_canvases_of_different_sizes_panics() {
+        Canvas::new(2, 2).add(&Canvas::new(3, 2));
+    }
+
+    #[test]
+    fn accumulating_weighted_samples_into_a_pixel() {
+        let mut canvas = Canvas::new(4, 4);
+
+        canvas.accumulate(Tuple::white(), 1.2, 2.7, 0.5);
+        canvas.accumulate(Tuple::black(), 1.9, 2.1, 0.5);
+        canvas.accumulate(Tuple::white(), 3.0, 0.0, 2.0);
+        canvas.accumulate(Tuple::white(), -0.5, 0.0, 1.0);
+        canvas.normalize_by_weights();
+
+        assert_eq!(canvas.pixel_at(1, 2), Tuple::new_color(0.5, 0.5, 0.5));
+        assert_eq!(canvas.pixel_at(3, 0), Tuple::white());
+        assert_eq!(canvas.pixel_at(0, 0), Tuple::black());
+    }
+
+    #[test]
+    fn the_weights_are_only_allocated_by_weighted_samples() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.write_pixel(Tuple::white(), 1, 1);
+        canvas.accumulate(Tuple::white(), -0.5, 0.0, 1.0);
+        assert!(canvas.weights.is_none());
+
+        canvas.accumulate(Tuple::white(), 1.5, 1.5, 4.0);
+        assert_eq!(canvas.weights.as_ref().map(Vec::len), Some(16));
+
+        canvas.normalize_by_weights();
+        assert!(canvas.weights.is_none());
+        assert_eq!(canvas.pixel_at(1, 1), Tuple::new_color(1.25, 1.25, 1.25));
+
+        // Nothing left to divide by
+        canvas.normalize_by_weights();
+        assert_eq!(canvas.pixel_at(1, 1), Tuple::new_color(1.25, 1.25, 1.25));
+    }
+
+    #[test]
+    fn constructing_the_ppm_pixel_data() {
+        let mut canvas = Canvas::new(5, 3);
+        canvas.write_pixel(Tuple::new_color(1.5, 0.0, 0.0), 0, 0);
+        canvas.write_pixel(Tuple::new_color(0.0, 0.5, 0.0), 2, 1);
+        canvas.write_pixel(Tuple::new_color(-0.5, 0.0, 1.0), 4, 2);
+
+        assert_eq!(
+            canvas.to_ppm(),
+            "P3\n5 3\n255\n\
+             255 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n\
+             0 0 0 0 0 0 0 128 0 0 0 0 0 0 0\n\
+             0 0 0 0 0 0 0 0 0 0 0 0 0 0 255\n"
+        );
+    }
+
+    #[test]
+    fn splitting_long_lines_in_ppm_files() {
+        let canvas = filled(10, 2, Tuple::new_color(1.0, 0.8, 0.6));
+        let ppm = canvas.to_ppm();
+        let lines: Vec<&str> = ppm.lines().collect();
+
+        assert_eq!(
+            lines[3],
+            "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204"
+        );
+        assert_eq!(
+            lines[4],
+            "153 255 204 153 255 204 153 255 204 153 255 204 153"
+        );
+    }
+
+    #[test]
+    fn saving_a_canvas_picks_the_format_from_the_extension() {
+        let directory = std::env::temp_dir()
+            .join(format!("ray_tracer_rs_{}", std::process::id()))
+            .join("nested");
+        let canvas = filled(2, 2, Tuple::new_color(1.0, 0.5, 0.0));
+
+        let headers: [(&str, &[u8]); 4] = [
+            ("image.ppm", b"P3\n"),
+            ("image.png", b"\x89PNG"),
+            ("image.jpg", b"\xFF\xD8"),
+            ("image.JPEG", b"\xFF\xD8"),
+        ];
+        for (name, header) in headers {
+            let path = directory.join(name);
+            canvas.save_to(&path).unwrap();
+
+            let content = fs::read(&path).unwrap();
+            assert!(content.starts_with(header), "{}", name);
+        }
+
+        let unsupported = canvas.save_to(&directory.join("image.gif")).unwrap_err();
+        assert_eq!(unsupported.kind(), io::ErrorKind::InvalidInput);
+
+        fs::remove_dir_all(directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn a_data_uri_holds_a_png_image() {
+        let uri = Canvas::new(3, 2).to_data_uri();
+
+        let encoded = uri.strip_prefix("data:image/png;base64,").unwrap();
+        let png = general_purpose::STANDARD.decode(encoded).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn loading_a_saved_canvas_gives_back_its_colors() {
+        let path = std::env::temp_dir()
+            .join(format!("ray_tracer_rs_from_image_{}", std::process::id()))
+            .join("image.png");
+        let mut canvas = Canvas::new(3, 2);
+        canvas.write_pixel(Tuple::new_color(1.0, 0.5, 0.0), 0, 0);
+        canvas.write_pixel(Tuple::new_color(0.2, 0.02, 0.9), 2, 1);
+        canvas.write_pixel(Tuple::new_color(0.001, 0.3, 0.7), 1, 1);
+
+        let mut encoded = Canvas::new(3, 2);
+        encoded.map_pixels(|x, y, _| canvas.pixel_at(x, y));
+        encoded.encode_srgb();
+        encoded.save_to(&path).unwrap();
+
+        let loaded = Canvas::from_image(&image::open(&path).unwrap().to_rgb8());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!((loaded.width, loaded.height), (3, 2));
+        for y in 0..2 {
+            for x in 0..3 {
+                let difference = loaded.pixel_at(x, y) - canvas.pixel_at(x, y);
+                assert!(difference.magnitude() < 0.01, "{x} {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn a_canvas_round_trips_through_json() {
+        let mut canvas = Canvas::new(1, 1);
+        canvas.write_pixel(Tuple::new_color(1.0, 0.0, 0.0), 0, 0);
+
+        let json = canvas.to_json();
+        assert_eq!(json, r#"{"width":1,"height":1,"pixels":[[1.0,0.0,0.0]]}"#);
+
+        let loaded = Canvas::from_json(&json).unwrap();
+        assert_eq!((loaded.width, loaded.height), (1, 1));
+        assert_eq!(loaded.pixel_at(0, 0), Tuple::new_color(1.0, 0.0, 0.0));
+
+        let missing = Canvas::from_json(r#"{"width":2,"height":1,"pixels":[[1.0,0.0,0.0]]}"#);
+        assert!(missing.is_err_and(|error| error.kind() == io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn each_stop_of_exposure_doubles_the_light() {
+        let mut canvas = filled(1, 1, Tuple::new_color(0.2, 0.4, 0.8));
+
+        canvas.apply_exposure(1.0);
+        assert_eq!(canvas.pixel_at(0, 0), Tuple::new_color(0.4, 0.8, 1.6));
+
+        canvas.apply_exposure(-2.0);
+        assert_eq!(canvas.pixel_at(0, 0), Tuple::new_color(0.1, 0.2, 0.4));
+    }
 }
@@ -1,4 +1,4 @@
-use std::ops;
+use std::{ops, sync::OnceLock};
 
 use crate::{core::tuples::Tuple, margin::Margin};
 use float_cmp::ApproxEq;
@@ -8,15 +8,15 @@ pub struct Matrix {
     width: usize,
     height: usize,
     grid: Vec<Vec<f64>>,
+    // Computed on first use, the same matrices are inverted over and over
+    // during a render. Cleared by set.
+    determinant: OnceLock<f64>,
+    inverse: OnceLock<Box<Matrix>>,
 }
 
 impl Matrix {
     fn new(width: usize, height: usize) -> Matrix {
-        Matrix {
-            grid: vec![vec![0.0; width]; height],
-            width,
-            height,
-        }
+        Matrix::from_grid(vec![vec![0.0; width]; height], width, height)
     }
 
     pub fn identity(size: usize) -> Matrix {
@@ -24,11 +24,7 @@ impl Matrix {
             .map(|i| (0..size).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
             .collect();
 
-        Matrix {
-            width: size,
-            height: size,
-            grid,
-        }
+        Matrix::from_grid(grid, size, size)
     }
 
     pub fn from_vector(a: Vec<f64>, width: usize, height: usize) -> Matrix {
@@ -40,10 +36,16 @@ impl Matrix {
             })
             .collect();
 
+        Matrix::from_grid(grid, width, height)
+    }
+
+    fn from_grid(grid: Vec<Vec<f64>>, width: usize, height: usize) -> Matrix {
         Matrix {
-            grid,
             width,
             height,
+            grid,
+            determinant: OnceLock::new(),
+            inverse: OnceLock::new(),
         }
     }
 
@@ -52,7 +54,9 @@ impl Matrix {
     }
 
     pub fn set(&mut self, row: usize, col: usize, value: f64) {
-        self.grid[row][col] = value
+        self.grid[row][col] = value;
+        self.determinant.take();
+        self.inverse.take();
     }
 
     pub fn transpose(&self) -> Matrix {
@@ -68,6 +72,10 @@ impl Matrix {
     }
 
     fn determinant(&self) -> f64 {
+        *self.determinant.get_or_init(|| self.compute_determinant())
+    }
+
+    fn compute_determinant(&self) -> f64 {
         match (self.width, self.height) {
             (x, y) if x != y => panic!("Determinant is a property of square matrices"),
             (2, 2) => self.get(0, 0) * self.get(1, 1) - self.get(0, 1) * self.get(1, 0),
@@ -114,6 +122,13 @@ impl Matrix {
     }
 
     pub fn invert(&self) -> Matrix {
+        self.inverse
+            .get_or_init(|| Box::new(self.compute_inverse()))
+            .as_ref()
+            .clone()
+    }
+
+    fn compute_inverse(&self) -> Matrix {
         if !self.is_invertible() {
             panic!("Matrix {:?} cannot be inverted", self)
         }
@@ -129,6 +144,149 @@ impl Matrix {
 
         inverted
     }
+
+    // Tween between two affine transformations made of a translation, a
+    // rotation and a scaling, without shearing. Translation and scale are
+    // interpolated linearly and the rotation along the shortest arc, so the
+    // shape does not shrink halfway through a turn.
+    pub fn lerp(&self, other: &Matrix, t: f64) -> Matrix {
+        let (translation_a, rotation_a, scale_a) = self.decompose();
+        let (translation_b, rotation_b, scale_b) = other.decompose();
+
+        let translation = translation_a.lerp(&translation_b, t);
+        let scale = scale_a.lerp(&scale_b, t);
+        let rotation = from_quaternion(slerp(rotation_a, rotation_b, t));
+
+        let mut output = Matrix::identity(4);
+        for (row, line) in rotation.iter().enumerate() {
+            for (col, axis_scale) in [scale.x, scale.y, scale.z].into_iter().enumerate() {
+                output.set(row, col, line[col] * axis_scale);
+            }
+        }
+        output.set(0, 3, translation.x);
+        output.set(1, 3, translation.y);
+        output.set(2, 3, translation.z);
+
+        output
+    }
+
+    // Translation, rotation as a unit quaternion (w, x, y, z) and scale of the
+    // axes. A mirroring matrix gets a negative scale on x.
+    fn decompose(&self) -> (Tuple, [f64; 4], Tuple) {
+        assert!(
+            self.width == 4 && self.height == 4,
+            "Only 4x4 transformations can be decomposed"
+        );
+
+        let translation = Tuple::new_vector(self.get(0, 3), self.get(1, 3), self.get(2, 3));
+
+        let column =
+            |col: usize| Tuple::new_vector(self.get(0, col), self.get(1, col), self.get(2, col));
+        let mut scale = Tuple::new_vector(
+            column(0).magnitude(),
+            column(1).magnitude(),
+            column(2).magnitude(),
+        );
+        if self.determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+
+        let mut rotation = [[0.0; 3]; 3];
+        for (col, axis_scale) in [scale.x, scale.y, scale.z].into_iter().enumerate() {
+            for (row, line) in rotation.iter_mut().enumerate() {
+                line[col] = self.get(row, col) / axis_scale;
+            }
+        }
+
+        (translation, to_quaternion(&rotation), scale)
+    }
+}
+
+// Unit quaternion (w, x, y, z) of a rotation matrix, taking the square root of
+// the largest term to stay accurate for every angle (Shepperd's method)
+fn to_quaternion(m: &[[f64; 3]; 3]) -> [f64; 4] {
+    let trace = m[0][0] + m[1][1] + m[2][2];
+
+    if trace > m[0][0] && trace > m[1][1] && trace > m[2][2] {
+        let s = (1.0 + trace).sqrt() * 2.0;
+        [
+            s / 4.0,
+            (m[2][1] - m[1][2]) / s,
+            (m[0][2] - m[2][0]) / s,
+            (m[1][0] - m[0][1]) / s,
+        ]
+    } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+        let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+        [
+            (m[2][1] - m[1][2]) / s,
+            s / 4.0,
+            (m[0][1] + m[1][0]) / s,
+            (m[0][2] + m[2][0]) / s,
+        ]
+    } else if m[1][1] > m[2][2] {
+        let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+        [
+            (m[0][2] - m[2][0]) / s,
+            (m[0][1] + m[1][0]) / s,
+            s / 4.0,
+            (m[1][2] + m[2][1]) / s,
+        ]
+    } else {
+        let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+        [
+            (m[1][0] - m[0][1]) / s,
+            (m[0][2] + m[2][0]) / s,
+            (m[1][2] + m[2][1]) / s,
+            s / 4.0,
+        ]
+    }
+}
+
+fn from_quaternion([w, x, y, z]: [f64; 4]) -> [[f64; 3]; 3] {
+    [
+        [
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y - w * z),
+            2.0 * (x * z + w * y),
+        ],
+        [
+            2.0 * (x * y + w * z),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z - w * x),
+        ],
+        [
+            2.0 * (x * z - w * y),
+            2.0 * (y * z + w * x),
+            1.0 - 2.0 * (x * x + y * y),
+        ],
+    ]
+}
+
+// Spherical interpolation between unit quaternions along the shortest arc
+fn slerp(a: [f64; 4], mut b: [f64; 4], t: f64) -> [f64; 4] {
+    let mut cos = (0..4).map(|i| a[i] * b[i]).sum::<f64>();
+
+    // q and -q are the same rotation, the other one goes the long way round
+    if cos < 0.0 {
+        b = b.map(|c| -c);
+        cos = -cos;
+    }
+
+    let (weight_a, weight_b) = if cos > 0.9995 {
+        // Nearly the same rotation, where sin(angle) would vanish
+        (1.0 - t, t)
+    } else {
+        let angle = cos.acos();
+        (
+            ((1.0 - t) * angle).sin() / angle.sin(),
+            (t * angle).sin() / angle.sin(),
+        )
+    };
+
+    let q: Vec<f64> = (0..4).map(|i| weight_a * a[i] + weight_b * b[i]).collect();
+    let length = q.iter().map(|c| c * c).sum::<f64>().sqrt();
+
+    [q[0] / length, q[1] / length, q[2] / length, q[3] / length]
 }
 
 impl PartialEq for Matrix {
@@ -201,27 +359,66 @@ impl ops::Mul<&Matrix> for &Matrix {
 impl ops::Mul<&Tuple> for &Matrix {
     type Output = Tuple;
 
-    // We are only interested in 4x4 matrix multiplications, so we can simplify this
-    // implementation. No need to be generic.
     fn mul(self, rhs: &Tuple) -> Tuple {
-        let mut output: Tuple = Tuple::new(0.0, 0.0, 0.0, 0.0);
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        return mul_tuple_simd(self, rhs);
 
-        for row in 0..4 {
-            let value = self.get(row, 0) * rhs.x
-                + self.get(row, 1) * rhs.y
-                + self.get(row, 2) * rhs.z
-                + self.get(row, 3) * rhs.w;
+        #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+        return mul_tuple_scalar(self, rhs);
+    }
+}
 
-            output.set(row, value);
-        }
+// We are only interested in 4x4 matrix multiplications, so we can simplify this
+// implementation. No need to be generic.
+#[cfg(any(test, not(all(feature = "simd", target_arch = "x86_64"))))]
+fn mul_tuple_scalar(matrix: &Matrix, rhs: &Tuple) -> Tuple {
+    let mut output: Tuple = Tuple::new(0.0, 0.0, 0.0, 0.0);
 
-        output
+    for row in 0..4 {
+        let value = matrix.get(row, 0) * rhs.x
+            + matrix.get(row, 1) * rhs.y
+            + matrix.get(row, 2) * rhs.z
+            + matrix.get(row, 3) * rhs.w;
+
+        output.set(row, value);
+    }
+
+    output
+}
+
+// Same sums as the scalar product, in the same order and without fused
+// multiply-add, two rows at a time. SSE2 is always available on x86_64.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+fn mul_tuple_simd(matrix: &Matrix, rhs: &Tuple) -> Tuple {
+    use std::arch::x86_64::{_mm_add_pd, _mm_mul_pd, _mm_set1_pd, _mm_set_pd, _mm_storeu_pd};
+
+    let g = &matrix.grid;
+    let mut output = [0.0; 4];
+
+    for rows in [0, 2] {
+        // SAFETY: SSE2 is part of the x86_64 baseline and the destination has
+        // room for the two lanes
+        unsafe {
+            let mut sum = _mm_mul_pd(_mm_set_pd(g[rows + 1][0], g[rows][0]), _mm_set1_pd(rhs.x));
+            for (col, value) in [(1, rhs.y), (2, rhs.z), (3, rhs.w)] {
+                let column = _mm_set_pd(g[rows + 1][col], g[rows][col]);
+                sum = _mm_add_pd(sum, _mm_mul_pd(column, _mm_set1_pd(value)));
+            }
+
+            _mm_storeu_pd(output[rows..].as_mut_ptr(), sum);
+        }
     }
+
+    Tuple::new(output[0], output[1], output[2], output[3])
 }
 
 #[cfg(test)]
 mod tests {
 
+    use std::f64::consts::PI;
+
+    use crate::core::transformations::Transformation;
+
     use super::*;
 
     #[test]
@@ -512,6 +709,29 @@ mod tests {
         assert!(matrix.invert() == inverse);
     }
 
+    #[test]
+    fn the_inverse_is_kept_until_the_matrix_changes() {
+        let mut matrix = Matrix::from_vector(
+            vec![
+                8.0, -5.0, 9.0, 2.0, 7.0, 5.0, 6.0, 1.0, -6.0, 0.0, 9.0, 6.0, -3.0, 0.0, -9.0, -4.0,
+            ],
+            4,
+            4,
+        );
+
+        let first = matrix.invert();
+        assert!(matrix.inverse.get().is_some());
+        assert!(matrix.invert() == first);
+
+        matrix.set(0, 0, 1.0);
+        assert!(matrix.inverse.get().is_none());
+        assert!(matrix.determinant.get().is_none());
+
+        let changed = matrix.invert();
+        assert!(changed != first);
+        assert!(&matrix * &changed == Matrix::identity(4));
+    }
+
     #[test]
     fn second_inverse_of_matrix() {
         let matrix = Matrix::from_vector(
@@ -628,4 +848,61 @@ mod tests {
 
         assert!(Matrix::identity(4) == &a * &a.invert())
     }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[test]
+    fn the_simd_product_with_a_tuple_matches_the_scalar_loop_bit_for_bit() {
+        use crate::core::sampler::Sampler;
+
+        let mut sampler = Sampler::new(5);
+        let mut random = || sampler.next_f64() * 200.0 - 100.0;
+        let bits = |t: Tuple| [t.x.to_bits(), t.y.to_bits(), t.z.to_bits(), t.w.to_bits()];
+
+        for _ in 0..100 {
+            let m = Matrix::from_vector((0..16).map(|_| random()).collect(), 4, 4);
+            let t = Tuple::new(random(), random(), random(), random());
+
+            assert_eq!(bits(mul_tuple_simd(&m, &t)), bits(mul_tuple_scalar(&m, &t)));
+        }
+    }
+
+    #[test]
+    fn lerping_halfway_to_a_translation() {
+        let half = Matrix::identity(4).lerp(&Transformation::translation(2.0, -4.0, 6.0), 0.5);
+
+        assert!(half == Transformation::translation(1.0, -2.0, 3.0));
+    }
+
+    #[test]
+    fn lerping_rotations_turns_along_the_shortest_arc() {
+        let from = Matrix::identity(4);
+        let to = Transformation::translation(0.0, 2.0, 0.0)
+            * Transformation::rotation_y(PI / 2.0)
+            * Transformation::scaling(3.0, 3.0, 3.0);
+
+        let middle = from.lerp(&to, 0.5);
+
+        assert!(
+            middle
+                == Transformation::translation(0.0, 1.0, 0.0)
+                    * Transformation::rotation_y(PI / 4.0)
+                    * Transformation::scaling(2.0, 2.0, 2.0)
+        );
+        assert!(from.lerp(&to, 0.0) == from);
+        assert!(from.lerp(&to, 1.0) == to);
+    }
+
+    #[test]
+    fn tweened_rotations_stay_rotations() {
+        let from = Transformation::rotation_euler(0.3, -1.2, 2.0);
+        let to = Transformation::rotation_euler(-2.5, 0.7, 0.1);
+
+        for step in 0..=10 {
+            let m = from.lerp(&to, step as f64 / 10.0);
+
+            // Orthonormal without mirroring
+            assert!(&m * &m.transpose() == Matrix::identity(4));
+            assert!(m.determinant().approx_eq(1.0, Margin::default_f64()));
+        }
+    }
 }
@@ -1,3 +1,4 @@
 pub mod matrices;
+pub mod sampler;
 pub mod transformations;
 pub mod tuples;
@@ -0,0 +1,272 @@
+use std::f64::consts::PI;
+
+// How the sub-pixel offsets of a supersampled pixel are placed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SamplingStrategy {
+    // One jittered offset per cell of a grid covering the pixel, avoids clumps
+    #[default]
+    Stratified,
+    // Every offset drawn independently over the whole pixel
+    Random,
+}
+
+// Shape of the opening of a lens, out of focus highlights take this shape
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ApertureShape {
+    #[default]
+    Disk,
+    // Regular polygon with the given number of sides, like the blades of an
+    // iris, with a corner pointing up
+    Polygon(usize),
+}
+
+// Deterministic pseudo random source shared by every feature that needs jitter,
+// so that a render is reproducible given its seed. Based on SplitMix64.
+#[derive(Clone, Debug)]
+pub struct Sampler {
+    state: u64,
+}
+
+impl Sampler {
+    pub fn new(seed: u64) -> Sampler {
+        Sampler { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniformly distributed value in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Offset inside a pixel, both components in [0, 1)
+    pub fn jitter(&mut self) -> (f64, f64) {
+        (self.next_f64(), self.next_f64())
+    }
+
+    // Offsets for `count` samples of one pixel, both components in [0, 1)
+    pub fn pixel_offsets(&mut self, strategy: SamplingStrategy, count: usize) -> Vec<(f64, f64)> {
+        match strategy {
+            SamplingStrategy::Random => (0..count).map(|_| self.jitter()).collect(),
+            SamplingStrategy::Stratified => {
+                // The squarest grid with exactly `count` cells, a prime count
+                // makes a single row of columns
+                let rows = (1..=(count as f64).sqrt() as usize)
+                    .rev()
+                    .find(|rows| count.is_multiple_of(*rows))
+                    .unwrap_or(1);
+                let columns = count / rows;
+
+                (0..count)
+                    .map(|i| {
+                        let (dx, dy) = self.jitter();
+                        let cell_x = (i % columns) as f64;
+                        let cell_y = (i / columns) as f64;
+
+                        ((cell_x + dx) / columns as f64, (cell_y + dy) / rows as f64)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // The numbers from 0 to count - 1 in a random order
+    pub fn permutation(&mut self, count: usize) -> Vec<usize> {
+        let mut numbers: Vec<usize> = (0..count).collect();
+
+        // Fisher-Yates shuffle
+        for i in (1..count).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            numbers.swap(i, j);
+        }
+
+        numbers
+    }
+
+    // Times in [0, 1) for `count` samples of one pixel, the start of each
+    // equal slice of the exposure, without jitter. The slices come in a random
+    // order, so that the time of a sample does not follow the cell of its
+    // pixel offset.
+    pub fn exposure_times(&mut self, count: usize) -> Vec<f64> {
+        self.permutation(count)
+            .into_iter()
+            .map(|slice| slice as f64 / count as f64)
+            .collect()
+    }
+
+    // Point uniformly distributed over an aperture of radius 1 centered on
+    // the origin, the radius of a polygon goes to its corners
+    pub fn sample_aperture(&mut self, shape: ApertureShape) -> (f64, f64) {
+        match shape {
+            ApertureShape::Disk => {
+                // The square root keeps the density even towards the rim
+                let radius = self.next_f64().sqrt();
+                let angle = 2.0 * PI * self.next_f64();
+
+                (radius * angle.cos(), radius * angle.sin())
+            }
+            ApertureShape::Polygon(sides) => {
+                assert!(
+                    sides >= 3,
+                    "An aperture needs at least 3 sides, got {sides}"
+                );
+
+                // The polygon is a fan of equal triangles around the center,
+                // pick one and then a point inside it
+                let side = (self.next_f64() * sides as f64) as usize;
+                let corner = |i: usize| {
+                    let angle = PI / 2.0 + 2.0 * PI * i as f64 / sides as f64;
+                    (angle.cos(), angle.sin())
+                };
+                let (a, b) = (corner(side), corner(side + 1));
+
+                let (mut u, mut v) = self.jitter();
+                if u + v > 1.0 {
+                    (u, v) = (1.0 - u, 1.0 - v);
+                }
+
+                (u * a.0 + v * b.0, u * a.1 + v * b.1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn samplers_with_the_same_seed_produce_the_same_sequence() {
+        let mut a = Sampler::new(42);
+        let mut b = Sampler::new(42);
+
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn samplers_with_different_seeds_produce_different_sequences() {
+        let mut a = Sampler::new(1);
+        let mut b = Sampler::new(2);
+
+        assert_ne!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn jitter_offsets_fall_inside_the_unit_square() {
+        let mut sampler = Sampler::new(7);
+
+        for _ in 0..1000 {
+            let (dx, dy) = sampler.jitter();
+            assert!((0.0..1.0).contains(&dx));
+            assert!((0.0..1.0).contains(&dy));
+        }
+    }
+
+    #[test]
+    fn stratified_offsets_fall_one_per_cell() {
+        let mut sampler = Sampler::new(11);
+        let offsets = sampler.pixel_offsets(SamplingStrategy::Stratified, 9);
+
+        let mut cells = vec![0; 9];
+        for (dx, dy) in offsets {
+            let cell = (dy * 3.0).floor() as usize * 3 + (dx * 3.0).floor() as usize;
+            cells[cell] += 1;
+        }
+
+        assert_eq!(cells, vec![1; 9]);
+    }
+
+    #[test]
+    fn stratified_offsets_of_a_non_square_count_cover_the_pixel() {
+        let mut sampler = Sampler::new(11);
+        let offsets = sampler.pixel_offsets(SamplingStrategy::Stratified, 6);
+
+        // 3 columns by 2 rows
+        let mut cells = vec![0; 6];
+        for (dx, dy) in offsets {
+            let cell = (dy * 2.0).floor() as usize * 3 + (dx * 3.0).floor() as usize;
+            cells[cell] += 1;
+        }
+        assert_eq!(cells, vec![1; 6]);
+
+        // A prime count leaves no row empty
+        let offsets = sampler.pixel_offsets(SamplingStrategy::Stratified, 5);
+        let mut columns = vec![0; 5];
+        for (dx, _) in offsets {
+            columns[(dx * 5.0).floor() as usize] += 1;
+        }
+        assert_eq!(columns, vec![1; 5]);
+    }
+
+    #[test]
+    fn random_offsets_are_drawn_from_the_generator() {
+        let mut sampler = Sampler::new(11);
+        let mut reference = Sampler::new(11);
+
+        let offsets = sampler.pixel_offsets(SamplingStrategy::Random, 9);
+        let expected: Vec<(f64, f64)> = (0..9).map(|_| reference.jitter()).collect();
+
+        assert_eq!(offsets, expected);
+    }
+
+    #[test]
+    fn exposure_times_start_every_slice_in_a_random_order() {
+        let mut orders = vec![];
+        for seed in 0..10 {
+            let times = Sampler::new(seed).exposure_times(8);
+
+            let mut slices: Vec<usize> = times.iter().map(|t| (t * 8.0) as usize).collect();
+            for (time, slice) in times.iter().zip(&slices) {
+                assert_eq!(*time, *slice as f64 / 8.0);
+            }
+            orders.push(slices.clone());
+            slices.sort();
+            assert_eq!(slices, (0..8).collect::<Vec<_>>());
+        }
+
+        assert!(orders.iter().any(|order| !order.is_sorted()));
+    }
+
+    // Whether the point lies inside the regular polygon of radius 1 with a
+    // corner pointing up
+    fn inside_polygon(sides: usize, (x, y): (f64, f64)) -> bool {
+        let apothem = (PI / sides as f64).cos();
+
+        (0..sides).all(|i| {
+            let normal = PI / 2.0 + (2.0 * i as f64 + 1.0) * PI / sides as f64;
+            x * normal.cos() + y * normal.sin() <= apothem + 1e-12
+        })
+    }
+
+    #[test]
+    fn hexagonal_aperture_samples_stay_inside_the_hexagon() {
+        let mut sampler = Sampler::new(3);
+
+        for _ in 0..1000 {
+            let point = sampler.sample_aperture(ApertureShape::Polygon(6));
+            assert!(inside_polygon(6, point));
+        }
+    }
+
+    #[test]
+    fn disk_aperture_samples_fill_the_circle() {
+        let mut sampler = Sampler::new(3);
+        let points: Vec<(f64, f64)> = (0..1000)
+            .map(|_| sampler.sample_aperture(ApertureShape::Disk))
+            .collect();
+
+        assert!(points.iter().all(|(x, y)| x.hypot(*y) <= 1.0));
+        // Some points fall between the hexagon and the circle around it
+        assert!(points.iter().any(|point| !inside_polygon(6, *point)));
+    }
+}
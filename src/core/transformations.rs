@@ -2,6 +2,51 @@ use crate::{core::matrices::Matrix, core::tuples::Tuple};
 
 pub struct Transformation {}
 
+// Parameters of Transformation::view_transform, kept around so that the
+// position of the eye is known without inverting the matrix
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViewTransform {
+    pub from: Tuple,
+    pub to: Tuple,
+    pub up: Tuple,
+}
+
+impl ViewTransform {
+    pub fn new(from: Tuple, to: Tuple, up: Tuple) -> ViewTransform {
+        ViewTransform { from, to, up }
+    }
+
+    pub fn matrix(&self) -> Matrix {
+        Transformation::view_transform(self.from.clone(), self.to.clone(), self.up.clone())
+    }
+
+    pub fn get_eye(&self) -> Tuple {
+        self.from.clone()
+    }
+}
+
+// Motion of a shape while the frame is exposed, a translation growing from
+// nothing at time 0 to `offset` at time 1. Applied on top of the transform of
+// the shape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animated {
+    offset: Tuple,
+}
+
+impl Animated {
+    pub fn translation(x: f64, y: f64, z: f64) -> Animated {
+        Animated {
+            offset: Tuple::new_vector(x, y, z),
+        }
+    }
+
+    pub fn transform_at(&self, time: f64) -> Matrix {
+        let offset = &self.offset * time;
+
+        Transformation::translation(offset.x, offset.y, offset.z)
+    }
+}
+
 impl Transformation {
     pub fn translation(x: f64, y: f64, z: f64) -> Matrix {
         let mut matrix = Matrix::identity(4);
@@ -23,7 +68,6 @@ impl Transformation {
         matrix
     }
 
-    #[cfg(test)]
     pub fn rotation_x(rad: f64) -> Matrix {
         let mut matrix = Matrix::identity(4);
 
@@ -57,6 +101,43 @@ impl Transformation {
         matrix
     }
 
+    // Rotation around an arbitrary axis through the origin, Rodrigues' formula
+    pub fn rotation_axis(axis: Tuple, rad: f64) -> Matrix {
+        let k = axis.normalize();
+        let (sin, cos) = rad.sin_cos();
+        let t = 1.0 - cos;
+
+        Matrix::from_vector(
+            vec![
+                cos + t * k.x * k.x,
+                t * k.x * k.y - sin * k.z,
+                t * k.x * k.z + sin * k.y,
+                0.0,
+                t * k.y * k.x + sin * k.z,
+                cos + t * k.y * k.y,
+                t * k.y * k.z - sin * k.x,
+                0.0,
+                t * k.z * k.x - sin * k.y,
+                t * k.z * k.y + sin * k.x,
+                cos + t * k.z * k.z,
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+            ],
+            4,
+            4,
+        )
+    }
+
+    // Rotates around x first, then y and finally z
+    pub fn rotation_euler(x: f64, y: f64, z: f64) -> Matrix {
+        Transformation::rotation_z(z)
+            * Transformation::rotation_y(y)
+            * Transformation::rotation_x(x)
+    }
+
     #[cfg(test)]
     fn shearing(x_y: f64, x_z: f64, y_x: f64, y_z: f64, z_x: f64, z_y: f64) -> Matrix {
         let mut matrix = Matrix::identity(4);
@@ -313,6 +394,22 @@ mod tests {
         assert!(t == Transformation::translation(0.0, 0.0, -8.0));
     }
 
+    #[test]
+    fn a_view_transform_remembers_the_eye() {
+        let from = Tuple::new_point(1.0, 3.0, 2.0);
+        let view = ViewTransform::new(
+            from.clone(),
+            Tuple::new_point(4.0, -2.0, 8.0),
+            Tuple::new_vector(1.0, 1.0, 0.0),
+        );
+
+        assert_eq!(view.get_eye(), from);
+        assert_eq!(
+            &view.matrix().invert() * &Tuple::new_point(0.0, 0.0, 0.0),
+            from
+        );
+    }
+
     #[test]
     fn arbitrary_view_transformation() {
         let from = Tuple::new_point(1.0, 3.0, 2.0);
@@ -346,4 +443,46 @@ mod tests {
 
         assert!(t == m);
     }
+
+    #[test]
+    fn rotation_around_the_z_axis_matches_rotation_z() {
+        assert_eq!(
+            Transformation::rotation_axis(Tuple::new_vector(0.0, 0.0, 1.0), PI / 3.0),
+            Transformation::rotation_z(PI / 3.0)
+        );
+        assert_eq!(
+            Transformation::rotation_axis(Tuple::new_vector(0.0, 2.0, 0.0), -PI / 5.0),
+            Transformation::rotation_y(-PI / 5.0)
+        );
+    }
+
+    #[test]
+    fn rotating_a_point_around_an_arbitrary_axis() {
+        // A third of a turn around the diagonal cycles the axes
+        let r = Transformation::rotation_axis(Tuple::new_vector(1.0, 1.0, 1.0), 2.0 * PI / 3.0);
+
+        assert_eq!(
+            &r * &Tuple::new_point(1.0, 0.0, 0.0),
+            Tuple::new_point(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            &r * &Tuple::new_point(0.0, 0.0, 2.0),
+            Tuple::new_point(2.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn euler_rotations_compose_the_rotations_around_each_axis() {
+        let r = Transformation::rotation_euler(PI / 2.0, PI / 2.0, 0.0);
+
+        // x turns the y axis into z, then y turns z into x
+        assert_eq!(
+            &r * &Tuple::new_vector(0.0, 1.0, 0.0),
+            Tuple::new_vector(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            Transformation::rotation_euler(0.0, 0.0, PI / 4.0),
+            Transformation::rotation_z(PI / 4.0)
+        );
+    }
 }
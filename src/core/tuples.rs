@@ -36,6 +36,51 @@ impl Tuple {
         }
     }
 
+    // Linear color from components encoded with the sRGB transfer function,
+    // e.g. the values of a PNG texture divided by 255
+    pub fn from_srgb(r: f64, g: f64, b: f64) -> Tuple {
+        let decode = |c: f64| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        Self::new_color(decode(r), decode(g), decode(b))
+    }
+
+    // Inverse of from_srgb, for the final pixels of an image
+    pub fn to_srgb(&self) -> Tuple {
+        let encode = |c: f64| {
+            if c <= 0.0031308 {
+                c * 12.92
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        };
+
+        Tuple::new_color(encode(self.x), encode(self.y), encode(self.z))
+    }
+
+    // Point at `radius` from the origin, y is up. `theta` turns around the y
+    // axis starting from +z towards +x, `phi` is the elevation above the xz
+    // plane, both in radians.
+    pub fn from_spherical(radius: f64, theta: f64, phi: f64) -> Tuple {
+        let direction = Tuple::on_unit_sphere(theta, phi);
+
+        Tuple::new_point(
+            radius * direction.x,
+            radius * direction.y,
+            radius * direction.z,
+        )
+    }
+
+    // Unit vector with the same convention as from_spherical
+    pub fn on_unit_sphere(theta: f64, phi: f64) -> Tuple {
+        Tuple::new_vector(phi.cos() * theta.sin(), phi.sin(), phi.cos() * theta.cos())
+    }
+
     pub fn black() -> Tuple {
         Self::new_color(0.0, 0.0, 0.0)
     }
@@ -84,9 +129,33 @@ impl Tuple {
         }
     }
 
+    // Component by component, self at t = 0 and other at t = 1
+    pub fn lerp(&self, other: &Tuple, t: f64) -> Tuple {
+        Tuple::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+            self.z + (other.z - self.z) * t,
+            self.w + (other.w - self.w) * t,
+        )
+    }
+
     pub fn reflect(&self, normal: &Tuple) -> Tuple {
         self - &(normal * 2.0 * self.dot(normal))
     }
+
+    // Two unit vectors that form an orthonormal basis together with this unit
+    // vector, without branching on its direction (Duff et al. revision of
+    // Frisvad's method)
+    pub fn orthonormal_basis(&self) -> (Tuple, Tuple) {
+        let sign = 1.0_f64.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+
+        let tangent = Tuple::new_vector(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x);
+        let bitangent = Tuple::new_vector(b, sign + self.y * self.y * a, -self.y);
+
+        (tangent, bitangent)
+    }
 }
 
 impl PartialEq for Tuple {
@@ -205,6 +274,8 @@ impl ops::Neg for Tuple {
 #[cfg(test)]
 mod tests {
 
+    use std::f64::consts::FRAC_PI_2;
+
     use super::*;
 
     impl Tuple {
@@ -469,4 +540,42 @@ mod tests {
 
         assert!(r == Tuple::new_vector(1.0, 0.0, 0.0));
     }
+
+    #[test]
+    fn converting_colors_between_srgb_and_linear() {
+        let linear = Tuple::from_srgb(0.5, 0.04, 1.0);
+
+        assert!((linear.x - 0.214041).abs() < 1e-6);
+        assert!((linear.y - 0.04 / 12.92).abs() < 1e-12);
+        assert!((linear.z - 1.0).abs() < 1e-12);
+        assert_eq!(linear.to_srgb(), Tuple::new_color(0.5, 0.04, 1.0));
+        assert_eq!(Tuple::black().to_srgb(), Tuple::black());
+    }
+
+    #[test]
+    fn spherical_coordinates_start_on_z_and_turn_towards_x_and_y() {
+        assert_eq!(
+            Tuple::from_spherical(1.0, 0.0, 0.0),
+            Tuple::new_point(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            Tuple::from_spherical(2.0, FRAC_PI_2, 0.0),
+            Tuple::new_point(2.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            Tuple::on_unit_sphere(0.0, FRAC_PI_2),
+            Tuple::new_vector(0.0, 1.0, 0.0)
+        );
+        assert_eq!(Tuple::on_unit_sphere(1.2, -0.7).magnitude(), 1.0);
+    }
+
+    #[test]
+    fn lerping_tuples_component_by_component() {
+        let a = Tuple::new_point(1.0, -2.0, 4.0);
+        let b = Tuple::new_point(3.0, 2.0, 0.0);
+
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 0.25), Tuple::new_point(1.5, -1.0, 3.0));
+        assert_eq!(a.lerp(&b, 1.0), b);
+    }
 }
@@ -0,0 +1,8 @@
+pub mod camera;
+pub mod canvas;
+pub mod core;
+pub mod margin;
+pub mod materials;
+pub mod rays;
+pub mod scenarios;
+pub mod shapes;
@@ -1,25 +1,18 @@
-mod camera;
-mod canvas;
-mod core;
-mod margin;
-mod materials;
-mod rays;
-mod scenarios;
-mod shapes;
-
-use std::f64::consts::PI;
+use std::{f64::consts::PI, time::Duration};
 
 use actix_cors::Cors;
 use actix_web::{error, get, post, web, App, HttpServer, Responder, Result};
-use scenarios::Scenario;
 use serde::{Deserialize, Serialize};
 
-use crate::{
-    camera::Camera,
-    core::{transformations::Transformation, tuples::Tuple},
-    scenarios::lights::PointLight,
+use ray_tracer_rs::{
+    camera::{Camera, CancelToken, RenderSettings},
+    core::tuples::Tuple,
+    scenarios::{lights::PointLight, Scenario, ScenarioOptions},
 };
 
+// Past this, the render stops and returns the rows done so far
+const RENDER_TIMEOUT: Duration = Duration::from_secs(60);
+
 #[actix_web::main] // or #[tokio::main]
 async fn main() -> std::io::Result<()> {
     HttpServer::new(|| {
@@ -57,11 +50,13 @@ async fn render_scenario(
     scenario: web::Path<String>,
     parameters: web::Json<ScenarioParameters>,
 ) -> Result<impl Responder> {
-    if !Scenario::list().contains(&scenario) {
-        return Err(error::ErrorBadRequest("err.name"));
-    }
-
-    let mut scenario = Scenario::get(&scenario);
+    let mut scenario = Scenario::get_with(
+        &scenario,
+        &ScenarioOptions {
+            seed: parameters.seed,
+        },
+    )
+    .map_err(|_| error::ErrorBadRequest("err.name"))?;
 
     scenario.get_world().set_light(PointLight::new(
         Tuple::white(),
@@ -73,7 +68,7 @@ async fn render_scenario(
     ));
 
     let mut camera = Camera::new(1000, 500, PI / 2.0);
-    camera.set_transform(Transformation::view_transform(
+    camera.look_at(
         Tuple::new_point(
             parameters.camera_position.from.x,
             parameters.camera_position.from.y,
@@ -89,10 +84,14 @@ async fn render_scenario(
             parameters.camera_position.up.y,
             parameters.camera_position.up.z,
         ),
-    ));
-    camera.precompute_inverse_transform();
+    );
+
+    let settings = RenderSettings {
+        cancel: CancelToken::with_timeout(RENDER_TIMEOUT),
+        ..RenderSettings::default()
+    };
 
-    let canvas = camera.render(scenario.get_world());
+    let canvas = camera.render(scenario.get_world(), &settings);
     let image = Image {
         base64_image: canvas.base64(),
     };
@@ -109,6 +108,7 @@ struct Scenarios {
 struct ScenarioParameters {
     camera_position: CameraPosition,
     light_position: LightPosition,
+    seed: Option<u64>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -1,9 +1,53 @@
+pub mod noise;
+pub mod normal_maps;
 pub mod patterns;
+pub mod uv;
+
+use std::fmt;
 
 use crate::{
-    core::tuples::Tuple, materials::patterns::Pattern, scenarios::lights::PointLight, shapes::Shape,
+    core::tuples::Tuple, materials::normal_maps::NormalMap, materials::patterns::Pattern,
+    scenarios::lights::Light, shapes::Shape,
 };
 
+// Returned by the try_set_* setters when a value is physically meaningless
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialError {
+    property: &'static str,
+    value: f64,
+}
+
+impl MaterialError {
+    pub fn get_property(&self) -> &'static str {
+        self.property
+    }
+
+    pub fn get_value(&self) -> f64 {
+        self.value
+    }
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid {} for a material: {}",
+            self.property, self.value
+        )
+    }
+}
+
+impl std::error::Error for MaterialError {}
+
+// How the highlight is computed, Phong dots the reflected light with the eye
+// while Blinn-Phong dots the half vector between light and eye with the normal
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SpecularModel {
+    #[default]
+    Phong,
+    BlinnPhong,
+}
+
 #[derive(Clone, Debug)]
 pub struct Material {
     color: Tuple,
@@ -12,13 +56,24 @@ pub struct Material {
     specular: f64,
     shininess: f64,
     reflective: f64,
+    // Spreads the reflected rays over a cone around the mirror direction, from
+    // 0 for a perfect mirror to 1 for a cone as wide as the hemisphere
+    roughness: f64,
+    reflection_samples: usize,
     transparency: f64,
     refractive_index: f64,
+    // Red, green and blue indexes of a dispersive material, the scalar index
+    // is still used for the Fresnel reflectance
+    channel_refractive_index: Option<[f64; 3]>,
     pattern: Option<Pattern>,
+    normal_map: Option<NormalMap>,
+    specular_model: SpecularModel,
+    casts_shadow: bool,
+    receives_shadow: bool,
 }
 
-impl Material {
-    pub fn default() -> Material {
+impl Default for Material {
+    fn default() -> Material {
         Material {
             color: Tuple::white(),
             ambient: 0.1,
@@ -26,13 +81,69 @@ impl Material {
             specular: 0.9,
             shininess: 200.0,
             reflective: 0.0,
+            roughness: 0.0,
+            reflection_samples: 16,
             transparency: 0.0,
             refractive_index: 1.0,
+            channel_refractive_index: None,
             pattern: None,
+            normal_map: None,
+            specular_model: SpecularModel::default(),
+            casts_shadow: true,
+            receives_shadow: true,
+        }
+    }
+}
+
+impl Material {
+    // Clear glass. It is not reflective on its own since shading already
+    // mirrors light off transparent surfaces at grazing angles.
+    pub fn glass() -> Material {
+        Material {
+            ambient: 0.0,
+            diffuse: 0.1,
+            specular: 1.0,
+            shininess: 300.0,
+            transparency: 1.0,
+            refractive_index: 1.5,
+            ..Material::default()
+        }
+    }
+
+    pub fn mirror() -> Material {
+        Material {
+            color: Tuple::black(),
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 1.0,
+            shininess: 300.0,
+            reflective: 1.0,
+            ..Material::default()
+        }
+    }
+
+    // Rough surface without highlights
+    pub fn matte(color: Tuple) -> Material {
+        Material {
+            color,
+            diffuse: 0.9,
+            specular: 0.0,
+            ..Material::default()
+        }
+    }
+
+    // Tinted reflections with sharp highlights
+    pub fn metal(color: Tuple) -> Material {
+        Material {
+            color,
+            diffuse: 0.3,
+            specular: 1.0,
+            shininess: 100.0,
+            reflective: 0.6,
+            ..Material::default()
         }
     }
 
-    #[cfg(test)]
     pub fn get_color(&self) -> Tuple {
         self.color.clone()
     }
@@ -41,6 +152,14 @@ impl Material {
         self.reflective
     }
 
+    pub fn get_roughness(&self) -> f64 {
+        self.roughness
+    }
+
+    pub fn get_reflection_samples(&self) -> usize {
+        self.reflection_samples
+    }
+
     pub fn get_transparency(&self) -> f64 {
         self.transparency
     }
@@ -49,6 +168,39 @@ impl Material {
         self.refractive_index
     }
 
+    pub fn get_channel_refractive_index(&self) -> Option<[f64; 3]> {
+        self.channel_refractive_index
+    }
+
+    // Index bending the given color channel, the scalar index when no channel
+    // is picked or the material does not disperse light
+    pub fn get_refractive_index_for(&self, channel: Option<usize>) -> f64 {
+        match (self.channel_refractive_index, channel) {
+            (Some(indexes), Some(channel)) => indexes[channel],
+            _ => self.refractive_index,
+        }
+    }
+
+    pub fn get_casts_shadow(&self) -> bool {
+        self.casts_shadow
+    }
+
+    pub fn get_receives_shadow(&self) -> bool {
+        self.receives_shadow
+    }
+
+    // An object that does not cast shadows is still seen by every other ray,
+    // the light just goes through it
+    pub fn set_casts_shadow(&mut self, casts_shadow: bool) {
+        self.casts_shadow = casts_shadow
+    }
+
+    // A surface that does not receive shadows is lit as if nothing stood
+    // between it and the light
+    pub fn set_receives_shadow(&mut self, receives_shadow: bool) {
+        self.receives_shadow = receives_shadow
+    }
+
     pub fn set_diffuse(&mut self, diffuse: f64) {
         self.diffuse = diffuse
     }
@@ -57,6 +209,10 @@ impl Material {
         self.specular = specular
     }
 
+    pub fn set_specular_model(&mut self, specular_model: SpecularModel) {
+        self.specular_model = specular_model
+    }
+
     pub fn set_color(&mut self, color: Tuple) {
         self.color = color
     }
@@ -65,16 +221,40 @@ impl Material {
         self.pattern = Some(pattern)
     }
 
+    pub fn get_normal_map_ref(&self) -> Option<&NormalMap> {
+        self.normal_map.as_ref()
+    }
+
+    // The map is sampled in object space and bends the shading normal, the
+    // geometry is unchanged
+    pub fn set_normal_map(&mut self, normal_map: NormalMap) {
+        self.normal_map = Some(normal_map)
+    }
+
     #[cfg(test)]
     pub fn set_ambient(&mut self, ambient: f64) {
         self.ambient = ambient;
     }
 
-    #[cfg(test)]
     pub fn set_reflective(&mut self, reflective: f64) {
         self.reflective = reflective
     }
 
+    pub fn set_roughness(&mut self, roughness: f64) {
+        self.roughness = roughness
+    }
+
+    // Rays averaged by a rough reflection seen from the camera. Reflections of
+    // reflections cast a single jittered ray, so the rays do not multiply at
+    // every bounce.
+    pub fn set_reflection_samples(&mut self, reflection_samples: usize) {
+        assert!(
+            reflection_samples > 0,
+            "A rough reflection needs at least one sample"
+        );
+        self.reflection_samples = reflection_samples
+    }
+
     pub fn set_transparency(&mut self, transparency: f64) {
         self.transparency = transparency
     }
@@ -83,41 +263,110 @@ impl Material {
         self.refractive_index = refractive_index
     }
 
+    // Bends red, green and blue by a different amount, like a prism. None
+    // refracts every channel with the scalar index.
+    pub fn set_channel_refractive_index(&mut self, channel_refractive_index: Option<[f64; 3]>) {
+        self.channel_refractive_index = channel_refractive_index
+    }
+
+    // Same as set_reflective, rejecting values outside [0, 1]
+    pub fn try_set_reflective(&mut self, reflective: f64) -> Result<(), MaterialError> {
+        self.reflective = check("reflective", reflective, (0.0..=1.0).contains(&reflective))?;
+        Ok(())
+    }
+
+    // Same as set_transparency, rejecting values outside [0, 1]
+    pub fn try_set_transparency(&mut self, transparency: f64) -> Result<(), MaterialError> {
+        self.transparency = check(
+            "transparency",
+            transparency,
+            (0.0..=1.0).contains(&transparency),
+        )?;
+        Ok(())
+    }
+
+    // Same as set_refractive_index, rejecting values below the one of vacuum
+    pub fn try_set_refractive_index(&mut self, refractive_index: f64) -> Result<(), MaterialError> {
+        self.refractive_index = check(
+            "refractive index",
+            refractive_index,
+            refractive_index >= 1.0 && refractive_index.is_finite(),
+        )?;
+        Ok(())
+    }
+
     pub fn lighting(
         &self,
         object: &Shape,
-        light: &PointLight,
+        light: &dyn Light,
         point: &Tuple,
         eyev: &Tuple,
         normalv: &Tuple,
         in_shadow: bool,
     ) -> Tuple {
-        let color = match &self.pattern {
+        let light_filter = if in_shadow {
+            Tuple::black()
+        } else {
+            Tuple::white()
+        };
+
+        self.lighting_filtered(object, light, point, eyev, normalv, &light_filter, 1.0)
+    }
+
+    // Color of the material at a point of the object, from its pattern when it
+    // has one, before any light is applied
+    pub fn surface_color(&self, object: &Shape, point: &Tuple) -> Tuple {
+        match &self.pattern {
             Some(p) => p.stripe_at_object(object, point),
             None => self.color.clone(),
-        };
+        }
+    }
+
+    // Same as lighting, with the direct light multiplied by `light_filter`:
+    // white when nothing is in the way, black in a full shadow and tinted
+    // behind colored glass. The ambient light is multiplied by
+    // `ambient_occlusion`, the fraction of it reaching the point.
+    #[allow(clippy::too_many_arguments)]
+    pub fn lighting_filtered(
+        &self,
+        object: &Shape,
+        light: &dyn Light,
+        point: &Tuple,
+        eyev: &Tuple,
+        normalv: &Tuple,
+        light_filter: &Tuple,
+        ambient_occlusion: f64,
+    ) -> Tuple {
+        let color = self.surface_color(object, point);
 
         let effective_color = color.hadamard_product(&light.get_intensity());
-        let lightv = (light.get_position_ref() - point).normalize();
+        let lightv = light.direction_from(point);
 
-        let ambient = &effective_color * self.ambient;
+        let ambient = &effective_color * (self.ambient * ambient_occlusion);
 
-        if in_shadow {
+        if *light_filter == Tuple::black() {
             return ambient;
         }
 
+        // A gobo dims the direct light only, like a shadow
+        let intensity = light.intensity_at(point).hadamard_product(light_filter);
+        let effective_color = color.hadamard_product(&intensity);
+
         let light_dot_normal = lightv.dot(normalv);
         let mut diffuse = Tuple::black();
         let mut specular = Tuple::black();
 
         if light_dot_normal > 0.0 {
             diffuse = effective_color * self.diffuse * light_dot_normal;
-            let reflectv = (-lightv).reflect(normalv);
-            let reflect_dot_eye = reflectv.dot(eyev);
 
-            if reflect_dot_eye > 0.0 {
-                let factor = reflect_dot_eye.powf(self.shininess);
-                specular = light.get_intensity() * self.specular * factor;
+            let highlight = match self.specular_model {
+                SpecularModel::Phong => (-lightv).reflect(normalv).dot(eyev),
+                SpecularModel::BlinnPhong => (&lightv + eyev).normalize().dot(normalv),
+            };
+
+            if highlight > 0.0 {
+                let factor = highlight.powf(self.shininess);
+                specular = intensity * self.specular * factor;
             }
         }
 
@@ -125,14 +374,28 @@ impl Material {
     }
 }
 
+fn check(property: &'static str, value: f64, valid: bool) -> Result<f64, MaterialError> {
+    if valid {
+        Ok(value)
+    } else {
+        Err(MaterialError { property, value })
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
-    use std::sync::{Arc, Mutex};
+    use std::{
+        f64::consts::{FRAC_1_SQRT_2, PI},
+        sync::{Arc, Mutex},
+    };
 
     use float_cmp::ApproxEq;
 
-    use crate::{margin::Margin, materials::patterns::PatternsKind, shapes::spheres::Sphere};
+    use crate::{
+        margin::Margin, materials::patterns::PatternsKind, scenarios::lights::PointLight,
+        shapes::spheres::Sphere,
+    };
 
     use super::*;
 
@@ -242,14 +505,14 @@ mod tests {
     #[test]
     fn lighting_with_a_pattern_applied() {
         let mut m = Material::default();
-        m.pattern = Some(Pattern::stripe(
+        m.set_pattern(Pattern::stripe(
             Tuple::white(),
             Tuple::black(),
             PatternsKind::Stripe,
         ));
-        m.ambient = 1.0;
-        m.diffuse = 0.0;
-        m.specular = 0.0;
+        m.set_ambient(1.0);
+        m.set_diffuse(0.0);
+        m.set_specular(0.0);
 
         let eyev = Tuple::new_vector(0.0, 0.0, -1.0);
         let normalv = Tuple::new_vector(0.0, 0.0, -1.0);
@@ -277,6 +540,61 @@ mod tests {
         assert_eq!(Tuple::black(), c2);
     }
 
+    #[test]
+    fn a_striped_gobo_lights_alternating_bands() {
+        let m = Material::default();
+        let eyev = Tuple::new_vector(0.0, 1.0, 0.0);
+        let normalv = Tuple::new_vector(0.0, 1.0, 0.0);
+        let mut light = PointLight::new(Tuple::white(), Tuple::new_point(0.0, 1.0, 0.0));
+        light.set_gobo(Pattern::stripe(
+            Tuple::white(),
+            Tuple::black(),
+            PatternsKind::Stripe,
+        ));
+        let object = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+
+        let bands: Vec<Tuple> = [0.5, 1.5, 2.5, 3.5]
+            .iter()
+            .map(|x| {
+                let point = Tuple::new_point(*x, 0.0, 0.0);
+                m.lighting(&object, &light, &point, &eyev, &normalv, false)
+            })
+            .collect();
+
+        let ambient = Tuple::new_color(0.1, 0.1, 0.1);
+        assert!(bands[0].x > 0.5);
+        assert_eq!(bands[1], ambient);
+        assert!(bands[2].x > 0.1);
+        assert_eq!(bands[3], ambient);
+    }
+
+    #[test]
+    fn blinn_phong_highlights_are_wider_than_phong_ones() {
+        let mut m = Material {
+            shininess: 10.0,
+            ..Material::default()
+        };
+        let point = Tuple::new_point(0.0, 0.0, 0.0);
+        let eyev = Tuple::new_vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(Tuple::white(), Tuple::new_point(0.0, 10.0, -10.0));
+        let object = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+
+        let phong = m.lighting(&object, &light, &point, &eyev, &normalv, false);
+        m.set_specular_model(SpecularModel::BlinnPhong);
+        let blinn_phong = m.lighting(&object, &light, &point, &eyev, &normalv, false);
+
+        // The light is 45 degrees off the normal: the reflected light is 45
+        // degrees away from the eye while the half vector is only 22.5 away
+        let base = 0.1 + 0.9 * FRAC_1_SQRT_2;
+        let phong_highlight = 0.9 * FRAC_1_SQRT_2.powf(10.0);
+        let blinn_phong_highlight = 0.9 * (PI / 8.0).cos().powf(10.0);
+
+        assert!((phong.x - (base + phong_highlight)).abs() < 1e-10);
+        assert!((blinn_phong.x - (base + blinn_phong_highlight)).abs() < 1e-10);
+        assert!(blinn_phong.x > phong.x);
+    }
+
     #[test]
     fn reflectivity_for_the_default_material() {
         let material = Material::default();
@@ -293,4 +611,63 @@ mod tests {
             .refractive_index
             .approx_eq(1.0, Margin::default_f64()));
     }
+
+    #[test]
+    fn validated_setters_accept_the_boundary_values() {
+        let mut material = Material::default();
+
+        assert!(material.try_set_reflective(0.0).is_ok());
+        assert!(material.try_set_reflective(1.0).is_ok());
+        assert!(material.try_set_transparency(0.0).is_ok());
+        assert!(material.try_set_transparency(1.0).is_ok());
+        assert!(material.try_set_refractive_index(1.0).is_ok());
+        assert!(material.try_set_refractive_index(2.417).is_ok());
+
+        assert_eq!(material.get_reflective(), 1.0);
+        assert_eq!(material.get_transparency(), 1.0);
+        assert_eq!(material.get_refractive_index(), 2.417);
+    }
+
+    #[test]
+    fn validated_setters_reject_out_of_range_values() {
+        let mut material = Material::default();
+
+        let error = material.try_set_refractive_index(0.8).unwrap_err();
+        assert_eq!(error.get_property(), "refractive index");
+        assert_eq!(error.get_value(), 0.8);
+
+        assert!(material.try_set_reflective(-0.1).is_err());
+        assert!(material.try_set_reflective(1.5).is_err());
+        assert!(material.try_set_transparency(1.01).is_err());
+        assert!(material.try_set_transparency(f64::NAN).is_err());
+
+        // A rejected value leaves the material untouched
+        assert_eq!(material.get_reflective(), 0.0);
+        assert_eq!(material.get_transparency(), 0.0);
+        assert_eq!(material.get_refractive_index(), 1.0);
+    }
+
+    #[test]
+    fn preset_materials() {
+        let glass = Material::glass();
+        assert_eq!(glass.get_transparency(), 1.0);
+        assert_eq!(glass.get_refractive_index(), 1.5);
+        assert_eq!(glass.get_reflective(), 0.0);
+
+        let mirror = Material::mirror();
+        assert_eq!(mirror.get_reflective(), 1.0);
+        assert_eq!(mirror.get_transparency(), 0.0);
+        assert_eq!(mirror.diffuse, 0.0);
+
+        let red = Tuple::new_color(1.0, 0.0, 0.0);
+        let matte = Material::matte(red.clone());
+        assert_eq!(matte.get_color(), red);
+        assert_eq!(matte.specular, 0.0);
+        assert_eq!(matte.get_reflective(), 0.0);
+
+        let metal = Material::metal(red.clone());
+        assert_eq!(metal.get_color(), red);
+        assert_eq!(metal.specular, 1.0);
+        assert_eq!(metal.get_reflective(), 0.6);
+    }
 }
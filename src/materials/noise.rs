@@ -0,0 +1,123 @@
+use crate::core::{sampler::Sampler, tuples::Tuple};
+
+// Gradient noise in about [-1, 1], 0 on every point of the integer lattice.
+// Each corner of the lattice cell gets a pseudo random gradient picked from
+// the 12 edges of a cube, drawn from the seed and the corner coordinates.
+pub fn gradient_noise(point: &Tuple, seed: u64) -> f64 {
+    let (x0, y0, z0) = (point.x.floor(), point.y.floor(), point.z.floor());
+    let (fx, fy, fz) = (point.x - x0, point.y - y0, point.z - z0);
+
+    let corner = |dx: f64, dy: f64, dz: f64| {
+        let (gx, gy, gz) = gradient(seed, x0 + dx, y0 + dy, z0 + dz);
+        gx * (fx - dx) + gy * (fy - dy) + gz * (fz - dz)
+    };
+
+    let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+
+    let x00 = lerp(u, corner(0.0, 0.0, 0.0), corner(1.0, 0.0, 0.0));
+    let x10 = lerp(u, corner(0.0, 1.0, 0.0), corner(1.0, 1.0, 0.0));
+    let x01 = lerp(u, corner(0.0, 0.0, 1.0), corner(1.0, 0.0, 1.0));
+    let x11 = lerp(u, corner(0.0, 1.0, 1.0), corner(1.0, 1.0, 1.0));
+
+    lerp(w, lerp(v, x00, x10), lerp(v, x01, x11))
+}
+
+// Fractional Brownian motion in [0, 1]: `octaves` layers of gradient noise,
+// each one `lacunarity` times finer and `persistence` times weaker than the
+// previous one
+pub fn fractal_noise(
+    point: &Tuple,
+    octaves: usize,
+    persistence: f64,
+    lacunarity: f64,
+    seed: u64,
+) -> f64 {
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    let mut max_total = 0.0;
+
+    for octave in 0..octaves {
+        let scaled = Tuple::new_point(
+            point.x * frequency,
+            point.y * frequency,
+            point.z * frequency,
+        );
+        // A different seed per octave, so the lattices of the layers do not
+        // line up at the origin
+        total += amplitude * gradient_noise(&scaled, seed.wrapping_add(octave as u64));
+        max_total += amplitude;
+
+        amplitude *= persistence;
+        frequency *= lacunarity;
+    }
+
+    if max_total == 0.0 {
+        return 0.5;
+    }
+
+    ((total / max_total + 1.0) / 2.0).clamp(0.0, 1.0)
+}
+
+fn gradient(seed: u64, x: f64, y: f64, z: f64) -> (f64, f64, f64) {
+    let mut hash = Sampler::new(seed);
+    for coordinate in [x, y, z] {
+        hash = Sampler::new(hash.next_u64() ^ (coordinate as i64 as u64));
+    }
+
+    match hash.next_u64() % 12 {
+        0 => (1.0, 1.0, 0.0),
+        1 => (-1.0, 1.0, 0.0),
+        2 => (1.0, -1.0, 0.0),
+        3 => (-1.0, -1.0, 0.0),
+        4 => (1.0, 0.0, 1.0),
+        5 => (-1.0, 0.0, 1.0),
+        6 => (1.0, 0.0, -1.0),
+        7 => (-1.0, 0.0, -1.0),
+        8 => (0.0, 1.0, 1.0),
+        9 => (0.0, -1.0, 1.0),
+        10 => (0.0, 1.0, -1.0),
+        _ => (0.0, -1.0, -1.0),
+    }
+}
+
+// 6t⁵ - 15t⁴ + 10t³, flat at 0 and 1 so the cells join without creases
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn gradient_noise_is_zero_on_the_lattice() {
+        assert_eq!(gradient_noise(&Tuple::new_point(3.0, -2.0, 7.0), 1), 0.0);
+    }
+
+    #[test]
+    fn fractal_noise_is_reproducible_from_its_seed() {
+        let point = Tuple::new_point(0.3, 1.7, -2.4);
+
+        let value = fractal_noise(&point, 4, 0.5, 2.0, 42);
+
+        assert_eq!(value, fractal_noise(&point, 4, 0.5, 2.0, 42));
+        assert_ne!(value, fractal_noise(&point, 4, 0.5, 2.0, 43));
+        assert!((0.0..=1.0).contains(&value));
+    }
+
+    #[test]
+    fn more_octaves_add_detail() {
+        let point = Tuple::new_point(0.3, 1.7, -2.4);
+
+        let coarse = fractal_noise(&point, 1, 0.5, 2.0, 42);
+        let detailed = fractal_noise(&point, 5, 0.5, 2.0, 42);
+
+        assert_ne!(coarse, detailed);
+    }
+}
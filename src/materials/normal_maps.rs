@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use image::RgbImage;
+
+use crate::{core::tuples::Tuple, materials::uv::UvMapping};
+
+// Tangent space normal map, each texel encodes a normal where red follows the
+// tangent, green the bitangent and blue the surface normal. The byte 128 is
+// 0 and 255 is 1, so an image filled with (128, 128, 255) leaves the normal
+// as it is.
+#[derive(Clone, Debug)]
+pub struct NormalMap {
+    image: Arc<RgbImage>,
+    mapping: UvMapping,
+}
+
+impl NormalMap {
+    pub fn new(image: RgbImage, mapping: UvMapping) -> NormalMap {
+        NormalMap {
+            image: Arc::new(image),
+            mapping,
+        }
+    }
+
+    // Nearest texel at the point, in tangent space. v grows upwards while the
+    // rows of the image grow downwards.
+    pub fn tangent_normal_at(&self, object_point: &Tuple) -> Tuple {
+        let (u, v) = self.mapping.uv(object_point);
+        let x = (u * (self.image.width() - 1) as f64).round() as u32;
+        let y = ((1.0 - v) * (self.image.height() - 1) as f64).round() as u32;
+
+        let texel = self.image.get_pixel(x, y);
+        let decode = |byte: u8| ((byte as f64 - 128.0) / 127.0).max(-1.0);
+
+        Tuple::new_vector(decode(texel[0]), decode(texel[1]), decode(texel[2]))
+    }
+
+    // Direction of the growing u of the map at the point, in object space
+    pub fn tangent_at(&self, object_point: &Tuple) -> Tuple {
+        self.mapping.tangent(object_point)
+    }
+
+    // Bends the normal of the surface with the texel at the point. The tangent
+    // and the bitangent are the directions of the growing u and v of the map.
+    pub fn perturb(
+        &self,
+        object_point: &Tuple,
+        normal: &Tuple,
+        tangent: &Tuple,
+        bitangent: &Tuple,
+    ) -> Tuple {
+        let texel = self.tangent_normal_at(object_point);
+
+        (tangent * texel.x + bitangent * texel.y + normal * texel.z).normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use image::Rgb;
+
+    use super::*;
+
+    #[test]
+    fn a_flat_normal_map_leaves_the_normal_unchanged() {
+        let map = NormalMap::new(
+            RgbImage::from_pixel(4, 4, Rgb([128, 128, 255])),
+            UvMapping::Planar,
+        );
+        let normal = Tuple::new_vector(0.0, 1.0, 0.0);
+        let tangent = Tuple::new_vector(1.0, 0.0, 0.0);
+        let bitangent = Tuple::new_vector(0.0, 0.0, 1.0);
+
+        let perturbed = map.perturb(
+            &Tuple::new_point(0.3, 0.0, 0.7),
+            &normal,
+            &tangent,
+            &bitangent,
+        );
+
+        assert_eq!(perturbed, normal);
+    }
+
+    #[test]
+    fn a_tilted_texel_bends_the_normal_towards_the_tangent() {
+        let map = NormalMap::new(
+            RgbImage::from_pixel(4, 4, Rgb([255, 128, 255])),
+            UvMapping::Planar,
+        );
+        let normal = Tuple::new_vector(0.0, 1.0, 0.0);
+        let tangent = Tuple::new_vector(1.0, 0.0, 0.0);
+        let bitangent = Tuple::new_vector(0.0, 0.0, 1.0);
+
+        let perturbed = map.perturb(
+            &Tuple::new_point(0.3, 0.0, 0.7),
+            &normal,
+            &tangent,
+            &bitangent,
+        );
+
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        assert_eq!(perturbed, (&tangent * half) + (&normal * half));
+    }
+}
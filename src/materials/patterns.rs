@@ -1,19 +1,45 @@
-use crate::{core::matrices::Matrix, core::tuples::Tuple, shapes::Shape};
+use crate::{
+    core::matrices::Matrix, core::tuples::Tuple, materials::noise::fractal_noise,
+    materials::uv::spherical_uv, shapes::Shape,
+};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum PatternsKind {
     Stripe,
     Gradient,
+    // Goes from a to b and back to a every two units, without seams
+    GradientSmooth,
     Ring,
     Checker,
+    // Checker on the x/z plane only, ignores y
+    Checker2D,
+    // Checker on the longitude and latitude of the point, as seen from the
+    // origin, with `u_divs` tiles around the y axis and `v_divs` from pole to
+    // pole
+    SphericalChecker {
+        u_divs: usize,
+        v_divs: usize,
+    },
+    // Blend from a to b driven by multi octave noise, for clouds, marble or
+    // terrain. Each octave is `lacunarity` times finer and `persistence`
+    // times weaker than the previous one.
+    FractalNoise {
+        octaves: usize,
+        persistence: f64,
+        lacunarity: f64,
+        seed: u64,
+    },
     Test,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Pattern {
     color_a: Tuple,
     color_b: Tuple,
     transformation: Matrix,
+    // Computed once when the transformation is set, patterns are sampled at
+    // every hit
+    inverse_transformation: Matrix,
     kind: PatternsKind,
 }
 
@@ -23,13 +49,21 @@ impl Pattern {
             color_a,
             color_b,
             transformation: Matrix::identity(4),
+            inverse_transformation: Matrix::identity(4),
             kind,
         }
     }
 
     pub fn stripe_at_object(&self, object: &Shape, world_point: &Tuple) -> Tuple {
         let object_point = &object.get_inverse_transformation() * world_point;
-        let pattern_point = &self.transformation.invert() * &object_point;
+
+        self.color_at_world(&object_point)
+    }
+
+    // Applies only the transformation of the pattern, for patterns that are
+    // not attached to any shape
+    pub fn color_at_world(&self, point: &Tuple) -> Tuple {
+        let pattern_point = &self.inverse_transformation * point;
 
         self.stripe_at(&pattern_point)
     }
@@ -37,7 +71,7 @@ impl Pattern {
     pub fn stripe_at(&self, point: &Tuple) -> Tuple {
         match self.kind {
             PatternsKind::Stripe => {
-                if (point.x.floor() as i64) % 2 == 0 {
+                if parity(point.x.floor()) {
                     return self.color_a.clone();
                 }
                 self.color_b.clone()
@@ -48,28 +82,64 @@ impl Pattern {
 
                 self.color_a.clone() + distance * fraction
             }
+            PatternsKind::GradientSmooth => {
+                let distance = self.color_b.clone() - self.color_a.clone();
+                // Triangle wave, 0 on even x and 1 on odd x
+                let fraction = 1.0 - (point.x.rem_euclid(2.0) - 1.0).abs();
+
+                self.color_a.clone() + distance * fraction
+            }
             PatternsKind::Ring => {
-                if (point.x.powi(2) + point.z.powi(2)).sqrt().floor() as i64 % 2 == 0 {
+                if parity((point.x.powi(2) + point.z.powi(2)).sqrt().floor()) {
                     return self.color_a.clone();
                 }
                 self.color_b.clone()
             }
             PatternsKind::Checker => {
-                if (point.x.abs() + point.y.abs() + point.z.abs()).floor() as i64 % 2 == 0 {
+                if parity(point.x.floor() + point.y.floor() + point.z.floor()) {
+                    return self.color_a.clone();
+                }
+                self.color_b.clone()
+            }
+            PatternsKind::Checker2D => {
+                if parity(point.x.floor() + point.z.floor()) {
+                    return self.color_a.clone();
+                }
+                self.color_b.clone()
+            }
+            PatternsKind::SphericalChecker { u_divs, v_divs } => {
+                let (u, v) = spherical_uv(point);
+                if parity((u * u_divs as f64).floor() + (v * v_divs as f64).floor()) {
                     return self.color_a.clone();
                 }
                 self.color_b.clone()
             }
+            PatternsKind::FractalNoise {
+                octaves,
+                persistence,
+                lacunarity,
+                seed,
+            } => {
+                let distance = self.color_b.clone() - self.color_a.clone();
+                let value = fractal_noise(point, octaves, persistence, lacunarity, seed);
+
+                self.color_a.clone() + distance * value
+            }
             PatternsKind::Test => Tuple::new_color(point.x, point.y, point.z),
         }
     }
 
-    #[cfg(test)]
     pub fn set_transformation(&mut self, transformation: Matrix) {
+        self.inverse_transformation = transformation.invert();
         self.transformation = transformation;
     }
 }
 
+// True when the whole number n is even, negative numbers included
+fn parity(n: f64) -> bool {
+    n.rem_euclid(2.0) == 0.0
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -192,6 +262,51 @@ mod tests {
         assert_eq!(Tuple::white(), c);
     }
 
+    #[test]
+    fn setting_the_transformation_stores_its_inverse_once() {
+        let mut pattern = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Test);
+        let transformation = Transformation::translation(1.0, 2.0, 3.0);
+        pattern.set_transformation(transformation.clone());
+
+        assert_eq!(pattern.inverse_transformation, transformation.invert());
+        assert_eq!(
+            pattern.color_at_world(&Tuple::new_point(1.5, 2.5, 3.5)),
+            Tuple::new_color(0.5, 0.5, 0.5)
+        );
+
+        // Sampling uses the stored inverse, the transformation is not inverted
+        // again
+        pattern.inverse_transformation = Matrix::identity(4);
+        assert_eq!(
+            pattern.color_at_world(&Tuple::new_point(1.5, 2.5, 3.5)),
+            Tuple::new_color(1.5, 2.5, 3.5)
+        );
+    }
+
+    #[test]
+    fn a_scaled_stripe_pattern_evaluated_in_world_space() {
+        let mut pattern = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Stripe);
+        pattern.set_transformation(Transformation::scaling(2.0, 2.0, 2.0));
+
+        // Bands are two units wide
+        assert_eq!(
+            pattern.color_at_world(&Tuple::new_point(0.5, 0.0, 0.0)),
+            Tuple::white()
+        );
+        assert_eq!(
+            pattern.color_at_world(&Tuple::new_point(1.5, 0.0, 0.0)),
+            Tuple::white()
+        );
+        assert_eq!(
+            pattern.color_at_world(&Tuple::new_point(2.5, 0.0, 0.0)),
+            Tuple::black()
+        );
+        assert_eq!(
+            pattern.color_at_world(&Tuple::new_point(-0.5, 0.0, 0.0)),
+            Tuple::black()
+        );
+    }
+
     #[test]
     fn a_gradient_linearly_interpolates_between_colors() {
         let pattern = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Gradient);
@@ -214,6 +329,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn a_smooth_gradient_goes_back_and_forth_between_colors() {
+        let pattern = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::GradientSmooth);
+
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(0.0, 0.0, 0.0)),
+            Tuple::white()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(1.0, 0.0, 0.0)),
+            Tuple::black()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(1.5, 0.0, 0.0)),
+            Tuple::new_color(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(2.0, 0.0, 0.0)),
+            Tuple::white()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(-0.25, 0.0, 0.0)),
+            Tuple::new_color(0.75, 0.75, 0.75)
+        );
+    }
+
     #[test]
     fn a_ring_should_extend_in_both_x_and_z() {
         let pattern = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Ring);
@@ -293,4 +434,158 @@ mod tests {
             Tuple::black()
         );
     }
+
+    #[test]
+    fn checkers_are_cubes_and_not_diagonal_bands() {
+        let pattern = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Checker);
+
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(0.5, 0.5, 0.5)),
+            Tuple::white()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(1.5, 1.5, 0.5)),
+            Tuple::white()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(-0.5, 0.0, 0.0)),
+            Tuple::black()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(-0.5, 0.0, -0.5)),
+            Tuple::white()
+        );
+    }
+
+    #[test]
+    fn planar_checkers_are_constant_in_y() {
+        let pattern = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Checker2D);
+
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(0.5, -0.01, 0.5)),
+            Tuple::white()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(0.5, 0.0, 0.5)),
+            Tuple::white()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(0.5, 1.5, 0.5)),
+            Tuple::white()
+        );
+    }
+
+    #[test]
+    fn planar_checkers_alternate_in_x_and_z() {
+        let pattern = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Checker2D);
+
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(0.99, 0.0, 0.0)),
+            Tuple::white()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(1.01, 0.0, 0.0)),
+            Tuple::black()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(0.0, 0.0, 1.01)),
+            Tuple::black()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(1.01, 0.0, 1.01)),
+            Tuple::white()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(-0.01, 0.0, 0.0)),
+            Tuple::black()
+        );
+    }
+
+    #[test]
+    fn parity_is_consistent_across_zero() {
+        assert!(parity(-2.0));
+        assert!(!parity(-1.0));
+        assert!(parity(0.0));
+        assert!(!parity(1.0));
+        assert!(parity(4.0));
+    }
+
+    #[test]
+    fn checkers_alternate_at_negative_coordinates() {
+        let pattern = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Checker);
+
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(-0.1, 0.0, 0.0)),
+            Tuple::black()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(-1.1, 0.0, 0.0)),
+            Tuple::white()
+        );
+        assert_eq!(
+            pattern.stripe_at(&Tuple::new_point(-1.1, -0.1, 0.0)),
+            Tuple::black()
+        );
+    }
+
+    #[test]
+    fn spherical_checkers_alternate_along_the_equator() {
+        let pattern = Pattern::stripe(
+            Tuple::white(),
+            Tuple::black(),
+            PatternsKind::SphericalChecker {
+                u_divs: 4,
+                v_divs: 2,
+            },
+        );
+
+        let at = |x: f64, y: f64, z: f64| pattern.stripe_at(&Tuple::new_point(x, y, z));
+
+        // A quarter turn around y moves to the next tile
+        assert_eq!(at(0.0, 0.1, 1.0), Tuple::black());
+        assert_eq!(at(1.0, 0.1, 0.0), Tuple::white());
+        assert_eq!(at(0.0, 0.1, -1.0), Tuple::black());
+        // Crossing the equator too
+        assert_eq!(at(0.0, -0.1, 1.0), Tuple::white());
+    }
+
+    #[test]
+    fn spherical_checkers_alternate_around_the_poles() {
+        let pattern = Pattern::stripe(
+            Tuple::white(),
+            Tuple::black(),
+            PatternsKind::SphericalChecker {
+                u_divs: 4,
+                v_divs: 2,
+            },
+        );
+
+        let at = |x: f64, y: f64, z: f64| pattern.stripe_at(&Tuple::new_point(x, y, z));
+
+        assert_eq!(at(0.01, 1.0, 0.0), Tuple::white());
+        assert_eq!(at(0.0, 1.0, 0.01), Tuple::black());
+        assert_eq!(at(0.01, -1.0, 0.0), Tuple::black());
+        assert_eq!(at(0.0, -1.0, 0.01), Tuple::white());
+    }
+
+    #[test]
+    fn fractal_noise_blends_between_the_two_colors() {
+        let pattern = Pattern::stripe(
+            Tuple::white(),
+            Tuple::black(),
+            PatternsKind::FractalNoise {
+                octaves: 4,
+                persistence: 0.5,
+                lacunarity: 2.0,
+                seed: 7,
+            },
+        );
+        let point = Tuple::new_point(0.3, 1.7, -2.4);
+
+        let gray = 1.0 - fractal_noise(&point, 4, 0.5, 2.0, 7);
+        assert_eq!(
+            pattern.stripe_at(&point),
+            Tuple::new_color(gray, gray, gray)
+        );
+    }
 }
@@ -0,0 +1,285 @@
+use std::f64::consts::PI;
+
+use crate::core::tuples::Tuple;
+
+// How a point in object space is flattened to (u, v) in [0, 1]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UvMapping {
+    Planar,
+    Spherical,
+    Cylindrical,
+}
+
+impl UvMapping {
+    pub fn uv(&self, point: &Tuple) -> (f64, f64) {
+        match self {
+            UvMapping::Planar => planar_uv(point),
+            UvMapping::Spherical => spherical_uv(point),
+            UvMapping::Cylindrical => cylinder_uv(point),
+        }
+    }
+
+    // Direction in which u grows at the point, dP/du up to its length. Around
+    // the y axis u grows clockwise as seen from above, and on the axis itself,
+    // where u is undefined, x is used.
+    pub fn tangent(&self, point: &Tuple) -> Tuple {
+        match self {
+            UvMapping::Planar => Tuple::new_vector(1.0, 0.0, 0.0),
+            UvMapping::Spherical | UvMapping::Cylindrical => {
+                if point.x == 0.0 && point.z == 0.0 {
+                    Tuple::new_vector(1.0, 0.0, 0.0)
+                } else {
+                    Tuple::new_vector(-point.z, 0.0, point.x)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CubeFace {
+    Front,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+pub fn cube_face(point: &Tuple) -> CubeFace {
+    let coord = point.x.abs().max(point.y.abs()).max(point.z.abs());
+
+    if coord == point.x {
+        CubeFace::Right
+    } else if coord == -point.x {
+        CubeFace::Left
+    } else if coord == point.y {
+        CubeFace::Up
+    } else if coord == -point.y {
+        CubeFace::Down
+    } else if coord == point.z {
+        CubeFace::Front
+    } else {
+        CubeFace::Back
+    }
+}
+
+// Maps a point on the unit cube to the face it lies on and to (u, v) in [0, 1]
+// inside that face, as seen when looking at the face from outside the cube.
+pub fn cube_uv(point: &Tuple) -> (CubeFace, f64, f64) {
+    let face = cube_face(point);
+
+    let (u, v) = match face {
+        CubeFace::Front => (wrap(point.x + 1.0), wrap(point.y + 1.0)),
+        CubeFace::Back => (wrap(1.0 - point.x), wrap(point.y + 1.0)),
+        CubeFace::Left => (wrap(point.z + 1.0), wrap(point.y + 1.0)),
+        CubeFace::Right => (wrap(1.0 - point.z), wrap(point.y + 1.0)),
+        CubeFace::Up => (wrap(point.x + 1.0), wrap(1.0 - point.z)),
+        CubeFace::Down => (wrap(point.x + 1.0), wrap(point.z + 1.0)),
+    };
+
+    (face, u, v)
+}
+
+// The x/z plane, repeating every unit in both directions
+pub fn planar_uv(point: &Tuple) -> (f64, f64) {
+    (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0))
+}
+
+// u follows the longitude around the y axis and v the latitude, from the
+// south pole (0) to the north pole (1)
+pub fn spherical_uv(point: &Tuple) -> (f64, f64) {
+    let theta = point.x.atan2(point.z);
+    let radius = (point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt();
+    let phi = (point.y / radius).acos();
+
+    let raw_u = theta / (2.0 * PI);
+    let u = 1.0 - (raw_u + 0.5);
+    let v = 1.0 - phi / PI;
+
+    (u, v)
+}
+
+// u follows the angle around the y axis, v repeats every unit of height
+pub fn cylinder_uv(point: &Tuple) -> (f64, f64) {
+    let theta = point.x.atan2(point.z);
+    let raw_u = theta / (2.0 * PI);
+    let u = 1.0 - (raw_u + 0.5);
+    let v = point.y.rem_euclid(1.0);
+
+    (u, v)
+}
+
+fn wrap(value: f64) -> f64 {
+    value.rem_euclid(2.0) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    use super::*;
+
+    #[test]
+    fn identifying_the_face_of_a_cube_from_a_point() {
+        assert_eq!(
+            cube_face(&Tuple::new_point(-1.0, 0.5, -0.25)),
+            CubeFace::Left
+        );
+        assert_eq!(
+            cube_face(&Tuple::new_point(1.1, -0.75, 0.8)),
+            CubeFace::Right
+        );
+        assert_eq!(cube_face(&Tuple::new_point(0.1, 0.6, 0.9)), CubeFace::Front);
+        assert_eq!(
+            cube_face(&Tuple::new_point(-0.7, 0.0, -2.0)),
+            CubeFace::Back
+        );
+        assert_eq!(cube_face(&Tuple::new_point(0.5, 1.0, 0.9)), CubeFace::Up);
+        assert_eq!(
+            cube_face(&Tuple::new_point(-0.2, -1.3, 1.1)),
+            CubeFace::Down
+        );
+    }
+
+    fn uv_mapping_on_a_cube(point: Tuple, face: CubeFace, u: f64, v: f64) {
+        assert_eq!(cube_uv(&point), (face, u, v));
+    }
+
+    #[test]
+    fn uv_mapping_the_front_face_of_a_cube() {
+        uv_mapping_on_a_cube(
+            Tuple::new_point(-0.5, 0.5, 1.0),
+            CubeFace::Front,
+            0.25,
+            0.75,
+        );
+        uv_mapping_on_a_cube(
+            Tuple::new_point(0.5, -0.5, 1.0),
+            CubeFace::Front,
+            0.75,
+            0.25,
+        );
+    }
+
+    #[test]
+    fn uv_mapping_the_back_face_of_a_cube() {
+        uv_mapping_on_a_cube(Tuple::new_point(0.5, 0.5, -1.0), CubeFace::Back, 0.25, 0.75);
+        uv_mapping_on_a_cube(
+            Tuple::new_point(-0.5, -0.5, -1.0),
+            CubeFace::Back,
+            0.75,
+            0.25,
+        );
+    }
+
+    #[test]
+    fn uv_mapping_the_left_face_of_a_cube() {
+        uv_mapping_on_a_cube(
+            Tuple::new_point(-1.0, 0.5, -0.5),
+            CubeFace::Left,
+            0.25,
+            0.75,
+        );
+        uv_mapping_on_a_cube(
+            Tuple::new_point(-1.0, -0.5, 0.5),
+            CubeFace::Left,
+            0.75,
+            0.25,
+        );
+    }
+
+    #[test]
+    fn uv_mapping_the_right_face_of_a_cube() {
+        uv_mapping_on_a_cube(Tuple::new_point(1.0, 0.5, 0.5), CubeFace::Right, 0.25, 0.75);
+        uv_mapping_on_a_cube(
+            Tuple::new_point(1.0, -0.5, -0.5),
+            CubeFace::Right,
+            0.75,
+            0.25,
+        );
+    }
+
+    #[test]
+    fn uv_mapping_the_upper_face_of_a_cube() {
+        uv_mapping_on_a_cube(Tuple::new_point(-0.5, 1.0, -0.5), CubeFace::Up, 0.25, 0.75);
+        uv_mapping_on_a_cube(Tuple::new_point(0.5, 1.0, 0.5), CubeFace::Up, 0.75, 0.25);
+    }
+
+    #[test]
+    fn uv_mapping_the_lower_face_of_a_cube() {
+        uv_mapping_on_a_cube(
+            Tuple::new_point(-0.5, -1.0, 0.5),
+            CubeFace::Down,
+            0.25,
+            0.75,
+        );
+        uv_mapping_on_a_cube(
+            Tuple::new_point(0.5, -1.0, -0.5),
+            CubeFace::Down,
+            0.75,
+            0.25,
+        );
+    }
+
+    #[test]
+    fn uv_mapping_a_sphere_and_a_plane() {
+        let (u, v) = spherical_uv(&Tuple::new_point(0.0, 0.0, -1.0));
+        assert_eq!((u, v), (0.0, 0.5));
+        let (u, v) = spherical_uv(&Tuple::new_point(1.0, 0.0, 0.0));
+        assert!((u - 0.25).abs() < 1e-10 && (v - 0.5).abs() < 1e-10);
+        let (_, v) = spherical_uv(&Tuple::new_point(0.0, 1.0, 0.0));
+        assert_eq!(v, 1.0);
+
+        assert_eq!(planar_uv(&Tuple::new_point(0.25, 0.0, 0.5)), (0.25, 0.5));
+        assert_eq!(
+            planar_uv(&Tuple::new_point(-0.25, 0.0, -1.25)),
+            (0.75, 0.75)
+        );
+    }
+
+    fn uv_mapping_on_a_cylinder(point: Tuple, u: f64, v: f64) {
+        let (actual_u, actual_v) = cylinder_uv(&point);
+
+        assert!((actual_u - u).abs() < 1e-10);
+        assert!((actual_v - v).abs() < 1e-10);
+    }
+
+    #[test]
+    fn uv_mapping_a_point_on_a_cylinder_at_angle_0() {
+        uv_mapping_on_a_cylinder(Tuple::new_point(0.0, 0.0, -1.0), 0.0, 0.0);
+        uv_mapping_on_a_cylinder(Tuple::new_point(0.0, 0.5, -1.0), 0.0, 0.5);
+        uv_mapping_on_a_cylinder(Tuple::new_point(0.0, 1.0, -1.0), 0.0, 0.0);
+    }
+
+    #[test]
+    fn uv_mapping_points_around_a_cylinder() {
+        uv_mapping_on_a_cylinder(
+            Tuple::new_point(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2),
+            0.125,
+            0.5,
+        );
+        uv_mapping_on_a_cylinder(Tuple::new_point(1.0, 0.5, 0.0), 0.25, 0.5);
+        uv_mapping_on_a_cylinder(Tuple::new_point(0.0, -0.25, 1.0), 0.5, 0.75);
+        uv_mapping_on_a_cylinder(Tuple::new_point(-1.0, 1.25, 0.0), 0.75, 0.25);
+    }
+
+    #[test]
+    fn the_tangent_follows_the_growing_u() {
+        let step = 1e-6;
+        for point in [
+            Tuple::new_point(0.3, 0.2, -0.9),
+            Tuple::new_point(-0.6, -0.4, 0.5),
+            Tuple::new_point(1.0, 0.0, 0.001),
+        ] {
+            for mapping in [UvMapping::Spherical, UvMapping::Cylindrical] {
+                let next = &point + &(mapping.tangent(&point) * step);
+                assert!(mapping.uv(&next).0 > mapping.uv(&point).0);
+            }
+        }
+
+        let next = Tuple::new_point(0.25 + 1e-6, 0.0, 0.5);
+        assert!(planar_uv(&next).0 > 0.25);
+    }
+}
@@ -4,11 +4,27 @@ use crate::{core::matrices::Matrix, core::tuples::Tuple};
 pub struct Ray {
     origin: Tuple,
     direction: Tuple,
+    // Instant of the frame exposure in [0, 1) the ray is cast at, moving
+    // shapes are intersected where they are at that time
+    time: f64,
 }
 
 impl Ray {
     pub fn new(origin: Tuple, direction: Tuple) -> Ray {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+        }
+    }
+
+    pub fn with_time(mut self, time: f64) -> Ray {
+        self.time = time;
+        self
+    }
+
+    pub fn get_time(&self) -> f64 {
+        self.time
     }
 
     pub fn get_origin(&self) -> Tuple {
@@ -27,6 +43,7 @@ impl Ray {
         Ray {
             origin: t * &self.origin,
             direction: t * &self.direction,
+            time: self.time,
         }
     }
 }
@@ -0,0 +1,59 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    core::transformations::Transformation,
+    core::tuples::Tuple,
+    materials::Material,
+    shapes::planes::Plane,
+    shapes::{cones::Cone, Shape},
+};
+
+use super::{lights::PointLight, world::World, Scenario, ScenarioOptions};
+
+const NAME: &str = "Cone Lamp";
+pub struct ConeLamp {}
+
+impl ConeLamp {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(_options: &ScenarioOptions) -> Scenario {
+        Scenario { world: draw() }
+    }
+
+    pub fn name() -> String {
+        NAME.to_owned()
+    }
+}
+
+pub fn draw() -> World {
+    let mut floor = Shape::default(Arc::new(Mutex::new(Plane::new())));
+    let mut floor_material = Material::default();
+    floor_material.set_color(Tuple::new_color(0.3, 0.3, 0.35));
+    floor_material.set_specular(0.0);
+    floor_material.set_reflective(0.4);
+    floor.set_material(floor_material);
+    floor.precompute_inverse_transformation();
+
+    // A truncated, upside-down cone: the wide cap sits at the bottom like a lampshade
+    let mut cone = Cone::new();
+    cone.set_minimum(-1.0);
+    cone.set_maximum(-0.25);
+    cone.set_closed(true);
+
+    let mut shade = Shape::default(Arc::new(Mutex::new(cone)));
+    shade.set_transformation(Transformation::translation(0.0, 1.5, 0.0));
+    let mut shade_material = Material::default();
+    shade_material.set_color(Tuple::new_color(1.0, 0.85, 0.4));
+    shade_material.set_diffuse(0.7);
+    shade_material.set_specular(0.3);
+    shade.set_material(shade_material);
+    shade.precompute_inverse_transformation();
+
+    let mut world = World::new();
+    world.set_light(PointLight::new(
+        Tuple::new_color(1.0, 0.9, 0.7),
+        Tuple::new_point(-4.0, 6.0, -6.0),
+    ));
+    world.add_shapes(&[floor, shade]);
+
+    world
+}
@@ -5,19 +5,33 @@ use std::{
 
 use crate::{
     core::transformations::Transformation,
+    core::tuples::Tuple,
+    materials::Material,
     shapes::groups::Group,
+    shapes::planes::Plane,
     shapes::spheres::Sphere,
     shapes::{cylinders::Cylinder, Shape},
 };
 
-use super::{world::World, Scenario};
+use super::{world::World, Scenario, ScenarioOptions};
 
 const NAME: &str = "Hexagon";
 
+// One color per side, going around the hexagon
+const SIDE_COLORS: [(f64, f64, f64); 6] = [
+    (0.9, 0.2, 0.2),
+    (0.9, 0.6, 0.1),
+    (0.9, 0.9, 0.2),
+    (0.2, 0.8, 0.3),
+    (0.2, 0.5, 0.9),
+    (0.6, 0.3, 0.9),
+];
+
 pub struct Hexagon {}
 
 impl Hexagon {
-    pub fn new() -> Scenario {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(_options: &ScenarioOptions) -> Scenario {
         let mut hex = Group::new();
         let parent_id = 0;
 
@@ -26,6 +40,7 @@ impl Hexagon {
         }
 
         let mut world = World::new();
+        world.add_shape(floor());
         world.add_group(hex);
 
         Scenario { world }
@@ -36,22 +51,45 @@ impl Hexagon {
     }
 }
 
-fn hexagon_corner(parent_id: usize) -> Shape {
+// Mirror-like floor right below the hexagon, so that it shows in reflections
+fn floor() -> Shape {
+    let mut floor = Shape::default(Arc::new(Mutex::new(Plane::new())));
+    floor.set_transformation(Transformation::translation(0.0, -0.25, 0.0));
+    let mut material = Material::default();
+    material.set_color(Tuple::new_color(0.2, 0.2, 0.25));
+    material.set_specular(0.0);
+    material.set_reflective(0.4);
+    floor.set_material(material);
+    floor.precompute_inverse_transformation();
+
+    floor
+}
+
+fn hexagon_corner(color: Tuple) -> Shape {
     let mut corner = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+    let mut material = Material::default();
+    material.set_color(color);
+    material.set_diffuse(0.6);
+    material.set_reflective(0.2);
+    corner.set_material(material);
     corner.set_transformation(
         Transformation::translation(0.0, 0.0, -1.0) * Transformation::scaling(0.25, 0.25, 0.25),
     );
     corner.precompute_inverse_transformation();
-    corner.set_parent_id(parent_id);
 
     corner
 }
 
-fn hexagon_edge(parent_id: usize) -> Shape {
+fn hexagon_edge(color: Tuple) -> Shape {
     let mut cylinder = Cylinder::new();
     cylinder.set_minimum(0.0);
     cylinder.set_maximum(1.0);
+    cylinder.set_closed(true);
     let mut edge = Shape::default(Arc::new(Mutex::new(cylinder)));
+    let mut material = Material::default();
+    material.set_color(color);
+    material.set_specular(0.3);
+    edge.set_material(material);
     edge.set_transformation(
         Transformation::translation(0.0, 0.0, -1.0)
             * Transformation::rotation_y(-PI / 6.0)
@@ -59,14 +97,20 @@ fn hexagon_edge(parent_id: usize) -> Shape {
             * Transformation::scaling(0.25, 1.00, 0.25),
     );
     edge.precompute_inverse_transformation();
-    edge.set_parent_id(parent_id);
 
     edge
 }
 
 fn hexagon_side(g: &mut Group, parent_id: usize, n: usize) {
     let rotation = Transformation::rotation_y(n as f64 * (PI / 3.0));
-    let matrix_id = g.add_matrix(rotation, Some(parent_id));
-    g.add_node(hexagon_corner(matrix_id), Some(matrix_id));
-    g.add_node(hexagon_edge(matrix_id), Some(matrix_id));
+    let (red, green, blue) = SIDE_COLORS[n % SIDE_COLORS.len()];
+    let color = Tuple::new_color(red, green, blue);
+    g.with_transform(rotation, Some(parent_id), |side, side_id| {
+        // Corners are a lighter shade of the edge leaving from them
+        side.add_node(
+            hexagon_corner((color.clone() + Tuple::white()) / 2.0),
+            Some(side_id),
+        );
+        side.add_node(hexagon_edge(color), Some(side_id));
+    });
 }
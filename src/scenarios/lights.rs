@@ -1,9 +1,41 @@
-use crate::core::tuples::Tuple;
+use std::{any::Any, f64::consts::PI, fmt::Debug};
+
+use crate::{core::sampler::Sampler, core::tuples::Tuple, materials::patterns::Pattern};
+
+// Anything that lights a world. Shading only asks a light where it is seen from
+// and how much of it arrives, shadows are cast by the world along the rays
+// returned by shadow_rays.
+pub trait Light: Any + Debug + Send + Sync {
+    // Color and brightness of the light, the ambient term is based on it
+    fn get_intensity(&self) -> Tuple;
+
+    // Light arriving at the point when nothing is in the way
+    fn intensity_at(&self, point: &Tuple) -> Tuple;
+
+    // Unit vector from the point towards the light
+    fn direction_from(&self, point: &Tuple) -> Tuple;
+
+    // Directions of the shadow rays cast from the point, each with the
+    // distance at which the ray reaches the light. By default a single ray
+    // towards a light infinitely far away.
+    fn shadow_rays(&self, point: &Tuple, _sampler: &mut Sampler) -> Vec<(Tuple, f64)> {
+        vec![(self.direction_from(point), f64::INFINITY)]
+    }
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct PointLight {
     intensity: Tuple,
     position: Tuple,
+    // Pattern projected by the light, like a gobo in front of a spotlight
+    gobo: Option<Pattern>,
+    // Shadows are cast from `soft_samples` points jittered inside a ball of
+    // this radius around the position, 0 casts hard shadows
+    soft_radius: f64,
+    soft_samples: usize,
+    // Whether the points of a soft light are stratified over the ball instead
+    // of jittered one independently of the other, see shadow_positions
+    stratified_shadows: bool,
 }
 
 impl PointLight {
@@ -11,21 +43,135 @@ impl PointLight {
         PointLight {
             intensity,
             position,
+            gobo: None,
+            soft_radius: 0.0,
+            soft_samples: 16,
+            stratified_shadows: false,
+        }
+    }
+
+    pub fn get_position_ref(&self) -> &Tuple {
+        &self.position
+    }
+
+    pub fn set_gobo(&mut self, gobo: Pattern) {
+        self.gobo = Some(gobo);
+    }
+
+    pub fn get_soft_radius(&self) -> f64 {
+        self.soft_radius
+    }
+
+    pub fn set_soft_radius(&mut self, soft_radius: f64) {
+        self.soft_radius = soft_radius;
+    }
+
+    pub fn set_soft_samples(&mut self, soft_samples: usize) {
+        assert!(soft_samples > 0, "A soft light needs at least one sample");
+        self.soft_samples = soft_samples;
+    }
+
+    pub fn set_stratified_shadows(&mut self, stratified_shadows: bool) {
+        self.stratified_shadows = stratified_shadows;
+    }
+
+    // Points the shadow rays aim at, the position alone for hard shadows
+    pub fn shadow_positions(&self, sampler: &mut Sampler) -> Vec<Tuple> {
+        if self.soft_radius <= 0.0 {
+            return vec![self.position.clone()];
+        }
+
+        if self.stratified_shadows {
+            return self.stratified_positions(sampler);
         }
+
+        (0..self.soft_samples)
+            .map(|_| loop {
+                let offset = Tuple::new_vector(
+                    2.0 * sampler.next_f64() - 1.0,
+                    2.0 * sampler.next_f64() - 1.0,
+                    2.0 * sampler.next_f64() - 1.0,
+                );
+                if offset.magnitude() <= 1.0 {
+                    break &self.position + &(offset * self.soft_radius);
+                }
+            })
+            .collect()
     }
 
-    pub fn get_intensity(&self) -> Tuple {
+    // Points uniformly distributed over the ball as the jittered ones, but
+    // stratified as a Latin hypercube over the cube of the radius, the height
+    // and the angle around the y axis: each of the three is split in one slice
+    // per sample and every slice is used once. The points cover the ball
+    // evenly for any count, which lowers the noise in the penumbrae.
+    fn stratified_positions(&self, sampler: &mut Sampler) -> Vec<Tuple> {
+        let count = self.soft_samples;
+        let radii = sampler.permutation(count);
+        let heights = sampler.permutation(count);
+        let angles = sampler.permutation(count);
+
+        (0..count)
+            .map(|i| {
+                let mut slice = |slice: usize| (slice as f64 + sampler.next_f64()) / count as f64;
+                let radius = self.soft_radius * slice(radii[i]).cbrt();
+                let y = 1.0 - 2.0 * slice(heights[i]);
+                let angle = 2.0 * PI * slice(angles[i]);
+
+                let ring = (1.0 - y * y).sqrt();
+                let offset = Tuple::new_vector(ring * angle.cos(), y, ring * angle.sin());
+                &self.position + &(offset * radius)
+            })
+            .collect()
+    }
+}
+
+impl Light for PointLight {
+    fn get_intensity(&self) -> Tuple {
         self.intensity.clone()
     }
 
-    pub fn get_position_ref(&self) -> &Tuple {
-        &self.position
+    // Intensity reaching the point. The gobo is a slide one unit below the
+    // light: the ray from the light to the point crosses it on the y = -1
+    // plane of the light space, where the pattern is sampled. Points that are
+    // not below the light receive no light through the gobo.
+    fn intensity_at(&self, point: &Tuple) -> Tuple {
+        let gobo = match &self.gobo {
+            None => return self.intensity.clone(),
+            Some(gobo) => gobo,
+        };
+
+        let direction = point - &self.position;
+        if direction.y >= 0.0 {
+            return Tuple::black();
+        }
+
+        let depth = -direction.y;
+        let slide_point = Tuple::new_point(direction.x / depth, -1.0, direction.z / depth);
+
+        self.intensity
+            .hadamard_product(&gobo.color_at_world(&slide_point))
+    }
+
+    fn direction_from(&self, point: &Tuple) -> Tuple {
+        (&self.position - point).normalize()
+    }
+
+    fn shadow_rays(&self, point: &Tuple, sampler: &mut Sampler) -> Vec<(Tuple, f64)> {
+        self.shadow_positions(sampler)
+            .iter()
+            .map(|position| {
+                let v = position - point;
+                (v.normalize(), v.magnitude())
+            })
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use crate::materials::patterns::PatternsKind;
+
     use super::*;
 
     #[test]
@@ -38,4 +184,72 @@ mod tests {
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
     }
+
+    #[test]
+    fn a_gobo_projects_its_pattern_below_the_light() {
+        let mut light = PointLight::new(Tuple::white(), Tuple::new_point(0.0, 2.0, 0.0));
+        light.set_gobo(Pattern::stripe(
+            Tuple::white(),
+            Tuple::black(),
+            PatternsKind::Stripe,
+        ));
+
+        // Twice as far as the slide, the stripes are twice as wide
+        assert_eq!(
+            light.intensity_at(&Tuple::new_point(1.0, 0.0, 0.0)),
+            Tuple::white()
+        );
+        assert_eq!(
+            light.intensity_at(&Tuple::new_point(3.0, 0.0, 0.0)),
+            Tuple::black()
+        );
+        assert_eq!(
+            light.intensity_at(&Tuple::new_point(1.0, 3.0, 0.0)),
+            Tuple::black()
+        );
+    }
+
+    #[test]
+    fn shadow_positions_of_a_soft_light_lie_around_it() {
+        let position = Tuple::new_point(1.0, 2.0, 3.0);
+        let mut light = PointLight::new(Tuple::white(), position.clone());
+        let mut sampler = Sampler::new(5);
+
+        assert_eq!(light.shadow_positions(&mut sampler), vec![position.clone()]);
+
+        light.set_soft_radius(0.5);
+        let positions = light.shadow_positions(&mut sampler);
+        assert_eq!(positions.len(), 16);
+        for p in positions {
+            assert!((&p - &position).magnitude() <= 0.5);
+        }
+    }
+
+    #[test]
+    fn stratified_shadow_positions_cover_the_ball_evenly() {
+        let position = Tuple::new_point(1.0, 2.0, 3.0);
+        let mut light = PointLight::new(Tuple::white(), position.clone());
+        light.set_soft_radius(2.0);
+        light.set_soft_samples(64);
+        light.set_stratified_shadows(true);
+
+        let positions = light.shadow_positions(&mut Sampler::new(5));
+        assert_eq!(positions.len(), 64);
+
+        // One point per slice of the volume, counted from the center, and per
+        // slice of the height
+        let mut volumes = vec![];
+        let mut heights = vec![];
+        for p in &positions {
+            let offset = (p - &position) / 2.0;
+            assert!(offset.magnitude() <= 1.0);
+            volumes.push((offset.magnitude().powi(3) * 64.0) as usize);
+            let y = offset.y / offset.magnitude();
+            heights.push(((1.0 - y) / 2.0 * 64.0) as usize);
+        }
+        volumes.sort();
+        heights.sort();
+        assert_eq!(volumes, (0..64).collect::<Vec<_>>());
+        assert_eq!(heights, (0..64).collect::<Vec<_>>());
+    }
 }
@@ -1,29 +1,69 @@
+mod cone_lamp;
 mod hexagon;
 pub mod lights;
 mod three_spheres;
 mod transparent_cube;
 pub mod world;
 
+use std::fmt;
+
 use self::{
-    hexagon::Hexagon, three_spheres::ThreeSpheres, transparent_cube::TransparentCube, world::World,
+    cone_lamp::ConeLamp, hexagon::Hexagon, three_spheres::ThreeSpheres,
+    transparent_cube::TransparentCube, world::World,
 };
 
 pub struct Scenario {
     world: World,
 }
 
+// Knobs shared by every scenario, each scenario uses the ones that make sense
+// for it and ignores the others.
+#[derive(Clone, Debug, Default)]
+pub struct ScenarioOptions {
+    // Seed for the scenarios with random variations, None keeps the fixed layout
+    pub seed: Option<u64>,
+}
+
+// Returned when asking for a scenario that is not in Scenario::list
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioError {
+    name: String,
+}
+
+impl ScenarioError {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no scenario defined for name {}", self.name)
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
 impl Scenario {
-    pub fn get(name: &str) -> Scenario {
+    pub fn get(name: &str) -> Result<Scenario, ScenarioError> {
+        Scenario::get_with(name, &ScenarioOptions::default())
+    }
+
+    pub fn get_with(name: &str, options: &ScenarioOptions) -> Result<Scenario, ScenarioError> {
         match name {
-            "Hexagon" => Hexagon::new(),
-            "Three Spheres" => ThreeSpheres::new(),
-            "Transparent Cube" => TransparentCube::new(),
-            _ => panic!("no scenario defined for name"),
+            "Cone Lamp" => Ok(ConeLamp::new(options)),
+            "Hexagon" => Ok(Hexagon::new(options)),
+            "Three Spheres" => Ok(ThreeSpheres::new(options)),
+            "Transparent Cube" => Ok(TransparentCube::new(options)),
+            _ => Err(ScenarioError {
+                name: name.to_owned(),
+            }),
         }
     }
 
     pub fn list() -> Vec<String> {
         vec![
+            ConeLamp::name(),
             Hexagon::name(),
             ThreeSpheres::name(),
             TransparentCube::name(),
@@ -34,3 +74,77 @@ impl Scenario {
         &mut self.world
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{core::tuples::Tuple, rays::Ray, scenarios::lights::PointLight};
+
+    use super::*;
+
+    #[test]
+    fn the_cone_lamp_scenario_is_registered() {
+        assert!(Scenario::list().contains(&"Cone Lamp".to_owned()));
+    }
+
+    #[test]
+    fn asking_for_an_unknown_scenario_is_an_error() {
+        let error = Scenario::get("Four Spheres").err().unwrap();
+
+        assert_eq!(error.get_name(), "Four Spheres");
+        assert_eq!(
+            error.to_string(),
+            "no scenario defined for name Four Spheres"
+        );
+    }
+
+    #[test]
+    fn the_cone_lamp_scenario_builds_a_floor_and_a_shade() {
+        let mut scenario = Scenario::get("Cone Lamp").unwrap();
+
+        assert_eq!(scenario.get_world().len(), 2);
+    }
+
+    #[test]
+    fn the_hexagon_scenario_stands_on_a_reflective_floor() {
+        let mut scenario = Scenario::get("Hexagon").unwrap();
+        let world = scenario.get_world();
+
+        // The floor is the only top level object, the hexagon is the world group
+        assert_eq!(world.len(), 1);
+        assert!(world.shape(0).unwrap().get_material().get_reflective() > 0.0);
+
+        let down = Tuple::new_vector(0.0, -1.0, 0.0);
+        let on_the_floor = world
+            .cast(&Ray::new(Tuple::new_point(3.0, 5.0, 0.0), down.clone()))
+            .unwrap();
+        assert_eq!(on_the_floor.get_object_id(), Some(0));
+
+        let on_a_corner = world
+            .cast(&Ray::new(Tuple::new_point(0.0, 5.0, -1.0), down))
+            .unwrap();
+        assert_eq!(on_a_corner.get_object_id(), None);
+    }
+
+    fn middle_sphere_color(seed: Option<u64>) -> Tuple {
+        let mut scenario = Scenario::get_with("Three Spheres", &ScenarioOptions { seed }).unwrap();
+        scenario.get_world().set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+        let r = Ray::new(
+            Tuple::new_point(-0.5, 1.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        scenario.get_world().color_at(&r, 5)
+    }
+
+    #[test]
+    fn seeded_three_spheres_scenarios_are_reproducible() {
+        assert_eq!(middle_sphere_color(Some(1)), middle_sphere_color(Some(1)));
+        assert_ne!(middle_sphere_color(Some(1)), middle_sphere_color(Some(2)));
+        assert_eq!(middle_sphere_color(None), middle_sphere_color(None));
+        assert_ne!(middle_sphere_color(None), middle_sphere_color(Some(1)));
+    }
+}
@@ -10,13 +10,14 @@ use crate::{
     shapes::{cubes::Cube, Shape},
 };
 
-use super::{world::World, Scenario};
+use super::{world::World, Scenario, ScenarioOptions};
 
 const NAME: &str = "Transparent Cube";
 pub struct TransparentCube {}
 
 impl TransparentCube {
-    pub fn new() -> Scenario {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(_options: &ScenarioOptions) -> Scenario {
         Scenario { world: draw() }
     }
 
@@ -30,7 +31,9 @@ pub fn draw() -> World {
     let mut floor_material = Material::default();
     floor_material.set_color(Tuple::new_color(1.0, 0.9, 0.9));
     floor_material.set_specular(0.0);
-    let floor_pattern = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Ring);
+    // Rings half a unit wide, so that several of them show through the cube
+    let mut floor_pattern = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Ring);
+    floor_pattern.set_transformation(Transformation::scaling(0.5, 0.5, 0.5));
     floor_material.set_pattern(floor_pattern);
     floor.set_material(floor_material.clone());
     floor.precompute_inverse_transformation();
@@ -1,121 +1,720 @@
+use std::{
+    f64::consts::PI,
+    sync::{Arc, Mutex},
+};
+
 use float_cmp::ApproxEq;
 
 use crate::{
+    core::sampler::Sampler,
+    core::transformations::Transformation,
     core::tuples::Tuple,
     margin::Margin,
+    materials::Material,
     rays::Ray,
     shapes::groups::Group,
-    shapes::intersections::{Computations, Intersection},
+    shapes::intersections::{
+        walk_boundaries, Computations, ComputationsSettings, Intersection, DEFAULT_EPSILON,
+    },
     shapes::objects::Objects,
-    shapes::Shape,
+    shapes::spheres::Sphere,
+    shapes::{intersection_tests, Shape},
 };
 
-use super::lights::PointLight;
+use super::lights::{Light, PointLight};
+
+// Nearest hit of a ray, without any shading. The object id is the one returned
+// by World::add_shape and is None for shapes that belong to the world group.
+#[derive(Clone, Debug)]
+pub struct HitInfo {
+    object_id: Option<usize>,
+    t: f64,
+    point: Tuple,
+    normal: Tuple,
+}
+
+impl HitInfo {
+    pub fn get_object_id(&self) -> Option<usize> {
+        self.object_id
+    }
+
+    pub fn get_t(&self) -> f64 {
+        self.t
+    }
+
+    pub fn get_point_ref(&self) -> &Tuple {
+        &self.point
+    }
+
+    pub fn get_normal_ref(&self) -> &Tuple {
+        &self.normal
+    }
+}
+
+// Counters collected while rendering, see World::start_stats
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    rays: usize,
+    intersection_tests: usize,
+    max_depth: usize,
+}
+
+impl RenderStats {
+    // Camera, reflected, refracted and shadow rays
+    pub fn get_rays(&self) -> usize {
+        self.rays
+    }
+
+    // Ray against shape tests, a mesh counts as one shape
+    pub fn get_intersection_tests(&self) -> usize {
+        self.intersection_tests
+    }
+
+    // Deepest bounce reached, 0 when no ray was reflected or refracted
+    pub fn get_max_depth(&self) -> usize {
+        self.max_depth
+    }
+}
+
+// What a ray is cast for. Shadow rays go through the objects that do not cast
+// shadows.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum IntersectPurpose {
+    #[default]
+    Camera,
+    Shadow,
+}
 
 pub struct World {
-    light: Option<PointLight>,
+    light: Option<Box<dyn Light>>,
     objects: Vec<Objects>,
     group: Group,
+    ambient_refractive_index: f64,
+    // Top and bottom colors seen by rays that miss everything
+    sky_gradient: Option<(Tuple, Tuple)>,
+    stats: Option<RenderStats>,
+    epsilon: f64,
+    parallel: bool,
+    colored_shadows: bool,
+    // Rays cast around a shaded point to dim its ambient light, 0 disables it
+    ao_samples: usize,
+    ao_radius: f64,
+    // Shading returns the bare surface color, see Camera::set_render_mode
+    unlit: bool,
+    // Bounces of the ray being traced, 0 for a camera ray
+    depth: usize,
+    // Color channel the ray carries alone once a dispersive surface split it
+    channel: Option<usize>,
+}
+
+/// The two concentric spheres lit from the upper left used throughout the
+/// book, handy for quick renders and benchmarks.
+///
+/// ```
+/// use std::f64::consts::PI;
+///
+/// use ray_tracer_rs::{
+///     camera::{Camera, RenderSettings},
+///     core::tuples::Tuple,
+///     scenarios::world::World,
+/// };
+///
+/// let mut world = World::default();
+/// let mut camera = Camera::new(1, 1, PI / 2.0);
+/// camera.look_at(
+///     Tuple::new_point(0.0, 0.0, -5.0),
+///     Tuple::new_point(0.0, 0.0, 0.0),
+///     Tuple::new_vector(0.0, 1.0, 0.0),
+/// );
+///
+/// let pixel = camera
+///     .render(&mut world, &RenderSettings::default())
+///     .pixel_at(0, 0);
+/// assert!((pixel.x - 0.38066).abs() < 1e-5);
+/// assert!((pixel.y - 0.47583).abs() < 1e-5);
+/// assert!((pixel.z - 0.28550).abs() < 1e-5);
+/// ```
+impl Default for World {
+    fn default() -> World {
+        let light = PointLight::new(Tuple::white(), Tuple::new_point(-10.0, 10.0, -10.0));
+
+        let sphere = Sphere::new();
+        let mut s1 = Shape::default(Arc::new(Mutex::new(sphere)));
+        let mut m = Material::default();
+        m.set_color(Tuple::new_color(0.8, 1.0, 0.6));
+        m.set_diffuse(0.7);
+        m.set_specular(0.2);
+        s1.set_material(m);
+
+        let sphere = Sphere::new();
+        let mut s2 = Shape::default(Arc::new(Mutex::new(sphere)));
+        s2.set_transformation(Transformation::scaling(0.5, 0.5, 0.5));
+
+        World {
+            light: Some(Box::new(light)),
+            objects: vec![Objects::Shape(Box::new(s1)), Objects::Shape(Box::new(s2))],
+            group: Group::new(),
+            ambient_refractive_index: 1.0,
+            sky_gradient: None,
+            stats: None,
+            epsilon: DEFAULT_EPSILON,
+            parallel: true,
+            colored_shadows: false,
+            ao_samples: 0,
+            ao_radius: 1.0,
+            unlit: false,
+            depth: 0,
+            channel: None,
+        }
+    }
+}
+
+// Assembles a world in a single expression, e.g.
+// World::builder().light(light).shape(floor).group(group).build()
+pub struct WorldBuilder {
+    world: World,
+}
+
+impl WorldBuilder {
+    pub fn light<L: Light>(mut self, light: L) -> WorldBuilder {
+        self.world.set_light(light);
+        self
+    }
+
+    pub fn shape(mut self, shape: Shape) -> WorldBuilder {
+        self.world.add_shape(shape);
+        self
+    }
+
+    // Same as add_group, the world group is replaced
+    pub fn group(mut self, group: Group) -> WorldBuilder {
+        self.world.add_group(group);
+        self
+    }
+
+    // Sky seen by the rays missing every object, see set_sky_gradient
+    pub fn background(mut self, top: Tuple, bottom: Tuple) -> WorldBuilder {
+        self.world.set_sky_gradient(top, bottom);
+        self
+    }
+
+    pub fn build(mut self) -> World {
+        self.world.precompute_all_inverses();
+        self.world
+    }
 }
 
 impl World {
+    pub fn builder() -> WorldBuilder {
+        WorldBuilder {
+            world: World::new(),
+        }
+    }
+
     pub fn new() -> World {
         World {
             light: None,
             objects: vec![],
             group: Group::new(),
+            ambient_refractive_index: 1.0,
+            sky_gradient: None,
+            stats: None,
+            epsilon: DEFAULT_EPSILON,
+            parallel: true,
+            colored_shadows: false,
+            ao_samples: 0,
+            ao_radius: 1.0,
+            unlit: false,
+            depth: 0,
+            channel: None,
         }
     }
 
-    pub fn get_light_ref(&self) -> &PointLight {
+    pub fn get_light_ref(&self) -> &dyn Light {
         match &self.light {
-            Some(light) => light,
+            Some(light) => light.as_ref(),
             None => panic!("No light defined"),
         }
     }
 
-    pub fn set_light(&mut self, light: PointLight) {
-        self.light = Some(light);
+    pub fn set_light<L: Light>(&mut self, light: L) {
+        self.light = Some(Box::new(light));
+    }
+
+    // Puts the given light in place of the current one, which is returned
+    pub fn replace_light(&mut self, light: Option<Box<dyn Light>>) -> Option<Box<dyn Light>> {
+        std::mem::replace(&mut self.light, light)
+    }
+
+    // Every light of the world, at the moment there is at most one
+    pub fn lights(&self) -> impl Iterator<Item = &dyn Light> {
+        self.light.iter().map(|light| light.as_ref())
+    }
+
+    // Objects are never removed from the world, so the returned id stays valid
+    // for the whole life of the world and can be used with shape / shape_mut.
+    pub fn add_shape(&mut self, shape: Shape) -> usize {
+        self.objects.push(Objects::Shape(Box::new(shape)));
+        self.objects.len() - 1
+    }
+
+    pub fn add_shapes(&mut self, shapes: &[Shape]) -> Vec<usize> {
+        shapes
+            .iter()
+            .map(|shape| self.add_shape(shape.clone()))
+            .collect()
+    }
+
+    pub fn shape(&self, id: usize) -> Option<&Shape> {
+        match self.objects.get(id) {
+            Some(Objects::Shape(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn shape_mut(&mut self, id: usize) -> Option<&mut Shape> {
+        match self.objects.get_mut(id) {
+            Some(Objects::Shape(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    // Top level objects of the world, the world group is not included
+    pub fn objects(&self) -> impl Iterator<Item = &Objects> {
+        self.objects.iter()
+    }
+
+    // Every shape of the world, top level ones first and then the ones inside
+    // groups, at any depth. Grouped shapes are copies since groups keep them
+    // behind locks.
+    pub fn all_shapes(&self) -> impl Iterator<Item = Shape> + '_ {
+        self.objects
+            .iter()
+            .flat_map(|object| match object {
+                Objects::Shape(shape) => vec![(**shape).clone()],
+                Objects::Group(group) => group.shapes(),
+            })
+            .chain(self.group.shapes())
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn get_ambient_refractive_index(&self) -> f64 {
+        self.ambient_refractive_index
     }
 
-    pub fn add_shapes(&mut self, shapes: &[Shape]) {
-        for shape in shapes {
-            self.objects.push(Objects::Shape(Box::new(shape.clone())));
+    // Refractive index of the medium the camera is in, 1.0 (vacuum) by default
+    pub fn set_ambient_refractive_index(&mut self, refractive_index: f64) {
+        self.ambient_refractive_index = refractive_index;
+    }
+
+    // Rays missing every object, reflected and refracted ones included, get a
+    // blend of the two colors depending on how much they point up instead of black
+    pub fn set_sky_gradient(&mut self, top: Tuple, bottom: Tuple) {
+        self.sky_gradient = Some((top, bottom));
+    }
+
+    fn sky_color(&self, ray: &Ray) -> Tuple {
+        match &self.sky_gradient {
+            None => Tuple::black(),
+            Some((top, bottom)) => {
+                let fraction = (ray.get_direction().normalize().y + 1.0) / 2.0;
+                top * fraction + bottom * (1.0 - fraction)
+            }
         }
     }
 
+    // Starts collecting statistics for the rays traced from now on
+    pub fn start_stats(&mut self) {
+        self.stats = Some(RenderStats::default());
+    }
+
+    // Statistics collected since start_stats, collection stops
+    pub fn take_stats(&mut self) -> Option<RenderStats> {
+        self.stats.take()
+    }
+
+    pub fn get_epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    // Offset of the points used to cast secondary rays off a surface
+    pub fn set_epsilon(&mut self, epsilon: f64) {
+        self.epsilon = epsilon;
+    }
+
+    pub fn get_parallel(&self) -> bool {
+        self.parallel
+    }
+
+    // When false the world group is intersected on the calling thread only
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    // When set, transparent objects let the light through tinted by their
+    // color instead of casting a full shadow
+    pub fn set_colored_shadows(&mut self, colored_shadows: bool) {
+        self.colored_shadows = colored_shadows;
+    }
+
+    pub fn get_unlit(&self) -> bool {
+        self.unlit
+    }
+
+    // When set, hits are shaded with the color of the material or of its
+    // pattern alone: no light, shadows, reflections nor refractions
+    pub fn set_unlit(&mut self, unlit: bool) {
+        self.unlit = unlit;
+    }
+
+    // Ambient occlusion: every shaded point casts this many rays over the
+    // hemisphere around its normal and its ambient light is dimmed by the
+    // fraction of them hitting an object closer than the radius
+    pub fn set_ao_samples(&mut self, ao_samples: usize) {
+        self.ao_samples = ao_samples;
+    }
+
+    pub fn set_ao_radius(&mut self, ao_radius: f64) {
+        self.ao_radius = ao_radius;
+    }
+
     pub fn add_group(&mut self, group: Group) {
         self.group = group;
     }
 
-    pub fn intersect(&mut self, ray: &Ray) -> Vec<Intersection> {
+    // Stores the inverse transformation of every shape of the world, so that
+    // it is not computed again for every ray
+    pub fn precompute_all_inverses(&mut self) {
+        for object in self.objects.iter_mut() {
+            match object {
+                Objects::Shape(shape) => shape.precompute_inverse_transformation(),
+                Objects::Group(group) => group.precompute_inverses(0),
+            }
+        }
+        self.group.precompute_inverses(0);
+    }
+
+    pub fn get_group_ref(&self) -> &Group {
+        &self.group
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
+        self.intersect_for(ray, IntersectPurpose::Camera)
+    }
+
+    pub fn intersect_for(&self, ray: &Ray, purpose: IntersectPurpose) -> Vec<Intersection> {
+        self.intersect_visible(ray, None, purpose)
+    }
+
+    // `visible` tells, for every object, whether the ray can reach it. The
+    // world group is always tested.
+    fn intersect_visible(
+        &self,
+        ray: &Ray,
+        visible: Option<&[bool]>,
+        purpose: IntersectPurpose,
+    ) -> Vec<Intersection> {
         let mut intersections = vec![];
+        let shadow = purpose == IntersectPurpose::Shadow;
+        // Intersection tests are counted on the calling thread only
+        let parallel = self.parallel && self.stats.is_none();
 
-        for object in &mut self.objects {
-            let xs = object.intersect(ray);
+        for (id, object) in self.objects.iter().enumerate() {
+            if visible.is_some_and(|visible| !visible[id]) {
+                continue;
+            }
+            if let Objects::Shape(shape) = object {
+                if shadow && !shape.get_material().get_casts_shadow() {
+                    continue;
+                }
+            }
+            let xs = if parallel {
+                object.intersect(ray)
+            } else {
+                object.intersect_serial(ray)
+            };
             intersections.extend(xs);
         }
 
-        intersections.extend(self.group.intersect(ray, 0));
+        if parallel {
+            intersections.extend(self.group.intersect(ray, 0));
+        } else {
+            intersections.extend(self.group.intersect_serial(ray, 0));
+        }
+
+        // Shapes inside groups are only known once intersected
+        if shadow {
+            intersections.retain(|i| i.get_object_ref().get_material().get_casts_shadow());
+        }
 
-        intersections.sort_by(|a, b| a.get_t().partial_cmp(&b.get_t()).unwrap());
+        // total_cmp puts NaN after every number instead of panicking. The sort
+        // is stable, so intersections with the same t keep the order of the
+        // objects in the world, the world group last.
+        intersections.sort_by(|a, b| a.get_t().total_cmp(&b.get_t()));
         intersections
     }
 
+    pub fn cast(&self, ray: &Ray) -> Option<HitInfo> {
+        self.cast_between(ray, 0.0, f64::INFINITY)
+    }
+
+    // Same as cast for the hits with near < t <= far, see Camera::set_clip
+    pub fn cast_between(&self, ray: &Ray, near: f64, far: f64) -> Option<HitInfo> {
+        let intersections = self.intersect(ray);
+        let hit = Intersection::hit_between(&intersections, near, far)?;
+
+        let object = hit.get_object_ref();
+        let object_id = self.object_id(object);
+        let point = hit.world_point(ray);
+        let normal = object.normal_at_hit(&point, &hit, Some(&self.group));
+
+        Some(HitInfo {
+            object_id,
+            t: hit.get_t(),
+            point,
+            normal,
+        })
+    }
+
+    // Every surface the ray crosses ahead of its origin, as the index of the
+    // object (None inside the world group), the refractive indices on the two
+    // sides of the surface and the distance t. Meant to debug nested glass.
+    pub fn trace_refraction_path(&self, ray: &Ray) -> Vec<(Option<usize>, f64, f64, f64)> {
+        let intersections = self.intersect(ray);
+        let mut path = vec![];
+
+        walk_boundaries(
+            &intersections,
+            self.ambient_refractive_index,
+            None,
+            |i, n1, n2| {
+                if i.get_t() > 0.0 {
+                    path.push((self.object_id(i.get_object_ref()), n1, n2, i.get_t()));
+                }
+                true
+            },
+        );
+
+        path
+    }
+
+    // Index of the object among the top level ones, None for the world group
+    fn object_id(&self, object: &Shape) -> Option<usize> {
+        self.objects.iter().position(|o| match o {
+            Objects::Shape(s) => **s == *object,
+            Objects::Group(_) => false,
+        })
+    }
+
     pub fn shade_hit(&mut self, comps: &Computations, recursion_depth_left: usize) -> Tuple {
-        let shadowed = self.is_shadowed(comps.get_over_point_ref());
+        if self.unlit {
+            let object = comps.get_object();
+            return object
+                .get_material()
+                .surface_color(&object, comps.get_point_ref());
+        }
 
-        let light = self.light.as_ref().unwrap();
-        let surface = comps.get_object().get_material().lighting(
+        let light_filter = if comps.get_object().get_material().get_receives_shadow() {
+            self.shadow_filter(comps.get_over_point_ref(), comps.get_time())
+        } else {
+            Tuple::white()
+        };
+        let ambient_occlusion = self.ambient_occlusion(comps);
+
+        let light = self.get_light_ref();
+        let surface = comps.get_object().get_material().lighting_filtered(
             &comps.get_object(),
             light,
             comps.get_point_ref(),
             comps.get_eyev_ref(),
             comps.get_normalv_ref(),
-            shadowed,
+            &light_filter,
+            ambient_occlusion,
         );
 
-        let reflected = self.reflected_color(comps, recursion_depth_left);
         let refracted = self.refracted_color(comps, recursion_depth_left);
 
-        if comps.get_object().get_material().get_reflective() > 0.0
-            && comps.get_object().get_material().get_transparency() > 0.0
-        {
+        let material = comps.get_object().get_material().clone();
+        if material.get_transparency() > 0.0 {
+            // A transparent surface mirrors part of the light at grazing angles
+            // even when it is not reflective on its own (Fresnel effect)
+            let reflected = if material.get_reflective() > 0.0 {
+                self.reflected_color(comps, recursion_depth_left)
+            } else {
+                self.mirrored_color(comps, recursion_depth_left)
+            };
+
             let reflectance = comps.schlick();
             return surface + reflected * reflectance + refracted * (1.0 - reflectance);
         }
 
+        let reflected = self.reflected_color(comps, recursion_depth_left);
         surface + reflected + refracted
     }
 
     pub fn color_at(&mut self, ray: &Ray, recursion_depth_left: usize) -> Tuple {
-        let intersections = self.intersect(ray);
+        self.color_at_filtered(ray, recursion_depth_left, None, (0.0, f64::INFINITY))
+            .0
+    }
 
-        match Intersection::hit(&intersections) {
-            None => Tuple::black(),
+    // Color of a camera ray that skips the objects marked as not visible, see
+    // Camera::visible_objects, and the hits outside of the (near, far] clip
+    // range. Reflected, refracted and shadow rays still test every object.
+    // Also returns the distance t of the hit, None when the ray misses.
+    pub fn color_at_visible(
+        &mut self,
+        ray: &Ray,
+        recursion_depth_left: usize,
+        visible: &[bool],
+        clip: (f64, f64),
+    ) -> (Tuple, Option<f64>) {
+        self.color_at_filtered(ray, recursion_depth_left, Some(visible), clip)
+    }
+
+    fn color_at_filtered(
+        &mut self,
+        ray: &Ray,
+        recursion_depth_left: usize,
+        visible: Option<&[bool]>,
+        (near, far): (f64, f64),
+    ) -> (Tuple, Option<f64>) {
+        if let Some(stats) = self.stats.as_mut() {
+            stats.max_depth = stats.max_depth.max(self.depth);
+        }
+
+        let intersections = self.trace(ray, visible, IntersectPurpose::Camera);
+
+        match Intersection::hit_between(&intersections, near, far) {
+            None => (self.sky_color(ray), None),
             Some(hit) => {
-                let comps = hit.prepare_computations(ray, &intersections, &self.group);
-                self.shade_hit(&comps, recursion_depth_left)
+                let settings = ComputationsSettings {
+                    ambient_refractive_index: self.ambient_refractive_index,
+                    epsilon: self.epsilon,
+                    channel: self.channel,
+                };
+                let comps =
+                    hit.prepare_computations_with(ray, &intersections, &self.group, &settings);
+
+                self.depth += 1;
+                let color = self.shade_hit(&comps, recursion_depth_left);
+                self.depth -= 1;
+
+                (color, Some(hit.get_t()))
             }
         }
     }
 
-    fn is_shadowed(&mut self, point: &Tuple) -> bool {
-        let v = self.get_light_ref().get_position_ref() - point;
-        let distance = v.magnitude();
-        let direction = v.normalize();
+    // Same as intersect, updating the statistics when they are collected
+    fn trace(
+        &mut self,
+        ray: &Ray,
+        visible: Option<&[bool]>,
+        purpose: IntersectPurpose,
+    ) -> Vec<Intersection> {
+        let tests = intersection_tests();
+        let intersections = self.intersect_visible(ray, visible, purpose);
+
+        if let Some(stats) = self.stats.as_mut() {
+            stats.rays += 1;
+            stats.intersection_tests += intersection_tests() - tests;
+        }
 
-        let r = Ray::new(point.clone(), direction);
-        let intersections = self.intersect(&r);
+        intersections
+    }
+
+    // Fraction of the hemisphere around the normal that is open within the
+    // occlusion radius, 1 when ambient occlusion is disabled
+    fn ambient_occlusion(&mut self, comps: &Computations) -> f64 {
+        if self.ao_samples == 0 {
+            return 1.0;
+        }
 
-        let h = Intersection::hit(&intersections);
-        if let Some(hit) = h {
-            if hit.get_t() < distance {
-                return true;
+        let point = comps.get_over_point_ref();
+        let mut sampler = point_sampler(point, OCCLUSION_STREAM);
+
+        let mut occluded = 0;
+        for _ in 0..self.ao_samples {
+            // Cosine weighted, directions near the normal count more
+            let (u, v) = sampler.jitter();
+            let radius = u.sqrt();
+            let angle = 2.0 * PI * v;
+            let direction = comps.get_tangent_ref() * (radius * angle.cos())
+                + comps.get_bitangent_ref() * (radius * angle.sin())
+                + comps.get_normalv_ref() * (1.0 - u).sqrt();
+
+            let ray = Ray::new(point.clone(), direction).with_time(comps.get_time());
+            let blocked = self
+                .trace(&ray, None, IntersectPurpose::Camera)
+                .iter()
+                .any(|i| i.get_t() > 0.0 && i.get_t() < self.ao_radius);
+            if blocked {
+                occluded += 1;
             }
         }
 
-        false
+        1.0 - occluded as f64 / self.ao_samples as f64
+    }
+
+    #[cfg(test)]
+    fn is_shadowed(&mut self, point: &Tuple, time: f64) -> bool {
+        self.shadow_filter(point, time) == Tuple::black()
+    }
+
+    // Fraction of the light reaching the point, per color channel. Any
+    // occluder casting shadows blocks the light, unless colored shadows are
+    // enabled: then every transparent object in the way filters it once
+    // through its color. A soft light averages the filters towards several
+    // points around it.
+    fn shadow_filter(&mut self, point: &Tuple, time: f64) -> Tuple {
+        let rays = self
+            .get_light_ref()
+            .shadow_rays(point, &mut point_sampler(point, SHADOW_STREAM));
+
+        let count = rays.len();
+        rays.into_iter()
+            .fold(Tuple::black(), |filter, (direction, distance)| {
+                filter + self.shadow_filter_along(point, direction, distance, time)
+            })
+            / count as f64
+    }
+
+    fn shadow_filter_along(
+        &mut self,
+        point: &Tuple,
+        direction: Tuple,
+        distance: f64,
+        time: f64,
+    ) -> Tuple {
+        let r = Ray::new(point.clone(), direction).with_time(time);
+        let intersections = self.trace(&r, None, IntersectPurpose::Shadow);
+
+        let mut occluders: Vec<Shape> = vec![];
+        for i in &intersections {
+            if i.get_t() <= 0.0 || i.get_t() >= distance {
+                continue;
+            }
+
+            let object = i.get_object_ref();
+            let material = object.get_material();
+            if !self.colored_shadows || material.get_transparency() <= 0.0 {
+                return Tuple::black();
+            }
+            if !occluders.contains(object) {
+                occluders.push(object.clone());
+            }
+        }
+
+        occluders.iter().fold(Tuple::white(), |filter, object| {
+            let material = object.get_material();
+            filter.hadamard_product(&(material.get_color() * material.get_transparency()))
+        })
     }
 
     pub fn reflected_color(&mut self, comps: &Computations, recursion_depth_left: usize) -> Tuple {
@@ -132,13 +731,63 @@ impl World {
             return Tuple::black();
         }
 
-        let reflected_ray = Ray::new(
-            comps.get_over_point_ref().clone(),
-            comps.get_reflectv().clone(),
-        );
-        let color = self.color_at(&reflected_ray, recursion_depth_left - 1);
+        self.mirrored_color(comps, recursion_depth_left)
+            * comps.get_object().get_material().get_reflective()
+    }
+
+    // Color seen along the reflected ray, not yet scaled by any reflectance
+    fn mirrored_color(&mut self, comps: &Computations, recursion_depth_left: usize) -> Tuple {
+        if recursion_depth_left == 0 {
+            return Tuple::black();
+        }
 
-        return color * comps.get_object().get_material().get_reflective();
+        let material = comps.get_object().get_material().clone();
+        if material.get_roughness() <= 0.0 {
+            let reflected_ray = Ray::new(
+                comps.get_over_point_ref().clone(),
+                comps.get_reflectv().clone(),
+            )
+            .with_time(comps.get_time());
+            return self.color_at(&reflected_ray, recursion_depth_left - 1);
+        }
+
+        // Rough surface: average rays spread uniformly over a cone around the
+        // mirror direction. Only the surfaces hit by camera rays fan out, a
+        // deeper bounce casts one jittered ray and relies on its parent for
+        // the averaging.
+        let reflectv = comps.get_reflectv().normalize();
+        let normalv = comps.get_normalv_ref();
+        let (tangent, bitangent) = reflectv.orthonormal_basis();
+        let cos_max = (material.get_roughness().min(1.0) * PI / 2.0).cos();
+        let mut sampler = point_sampler(comps.get_over_point_ref(), REFLECTION_STREAM);
+
+        let samples = if self.depth <= 1 {
+            material.get_reflection_samples()
+        } else {
+            1
+        };
+        let mut color = Tuple::black();
+        for _ in 0..samples {
+            let (u, v) = sampler.jitter();
+            let cos_theta = 1.0 - u * (1.0 - cos_max);
+            let sin_theta = (1.0 - cos_theta.powi(2)).sqrt();
+            let angle = 2.0 * PI * v;
+            let mut direction = &tangent * (sin_theta * angle.cos())
+                + &bitangent * (sin_theta * angle.sin())
+                + &reflectv * cos_theta;
+
+            // Directions going below the surface are mirrored back above it
+            let below = direction.dot(normalv);
+            if below < 0.0 {
+                direction = direction - normalv * (2.0 * below);
+            }
+
+            let ray =
+                Ray::new(comps.get_over_point_ref().clone(), direction).with_time(comps.get_time());
+            color = color + self.color_at(&ray, recursion_depth_left - 1);
+        }
+
+        color / samples as f64
     }
 
     pub fn refracted_color(&mut self, comps: &Computations, remaining: usize) -> Tuple {
@@ -146,16 +795,45 @@ impl World {
             return Tuple::black();
         }
 
-        if comps
-            .get_object()
-            .get_material()
-            .get_transparency()
-            .approx_eq(0.0, Margin::default_f64())
-        {
+        let object = comps.get_object();
+        let transparency = object.get_material().get_transparency();
+
+        if transparency.approx_eq(0.0, Margin::default_f64()) {
             return Tuple::black();
         }
 
-        let n_ratio = comps.get_n1() / comps.get_n2();
+        let channel_n = match comps.get_channel_n() {
+            None => {
+                return self.refracted_channel(comps, comps.get_n1(), comps.get_n2(), remaining)
+                    * transparency
+            }
+            Some(channel_n) => channel_n,
+        };
+
+        // The first dispersive surface splits the ray in one ray per channel.
+        // Each one carries its channel alone from then on, and is bent by the
+        // index of that channel in every dispersive object it goes through.
+        let mut channels = [0.0; 3];
+        for (channel, (n1, n2)) in channel_n.into_iter().enumerate() {
+            self.channel = Some(channel);
+            let color = self.refracted_channel(comps, n1, n2, remaining);
+            self.channel = None;
+            channels[channel] = [color.x, color.y, color.z][channel];
+        }
+
+        Tuple::new_color(channels[0], channels[1], channels[2]) * transparency
+    }
+
+    // Color seen through the surface going from index n1 to n2, black under
+    // total internal reflection
+    fn refracted_channel(
+        &mut self,
+        comps: &Computations,
+        n1: f64,
+        n2: f64,
+        remaining: usize,
+    ) -> Tuple {
+        let n_ratio = n1 / n2;
         let cos_i = comps.get_eyev_ref().dot(comps.get_normalv_ref());
         let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
 
@@ -166,50 +844,172 @@ impl World {
         let cos_t = (1.0 - sin2_t).sqrt();
         let direction =
             comps.get_normalv_ref() * (n_ratio * cos_i - cos_t) - comps.get_eyev_ref() * n_ratio;
-        let refracted_ray = Ray::new(comps.get_under_point_ref().clone(), direction);
+        let refracted_ray =
+            Ray::new(comps.get_under_point_ref().clone(), direction).with_time(comps.get_time());
 
         self.color_at(&refracted_ray, remaining - 1)
-            * comps.get_object().get_material().get_transparency()
     }
 }
 
+// Streams of point_sampler, so that the shadow, occlusion and reflection rays
+// of a point are not jittered the same way
+const SHADOW_STREAM: u64 = 0x5D0B_1A7E_3C29_F4E1;
+const OCCLUSION_STREAM: u64 = 0xA3C5_9E07_62D8_1B4F;
+const REFLECTION_STREAM: u64 = 0x1F86_D3B2_C74A_0E95;
+
+// Sampler seeded by the point and the stream, so that the rays cast from a
+// shaded point do not depend on the order in which the pixels are rendered
+fn point_sampler(point: &Tuple, stream: u64) -> Sampler {
+    Sampler::new(
+        point.x.to_bits()
+            ^ point.y.to_bits().rotate_left(21)
+            ^ point.z.to_bits().rotate_left(42)
+            ^ stream,
+    )
+}
+
 #[cfg(test)]
 mod tests {
 
-    use std::sync::{Arc, Mutex};
+    use std::{
+        any::Any,
+        sync::{Arc, Mutex},
+    };
 
     use crate::{
         core::transformations::Transformation,
         materials::patterns::{Pattern, PatternsKind},
         materials::Material,
+        shapes::cubes::Cube,
         shapes::planes::Plane,
         shapes::spheres::Sphere,
+        shapes::MockPolygon,
     };
 
     use super::*;
 
-    impl World {
-        pub fn default() -> World {
-            let light = PointLight::new(Tuple::white(), Tuple::new_point(-10.0, 10.0, -10.0));
-
-            let sphere = Sphere::new();
-            let mut s1 = Shape::default(Arc::new(Mutex::new(sphere)));
-            let mut m = Material::default();
-            m.set_color(Tuple::new_color(0.8, 1.0, 0.6));
-            m.set_diffuse(0.7);
-            m.set_specular(0.2);
-            s1.set_material(m);
-
-            let sphere = Sphere::new();
-            let mut s2 = Shape::default(Arc::new(Mutex::new(sphere)));
-            s2.set_transformation(Transformation::scaling(0.5, 0.5, 0.5));
-
-            World {
-                light: Some(light),
-                objects: vec![Objects::Shape(Box::new(s1)), Objects::Shape(Box::new(s2))],
-                group: Group::new(),
-            }
-        }
+    #[test]
+    fn a_ray_missing_everything_sees_the_sky_gradient() {
+        let mut w = World::default();
+        let top = Tuple::new_color(0.2, 0.4, 0.9);
+        let bottom = Tuple::new_color(0.9, 0.9, 0.8);
+        w.set_sky_gradient(top.clone(), bottom.clone());
+
+        let origin = Tuple::new_point(0.0, 5.0, 0.0);
+        let up = Ray::new(origin.clone(), Tuple::new_vector(0.0, 1.0, 0.0));
+        let down = Ray::new(
+            Tuple::new_point(0.0, -5.0, 0.0),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+        let sideways = Ray::new(origin, Tuple::new_vector(0.0, 0.0, 1.0));
+
+        assert_eq!(w.color_at(&up, 5), top);
+        assert_eq!(w.color_at(&down, 5), bottom);
+        assert_eq!(w.color_at(&sideways, 5), Tuple::new_color(0.55, 0.65, 0.85));
+    }
+
+    #[test]
+    fn a_mirror_reflects_the_sky() {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+        let mut mirror = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        let mut material = Material::default();
+        material.set_color(Tuple::black());
+        material.set_specular(0.0);
+        material.set_reflective(1.0);
+        mirror.set_material(material);
+        w.add_shape(mirror);
+
+        // Straight down, the reflection goes straight up
+        let r = Ray::new(
+            Tuple::new_point(0.0, 1.0, 0.0),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+        assert_eq!(w.color_at(&r, 5), Tuple::black());
+
+        w.set_sky_gradient(Tuple::new_color(0.2, 0.4, 0.9), Tuple::black());
+        assert_eq!(w.color_at(&r, 5), Tuple::new_color(0.2, 0.4, 0.9));
+    }
+
+    #[test]
+    fn visiting_every_shape_of_a_world() {
+        let mut w = World::default();
+
+        let mut g = Group::new();
+        g.add_node(Shape::default(Arc::new(Mutex::new(Sphere::new()))), Some(0));
+        g.with_transform(
+            Transformation::translation(0.0, 2.0, 0.0),
+            Some(0),
+            |g, id| {
+                g.add_node(Shape::default(Arc::new(Mutex::new(Plane::new()))), Some(id));
+                g.with_transform(Transformation::scaling(2.0, 2.0, 2.0), Some(id), |g, id| {
+                    g.add_node(
+                        Shape::default(Arc::new(Mutex::new(Sphere::new()))),
+                        Some(id),
+                    );
+                });
+            },
+        );
+        w.add_group(g);
+
+        // Two loose spheres of the default world and three grouped shapes
+        assert_eq!(w.all_shapes().count(), 5);
+        assert_eq!(w.all_shapes().next().unwrap(), *w.shape(0).unwrap());
+    }
+
+    #[test]
+    fn coincident_intersections_are_ordered_like_the_objects() {
+        let mut w = World::new();
+        let mut a = Shape::glass(Arc::new(Mutex::new(Sphere::new())));
+        let mut material = a.get_material().clone();
+        material.set_refractive_index(1.5);
+        a.set_material(material.clone());
+        let mut b = Shape::glass(Arc::new(Mutex::new(Sphere::new())));
+        material.set_refractive_index(2.0);
+        b.set_material(material);
+        w.add_shapes(&[a.clone(), b.clone()]);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = w.intersect(&r);
+
+        let objects: Vec<&Shape> = xs.iter().map(|i| i.get_object_ref()).collect();
+        assert_eq!(objects, vec![&a, &b, &a, &b]);
+
+        let refractive_indexes: Vec<(f64, f64)> = xs
+            .iter()
+            .map(|i| {
+                let comps = i.prepare_computations(&r, &xs, &Group::new());
+                (comps.get_n1(), comps.get_n2())
+            })
+            .collect();
+        assert_eq!(
+            refractive_indexes,
+            vec![(1.0, 1.5), (1.5, 2.0), (2.0, 2.0), (2.0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn a_nan_intersection_does_not_panic_and_sorts_last() {
+        let mut w = World::new();
+        let mut mock = MockPolygon::default();
+        mock.expect_intersect_faces()
+            .returning(|_| vec![(f64::NAN, 0.0, 0.0, 0), (2.0, 0.0, 0.0, 0)]);
+        w.add_shape(Shape::default(Arc::new(Mutex::new(mock))));
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = w.intersect(&r);
+
+        assert_eq!(xs[0].get_t(), 2.0);
+        assert!(xs[1].get_t().is_nan());
     }
 
     #[test]
@@ -217,7 +1017,20 @@ mod tests {
         let w = World::new();
 
         assert!(w.light.is_none());
-        assert!(w.objects.len() == 0);
+        assert!(w.objects.is_empty());
+    }
+
+    #[test]
+    fn listing_the_lights_of_a_world() {
+        let mut w = World::new();
+        assert_eq!(w.lights().count(), 0);
+
+        let light = PointLight::new(Tuple::white(), Tuple::new_point(-10.0, 10.0, -10.0));
+        w.set_light(light.clone());
+
+        let lights: Vec<&dyn Any> = w.lights().map(|l| l as &dyn Any).collect();
+        assert_eq!(lights.len(), 1);
+        assert_eq!(lights[0].downcast_ref::<PointLight>(), Some(&light));
     }
 
     #[test]
@@ -238,13 +1051,29 @@ mod tests {
 
         let w = World::default();
 
-        assert!(w.light == Some(l));
+        let light = w.get_light_ref() as &dyn Any;
+        assert_eq!(light.downcast_ref::<PointLight>(), Some(&l));
         assert!(w.objects.len() == 2);
     }
 
+    #[test]
+    fn iterating_the_objects_of_the_default_world() {
+        let w = World::default();
+
+        let shapes = w
+            .objects()
+            .filter(|o| matches!(o, Objects::Shape(_)))
+            .count();
+
+        assert_eq!(w.len(), 2);
+        assert_eq!(shapes, 2);
+        assert!(!w.is_empty());
+        assert!(World::new().is_empty());
+    }
+
     #[test]
     fn intersect_a_world_with_a_ray() {
-        let mut w = World::default();
+        let w = World::default();
         let r = Ray::new(
             Tuple::new_point(0.0, 0.0, -5.0),
             Tuple::new_vector(0.0, 0.0, 1.0),
@@ -253,7 +1082,7 @@ mod tests {
         let xs = w.intersect(&r);
 
         assert!(xs.len() == 4);
-        assert!(xs.get(0).unwrap().get_t() == 4.0);
+        assert!(xs.first().unwrap().get_t() == 4.0);
         assert!(xs.get(1).unwrap().get_t() == 4.5);
         assert!(xs.get(2).unwrap().get_t() == 5.5);
         assert!(xs.get(3).unwrap().get_t() == 6.0);
@@ -268,12 +1097,9 @@ mod tests {
             Tuple::new_vector(0.0, 0.0, 1.0),
         );
 
-        let shape = match w.objects.get(0).unwrap() {
-            Objects::Shape(s) => s.clone(),
-            Objects::Group(_) => panic!(),
-        };
+        let shape = w.shape(0).unwrap().clone();
 
-        let i = Intersection::new(4.0, *shape);
+        let i = Intersection::new(4.0, shape);
         let comps = i.prepare_computations(&r, &[], &Group::new());
         let c = w.shade_hit(&comps, 5);
         assert!(
@@ -298,12 +1124,9 @@ mod tests {
             Tuple::new_vector(0.0, 0.0, 1.0),
         );
 
-        let shape = match w.objects.get(1).unwrap() {
-            Objects::Shape(s) => s.clone(),
-            Objects::Group(_) => panic!(),
-        };
+        let shape = w.shape(1).unwrap().clone();
 
-        let i = Intersection::new(0.5, *shape);
+        let i = Intersection::new(0.5, shape);
         let comps = i.prepare_computations(&r, &[], &Group::new());
         let c = w.shade_hit(&comps, 5);
 
@@ -344,87 +1167,395 @@ mod tests {
     fn the_color_with_an_intersection_behind_the_ray() {
         let mut w = World::default();
 
-        match w.objects.get_mut(0).unwrap() {
-            Objects::Shape(s) => s.material.set_ambient(1.0),
-            Objects::Group(_) => panic!(),
-        };
+        w.shape_mut(0).unwrap().material_mut().set_ambient(1.0);
+        w.shape_mut(1).unwrap().material_mut().set_ambient(1.0);
 
-        match w.objects.get_mut(1).unwrap() {
-            Objects::Shape(s) => s.material.set_ambient(1.0),
-            Objects::Group(_) => panic!(),
-        };
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, 0.75),
+            Tuple::new_vector(0.0, 0.0, -1.0),
+        );
+
+        let color = w.shape(1).unwrap().get_material().get_color();
+
+        assert_eq!(color, w.color_at(&r, 5));
+    }
+
+    #[test]
+    fn mutating_a_shape_through_its_handle_affects_the_next_render() {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+        let id = w.add_shape(Shape::default(Arc::new(Mutex::new(Sphere::new()))));
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let before = w.color_at(&r, 5);
+
+        let mut m = Material::default();
+        m.set_color(Tuple::new_color(1.0, 0.0, 0.0));
+        w.shape_mut(id).unwrap().set_material(m);
+
+        let after = w.color_at(&r, 5);
+
+        assert_ne!(before, after);
+        assert_eq!(after.y, after.z);
+        assert!(after.x > after.y);
+    }
+
+    #[test]
+    fn a_material_tweaked_in_place_is_rendered_like_a_new_one() {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+        let id = w.add_shape(Shape::default(Arc::new(Mutex::new(Sphere::new()))));
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        let mut m = Material::default();
+        m.set_color(Tuple::new_color(1.0, 0.0, 0.0));
+        m.set_specular(0.2);
+        w.shape_mut(id).unwrap().set_material(m);
+        let replaced = w.color_at(&r, 5);
+
+        w.shape_mut(id).unwrap().set_material(Material::default());
+        let material = w.shape_mut(id).unwrap().material_mut();
+        material.set_color(Tuple::new_color(1.0, 0.0, 0.0));
+        material.set_specular(0.2);
+
+        assert_eq!(w.color_at(&r, 5), replaced);
+    }
+
+    #[test]
+    fn handles_of_shapes_stay_valid_as_more_shapes_are_added() {
+        let mut w = World::new();
+        let first = w.add_shape(Shape::default(Arc::new(Mutex::new(Sphere::new()))));
+        let others = w.add_shapes(&[
+            Shape::default(Arc::new(Mutex::new(Plane::new()))),
+            Shape::default(Arc::new(Mutex::new(Sphere::new()))),
+        ]);
+
+        w.shape_mut(first)
+            .unwrap()
+            .material_mut()
+            .set_reflective(0.5);
+
+        assert_eq!(others, vec![1, 2]);
+        assert_eq!(w.shape(first).unwrap().get_material().get_reflective(), 0.5);
+        assert!(w.shape(3).is_none());
+    }
+
+    #[test]
+    fn there_is_no_shadow_when_nothing_is_collinear_with_point_and_light() {
+        let mut w = World::default();
+        let p = Tuple::new_point(0.0, 10.0, 0.0);
+
+        assert!(!w.is_shadowed(&p, 0.0));
+    }
+
+    #[test]
+    fn shadow_when_an_object_is_between_the_point_and_the_light() {
+        let mut w = World::default();
+        let p = Tuple::new_point(10.0, -10.0, 10.0);
+
+        assert!(w.is_shadowed(&p, 0.0));
+    }
+
+    #[test]
+    fn there_is_no_shadow_when_an_object_is_behind_the_light() {
+        let mut w = World::default();
+        let p = Tuple::new_point(-20.0, 20.0, -20.0);
+
+        assert!(!w.is_shadowed(&p, 0.0));
+    }
+
+    #[test]
+    fn there_is_no_shadow_when_an_object_is_behind_the_point() {
+        let mut w = World::default();
+        let p = Tuple::new_point(-2.0, 2.0, -2.0);
+
+        assert!(!w.is_shadowed(&p, 0.0));
+    }
+
+    #[test]
+    fn intersection_in_shadow() {
+        let mut w = World::default();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(0.0, 0.0, -10.0),
+        ));
+
+        let sphere = Sphere::new();
+        let s1 = Shape::default(Arc::new(Mutex::new(sphere)));
+
+        let sphere = Sphere::new();
+        let mut s2 = Shape::default(Arc::new(Mutex::new(sphere)));
+        s2.set_transformation(Transformation::translation(0.0, 0.0, 10.0));
+
+        w.add_shapes(&[s1, s2.clone()]);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, 5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let i = Intersection::new(4.0, s2);
+        let comps = i.prepare_computations(&r, &[], &Group::new());
+        let c = w.shade_hit(&comps, 5);
+
+        assert!(c == Tuple::new_color(0.1, 0.1, 0.1));
+    }
+
+    fn floor_color_below(occluder: Material, colored_shadows: bool) -> Tuple {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(0.0, 10.0, 0.0),
+        ));
+        w.set_colored_shadows(colored_shadows);
+        w.add_shape(Shape::default(Arc::new(Mutex::new(Plane::new()))));
+
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere.set_transformation(Transformation::translation(0.0, 3.0, 0.0));
+        sphere.set_material(occluder);
+        w.add_shape(sphere);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 1.0, -5.0),
+            Tuple::new_vector(0.0, -1.0, 5.0).normalize(),
+        );
+        w.color_at(&r, 5)
+    }
+
+    #[test]
+    fn colored_glass_casts_a_tinted_shadow() {
+        let mut red_glass = Material::default();
+        red_glass.set_color(Tuple::new_color(1.0, 0.0, 0.0));
+        red_glass.set_transparency(0.9);
+        red_glass.set_refractive_index(1.5);
+
+        let ambient = Tuple::new_color(0.1, 0.1, 0.1);
+
+        let tinted = floor_color_below(red_glass.clone(), true);
+        assert!(tinted.x > 0.5);
+        assert_eq!(
+            Tuple::new_color(0.0, tinted.y, tinted.z),
+            Tuple::new_color(0.0, 0.1, 0.1)
+        );
+
+        assert_eq!(floor_color_below(Material::default(), true), ambient);
+        // Without colored shadows any object blocks the light
+        assert_eq!(floor_color_below(red_glass, false), ambient);
+    }
+
+    fn floor_color_next_to_a_sphere(x: f64, ao_samples: usize) -> Tuple {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+        w.set_ao_samples(ao_samples);
+        w.set_ao_radius(1.0);
+        w.add_shape(Shape::default(Arc::new(Mutex::new(Plane::new()))));
+
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere.set_transformation(Transformation::translation(0.0, 1.0, 0.0));
+        w.add_shape(sphere);
+
+        let r = Ray::new(
+            Tuple::new_point(x, 5.0, -0.01),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+        w.color_at(&r, 5)
+    }
+
+    #[test]
+    fn ambient_occlusion_darkens_the_floor_where_a_sphere_rests() {
+        let without = floor_color_next_to_a_sphere(1.1, 0);
+        let with = floor_color_next_to_a_sphere(1.1, 64);
+        assert!(with.x < without.x);
 
-        let r = Ray::new(
-            Tuple::new_point(0.0, 0.0, 0.75),
-            Tuple::new_vector(0.0, 0.0, -1.0),
+        // Far from the sphere nothing is closer than the radius
+        assert_eq!(
+            floor_color_next_to_a_sphere(4.0, 64),
+            floor_color_next_to_a_sphere(4.0, 0)
         );
+    }
 
-        let color = match w.objects.get(1).unwrap() {
-            Objects::Shape(s) => s.material.get_color(),
-            Objects::Group(_) => panic!(),
-        };
+    fn light_reaching_the_floor(soft_radius: f64, x: f64) -> f64 {
+        let mut light = PointLight::new(Tuple::white(), Tuple::new_point(0.0, 10.0, 0.0));
+        light.set_soft_radius(soft_radius);
+        light.set_soft_samples(32);
 
-        assert_eq!(color, w.color_at(&r, 5));
+        let mut w = World::new();
+        w.set_light(light);
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere.set_transformation(Transformation::translation(0.0, 3.0, 0.0));
+        w.add_shape(sphere);
+
+        w.shadow_filter(&Tuple::new_point(x, 0.0, 0.0), 0.0).x
     }
 
-    #[test]
-    fn there_is_no_shadow_when_nothing_is_collinear_with_point_and_light() {
-        let mut w = World::default();
-        let p = Tuple::new_point(0.0, 10.0, 0.0);
+    // Mean and variance of the light reaching a point of the penumbra, over
+    // renders that differ by the seed of the shadow rays only
+    fn penumbra_statistics(stratified: bool) -> (f64, f64) {
+        let mut light = PointLight::new(Tuple::white(), Tuple::new_point(0.0, 10.0, 0.0));
+        light.set_soft_radius(1.0);
+        light.set_soft_samples(16);
+        light.set_stratified_shadows(stratified);
 
-        assert!(!w.is_shadowed(&p));
+        let mut w = World::new();
+        w.set_light(light);
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere.set_transformation(Transformation::translation(0.0, 3.0, 0.0));
+        w.add_shape(sphere);
+
+        // Nudging the point changes the seed, not the penumbra
+        let renders = 400;
+        let values: Vec<f64> = (0..renders)
+            .map(|i| {
+                let point = Tuple::new_point(1.443 + i as f64 * 1e-12, 0.0, 0.0);
+                w.shadow_filter(&point, 0.0).x
+            })
+            .collect();
+
+        let mean = values.iter().sum::<f64>() / renders as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / renders as f64;
+
+        (mean, variance)
     }
 
     #[test]
-    fn shadow_when_an_object_is_between_the_point_and_the_light() {
-        let mut w = World::default();
-        let p = Tuple::new_point(10.0, -10.0, 10.0);
+    fn stratified_shadows_of_a_soft_light_lower_the_noise_of_its_penumbra() {
+        let (jittered_mean, jittered_variance) = penumbra_statistics(false);
+        let (stratified_mean, stratified_variance) = penumbra_statistics(true);
+
+        // Both estimate the same light, the points stratified over the ball
+        // with less noise than the ones jittered independently
+        assert!((jittered_mean - stratified_mean).abs() < 0.02);
+        assert!(stratified_variance < jittered_variance / 2.0);
+    }
 
-        assert!(w.is_shadowed(&p));
+    #[test]
+    fn a_soft_light_casts_a_penumbra_around_a_dark_umbra() {
+        // The hard shadow of the sphere ends at x = 10 / √48 ≈ 1.443
+        assert_eq!(light_reaching_the_floor(0.0, 1.3), 0.0);
+        assert_eq!(light_reaching_the_floor(0.0, 1.6), 1.0);
+
+        assert_eq!(light_reaching_the_floor(1.0, 0.0), 0.0);
+        let penumbra = light_reaching_the_floor(1.0, 1.443);
+        assert!(penumbra > 0.0 && penumbra < 1.0);
     }
 
     #[test]
-    fn there_is_no_shadow_when_an_object_is_behind_the_light() {
-        let mut w = World::default();
-        let p = Tuple::new_point(-20.0, 20.0, -20.0);
+    fn an_occluder_that_casts_no_shadow_lets_the_light_through() {
+        let mut ghost = Material::default();
+        ghost.set_casts_shadow(false);
+
+        let lit = floor_color_below(ghost.clone(), false);
+        assert!(lit.x > 0.5);
 
-        assert!(!w.is_shadowed(&p));
+        // The occluder is still seen by camera rays
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(0.0, 10.0, 0.0),
+        ));
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere.set_material(ghost);
+        w.add_shape(sphere);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(w.cast(&r).unwrap().get_object_id(), Some(0));
     }
 
     #[test]
-    fn there_is_no_shadow_when_an_object_is_behind_the_point() {
+    fn shadow_rays_skip_the_objects_that_cast_no_shadow() {
         let mut w = World::default();
-        let p = Tuple::new_point(-2.0, 2.0, -2.0);
+        let outer = w.shape_mut(0).unwrap();
+        let mut material = outer.get_material().clone();
+        material.set_casts_shadow(false);
+        outer.set_material(material);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
 
-        assert!(!w.is_shadowed(&p));
+        let ts = |xs: Vec<Intersection>| xs.iter().map(|i| i.get_t()).collect::<Vec<f64>>();
+        assert_eq!(
+            ts(w.intersect_for(&r, IntersectPurpose::Camera)),
+            vec![4.0, 4.5, 5.5, 6.0]
+        );
+        assert_eq!(
+            ts(w.intersect_for(&r, IntersectPurpose::Shadow)),
+            vec![4.5, 5.5]
+        );
+        assert_eq!(w.intersect(&r).len(), 4);
     }
 
     #[test]
-    fn intersection_in_shadow() {
-        let mut w = World::default();
+    fn a_surface_that_receives_no_shadow_ignores_occluders() {
+        let mut w = World::new();
         w.set_light(PointLight::new(
             Tuple::white(),
-            Tuple::new_point(0.0, 0.0, -10.0),
+            Tuple::new_point(0.0, 10.0, 0.0),
         ));
 
-        let sphere = Sphere::new();
-        let s1 = Shape::default(Arc::new(Mutex::new(sphere)));
+        let mut floor = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        let mut floor_material = Material::default();
+        floor_material.set_receives_shadow(false);
+        floor.set_material(floor_material);
+        w.add_shape(floor);
 
-        let sphere = Sphere::new();
-        let mut s2 = Shape::default(Arc::new(Mutex::new(sphere)));
-        s2.set_transformation(Transformation::translation(0.0, 0.0, 10.0));
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere.set_transformation(Transformation::translation(0.0, 3.0, 0.0));
+        w.add_shape(sphere);
 
-        w.add_shapes(&[s1, s2.clone()]);
+        let r = Ray::new(
+            Tuple::new_point(0.0, 1.0, -5.0),
+            Tuple::new_vector(0.0, -1.0, 5.0).normalize(),
+        );
+        assert!(w.color_at(&r, 5).x > 0.5);
+    }
+
+    #[test]
+    fn shading_a_shape_inside_a_transformed_group_uses_the_group_normal() {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(5.0, 0.0, -10.0),
+        ));
+
+        let mut g = Group::new();
+        let matrix_id = g.add_matrix(Transformation::translation(5.0, 0.0, 0.0), Some(0));
+        g.add_node(
+            Shape::default(Arc::new(Mutex::new(Sphere::new()))),
+            Some(matrix_id),
+        );
+        w.add_group(g);
 
         let r = Ray::new(
-            Tuple::new_point(0.0, 0.0, 5.0),
+            Tuple::new_point(5.0, 0.0, -5.0),
             Tuple::new_vector(0.0, 0.0, 1.0),
         );
-        let i = Intersection::new(4.0, s2);
-        let comps = i.prepare_computations(&r, &[], &Group::new());
-        let c = w.shade_hit(&comps, 5);
 
-        assert!(c == Tuple::new_color(0.1, 0.1, 0.1));
+        let xs = w.intersect(&r);
+        let hit = Intersection::hit(&xs).unwrap();
+        let comps = hit.prepare_computations(&r, &xs, &w.group);
+
+        assert_eq!(*comps.get_point_ref(), Tuple::new_point(5.0, 0.0, -1.0));
+        assert_eq!(*comps.get_normalv_ref(), Tuple::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(w.color_at(&r, 5), Tuple::new_color(1.9, 1.9, 1.9));
     }
 
     #[test]
@@ -435,17 +1566,11 @@ mod tests {
             Tuple::new_vector(0.0, 0.0, 1.0),
         );
 
-        match w.objects.get_mut(1).unwrap() {
-            Objects::Shape(s) => s.material.set_ambient(1.0),
-            Objects::Group(_) => panic!(),
-        };
+        w.shape_mut(1).unwrap().material_mut().set_ambient(1.0);
 
-        let shape = match w.objects.get(1).unwrap() {
-            Objects::Shape(s) => s.clone(),
-            Objects::Group(_) => panic!(),
-        };
+        let shape = w.shape(1).unwrap().clone();
 
-        let i = Intersection::new(1.0, *shape);
+        let i = Intersection::new(1.0, shape);
         let comps = i.prepare_computations(&r, &[], &Group::new());
         let color = w.reflected_color(&comps, 5);
 
@@ -477,8 +1602,57 @@ mod tests {
 
         assert_eq!(
             color,
-            Tuple::new_color(0.1903307689243628, 0.23791346115545348, 0.1427480766932721)
+            Tuple::new_color(
+                0.19033076892474832,
+                0.23791346115593542,
+                0.14274807669356124
+            )
+        );
+    }
+
+    // A mirror floor reflecting a wall that is white for x >= 0 and black
+    // for x < 0, seen right next to the edge on the white side
+    fn reflection_next_to_an_edge(roughness: f64) -> Tuple {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(0.0, 5.0, 0.0),
+        ));
+
+        let mut floor = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        let mut floor_material = Material::mirror();
+        floor_material.set_specular(0.0);
+        floor_material.set_roughness(roughness);
+        floor.set_material(floor_material);
+
+        let mut wall = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        wall.set_transformation(
+            Transformation::translation(0.0, 0.0, 10.0) * Transformation::rotation_x(PI / 2.0),
+        );
+        let mut stripes = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Stripe);
+        stripes.set_transformation(Transformation::scaling(100.0, 1.0, 1.0));
+        let mut wall_material = Material::default();
+        wall_material.set_ambient(1.0);
+        wall_material.set_diffuse(0.0);
+        wall_material.set_specular(0.0);
+        wall_material.set_pattern(stripes);
+        wall.set_material(wall_material);
+
+        w.add_shapes(&[floor, wall]);
+
+        let r = Ray::new(
+            Tuple::new_point(0.05, 1.0, -1.0),
+            Tuple::new_vector(0.0, -1.0, 1.0).normalize(),
         );
+        w.color_at(&r, 5)
+    }
+
+    #[test]
+    fn a_rough_mirror_blurs_the_reflection() {
+        assert_eq!(reflection_next_to_an_edge(0.0), Tuple::white());
+
+        let blurred = reflection_next_to_an_edge(0.2);
+        assert!(blurred.x > 0.1 && blurred.x < 0.9);
     }
 
     #[test]
@@ -506,7 +1680,7 @@ mod tests {
 
         assert_eq!(
             color,
-            Tuple::new_color(0.8767561579058643, 0.9243388501369549, 0.8291734656747736)
+            Tuple::new_color(0.8767561579062497, 0.9243388501374368, 0.8291734656750627)
         );
     }
 
@@ -537,10 +1711,8 @@ mod tests {
             Tuple::new_vector(0.0, 1.0, 0.0),
         );
 
+        // No infinite recursion happened if this call returns
         w.color_at(&r, 5);
-
-        // No infinite recursion happened and we safely reached this assetion
-        assert!(true)
     }
 
     #[test]
@@ -569,10 +1741,7 @@ mod tests {
     fn the_refracted_color_with_an_opaque_surface() {
         let mut w = World::default();
 
-        let shape = match w.objects.get(0).unwrap() {
-            Objects::Shape(s) => s.clone(),
-            Objects::Group(_) => panic!(),
-        };
+        let shape = w.shape(0).unwrap().clone();
 
         let r = Ray::new(
             Tuple::new_point(0.0, 0.0, -5.0),
@@ -580,11 +1749,11 @@ mod tests {
         );
 
         let xs = Intersection::intersects(&[
-            Intersection::new(4.0, *shape.clone()),
-            Intersection::new(6.0, *shape.clone()),
+            Intersection::new(4.0, shape.clone()),
+            Intersection::new(6.0, shape.clone()),
         ]);
         let comps = xs
-            .get(0)
+            .first()
             .unwrap()
             .prepare_computations(&r, &xs, &Group::new());
 
@@ -600,26 +1769,20 @@ mod tests {
         material.set_transparency(1.0);
         material.set_refractive_index(1.5);
 
-        match w.objects.get_mut(0).unwrap() {
-            Objects::Shape(s) => s.set_material(material),
-            Objects::Group(_) => panic!(),
-        };
+        w.shape_mut(0).unwrap().set_material(material);
 
-        let shape = match w.objects.get(0).unwrap() {
-            Objects::Shape(s) => s.clone(),
-            Objects::Group(_) => panic!(),
-        };
+        let shape = w.shape(0).unwrap().clone();
 
         let r = Ray::new(
             Tuple::new_point(0.0, 0.0, -5.0),
             Tuple::new_vector(0.0, 0.0, 1.0),
         );
         let xs = Intersection::intersects(&[
-            Intersection::new(4.0, *shape.clone()),
-            Intersection::new(6.0, *shape.clone()),
+            Intersection::new(4.0, shape.clone()),
+            Intersection::new(6.0, shape.clone()),
         ]);
         let comps = xs
-            .get(0)
+            .first()
             .unwrap()
             .prepare_computations(&r, &xs, &Group::new());
 
@@ -635,23 +1798,17 @@ mod tests {
         material.set_transparency(1.0);
         material.set_refractive_index(1.5);
 
-        match w.objects.get_mut(0).unwrap() {
-            Objects::Shape(s) => s.set_material(material),
-            Objects::Group(_) => panic!(),
-        };
+        w.shape_mut(0).unwrap().set_material(material);
 
-        let shape = match w.objects.get(0).unwrap() {
-            Objects::Shape(s) => s.clone(),
-            Objects::Group(_) => panic!(),
-        };
+        let shape = w.shape(0).unwrap().clone();
 
         let r = Ray::new(
             Tuple::new_point(0.0, 0.0, 2.0_f64.sqrt() / 2.0),
             Tuple::new_vector(0.0, 1.0, 0.0),
         );
         let xs = Intersection::intersects(&[
-            Intersection::new(-2.0_f64.sqrt() / 2.0, *shape.clone()),
-            Intersection::new(2.0_f64.sqrt() / 2.0, *shape.clone()),
+            Intersection::new(-2.0_f64.sqrt() / 2.0, shape.clone()),
+            Intersection::new(2.0_f64.sqrt() / 2.0, shape.clone()),
         ]);
 
         let comps = xs
@@ -671,38 +1828,26 @@ mod tests {
         a_material.set_ambient(1.0);
         let a_pattern = Pattern::stripe(Tuple::black(), Tuple::black(), PatternsKind::Test);
         a_material.set_pattern(a_pattern);
-        match w.objects.get_mut(0).unwrap() {
-            Objects::Shape(s) => s.set_material(a_material),
-            Objects::Group(_) => panic!(),
-        };
+        w.shape_mut(0).unwrap().set_material(a_material);
 
-        let a = match w.objects.get(0).unwrap() {
-            Objects::Shape(s) => s.clone(),
-            Objects::Group(_) => panic!(),
-        };
+        let a = w.shape(0).unwrap().clone();
 
         let mut b_material = Material::default();
         b_material.set_transparency(1.0);
         b_material.set_refractive_index(1.5);
-        match w.objects.get_mut(1).unwrap() {
-            Objects::Shape(s) => s.set_material(b_material),
-            Objects::Group(_) => panic!(),
-        };
+        w.shape_mut(1).unwrap().set_material(b_material);
 
-        let b = match w.objects.get(1).unwrap() {
-            Objects::Shape(s) => s.clone(),
-            Objects::Group(_) => panic!(),
-        };
+        let b = w.shape(1).unwrap().clone();
 
         let r = Ray::new(
             Tuple::new_point(0.0, 0.0, 0.1),
             Tuple::new_vector(0.0, 1.0, 0.0),
         );
         let xs = Intersection::intersects(&[
-            Intersection::new(-0.9899, *a.clone()),
-            Intersection::new(-0.4899, *b.clone()),
-            Intersection::new(0.4899, *b.clone()),
-            Intersection::new(0.9899, *a.clone()),
+            Intersection::new(-0.9899, a.clone()),
+            Intersection::new(-0.4899, b.clone()),
+            Intersection::new(0.4899, b.clone()),
+            Intersection::new(0.9899, a.clone()),
         ]);
 
         let comps = xs
@@ -713,8 +1858,241 @@ mod tests {
 
         assert_eq!(
             c,
-            Tuple::new_color(0.0, 0.9988846684722223, 0.04721672469727399)
+            Tuple::new_color(0.0, 0.9988846684722154, 0.047216724697425515)
+        );
+    }
+
+    // Colors seen through a glass ball in front of a wall of thin black and
+    // white stripes, along a row crossing the ball
+    fn colors_through_glass(channel_refractive_index: Option<[f64; 3]>) -> Vec<Tuple> {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+
+        let mut wall = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        wall.set_transformation(
+            Transformation::translation(0.0, 0.0, 3.0) * Transformation::rotation_x(PI / 2.0),
+        );
+        let mut stripes = Pattern::stripe(Tuple::white(), Tuple::black(), PatternsKind::Stripe);
+        stripes.set_transformation(Transformation::scaling(0.1, 0.1, 0.1));
+        let mut wall_material = Material::matte(Tuple::white());
+        wall_material.set_pattern(stripes);
+        wall_material.set_ambient(1.0);
+        wall_material.set_diffuse(0.0);
+        wall.set_material(wall_material);
+
+        let mut ball = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        let mut glass = Material::glass();
+        glass.set_specular(0.0);
+        glass.set_channel_refractive_index(channel_refractive_index);
+        ball.set_material(glass);
+
+        w.add_shapes(&[wall, ball]);
+
+        (0..40)
+            .map(|i| {
+                let r = Ray::new(
+                    Tuple::new_point(-0.8 + i as f64 * 0.04, 0.0, -5.0),
+                    Tuple::new_vector(0.0, 0.0, 1.0),
+                );
+                w.color_at(&r, 5)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_dispersive_glass_splits_white_into_tinted_fringes() {
+        let is_gray = |c: &Tuple| (c.x - c.y).abs() < 1e-9 && (c.y - c.z).abs() < 1e-9;
+
+        let plain = colors_through_glass(None);
+        assert!(plain.iter().all(is_gray));
+
+        // The same index on every channel is the plain glass
+        assert_eq!(colors_through_glass(Some([1.5, 1.5, 1.5])), plain);
+
+        let dispersive = colors_through_glass(Some([1.45, 1.5, 1.55]));
+        assert!(dispersive.iter().any(|c| (c.x - c.z).abs() > 0.5));
+    }
+
+    // Rays traced for one camera ray through two nested glass balls
+    fn rays_through_nested_glass(channel_refractive_index: Option<[f64; 3]>) -> usize {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+        let mut glass = Material::glass();
+        glass.set_channel_refractive_index(channel_refractive_index);
+        let mut outer = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        outer.set_material(glass.clone());
+        let mut inner = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        inner.set_transformation(Transformation::scaling(0.5, 0.5, 0.5));
+        inner.set_material(glass);
+        w.add_shapes(&[outer, inner]);
+
+        w.start_stats();
+        w.color_at(
+            &Ray::new(
+                Tuple::new_point(0.1, 0.1, -5.0),
+                Tuple::new_vector(0.0, 0.0, 1.0),
+            ),
+            8,
+        );
+        w.take_stats().unwrap().get_rays()
+    }
+
+    #[test]
+    fn a_dispersed_ray_carries_its_channel_as_a_single_ray() {
+        let plain = rays_through_nested_glass(None);
+        let dispersive = rays_through_nested_glass(Some([1.45, 1.5, 1.55]));
+
+        // Only the first crossing splits the ray, the three channels then
+        // follow a path as long as the plain one
+        assert!(dispersive <= 3 * plain, "{dispersive} rays for {plain}");
+    }
+
+    #[test]
+    fn a_dispersed_ray_uses_its_channel_index_in_the_containers() {
+        let mut glass = Material::glass();
+        glass.set_channel_refractive_index(Some([1.45, 1.5, 1.55]));
+        let mut a = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        a.set_transformation(Transformation::scaling(2.0, 2.0, 2.0));
+        a.set_material(glass);
+        let mut b = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        let mut water = Material::glass();
+        water.set_refractive_index(1.33);
+        b.set_material(water);
+        let mut w = World::new();
+        w.add_shapes(&[a, b]);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -4.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = w.intersect(&r);
+
+        // Entering the water ball from inside the dispersive one
+        let settings = ComputationsSettings {
+            channel: Some(2),
+            ..ComputationsSettings::default()
+        };
+        let comps = xs[1].prepare_computations_with(&r, &xs, &w.group, &settings);
+        assert_eq!((comps.get_n1(), comps.get_n2()), (1.55, 1.33));
+        assert_eq!(comps.get_channel_n(), None);
+
+        // A ray carrying all the channels splits when it enters the dispersive ball
+        let comps = xs[0].prepare_computations(&r, &xs, &w.group);
+        assert_eq!(
+            comps.get_channel_n(),
+            Some([(1.0, 1.45), (1.0, 1.5), (1.0, 1.55)])
+        );
+    }
+
+    // Rays hitting the upper front of a sphere of radius `scale` centered on
+    // (offset, offset, offset), lit from above, that end up in its own shadow
+    fn self_shadowed_rays(scale: f64, offset: f64) -> usize {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(offset, offset + 10.0 * scale, offset - 10.0 * scale),
+        ));
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere.set_transformation(
+            Transformation::translation(offset, offset, offset)
+                * Transformation::scaling(scale, scale, scale),
+        );
+        w.add_shape(sphere);
+
+        let mut shadowed = 0;
+        for i in 0..20 {
+            for j in 0..20 {
+                let x = offset + scale * (-0.5 + i as f64 / 20.0);
+                let y = offset + scale * (0.1 + j as f64 / 40.0);
+                let r = Ray::new(
+                    Tuple::new_point(x, y, offset - 5.0 * scale),
+                    Tuple::new_vector(0.0, 0.0, 1.0),
+                );
+                // Only the ambient light is left in the shadow
+                if w.color_at(&r, 0).x < 0.11 {
+                    shadowed += 1;
+                }
+            }
+        }
+
+        shadowed
+    }
+
+    #[test]
+    fn the_features_jittering_rays_from_a_point_draw_different_numbers() {
+        let point = Tuple::new_point(0.3, -1.2, 4.5);
+        let draw = |stream| point_sampler(&point, stream).jitter();
+
+        assert_eq!(draw(SHADOW_STREAM), draw(SHADOW_STREAM));
+        assert_ne!(draw(SHADOW_STREAM), draw(OCCLUSION_STREAM));
+        assert_ne!(draw(SHADOW_STREAM), draw(REFLECTION_STREAM));
+        assert_ne!(draw(OCCLUSION_STREAM), draw(REFLECTION_STREAM));
+    }
+
+    #[test]
+    fn far_away_shapes_do_not_shadow_themselves() {
+        // A fixed offset of 1e-6 is below the rounding errors this far away
+        assert_eq!(self_shadowed_rays(1.0, 1e10), 0);
+        assert_eq!(self_shadowed_rays(1e6, 1e10), 0);
+        assert_eq!(self_shadowed_rays(1.0, 1000.0), 0);
+    }
+
+    #[test]
+    fn tiny_shapes_close_to_the_origin_keep_a_tiny_offset() {
+        assert_eq!(self_shadowed_rays(0.001, 0.0), 0);
+
+        let mut w = World::new();
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere.set_transformation(Transformation::scaling(0.001, 0.001, 0.001));
+        w.add_shape(sphere);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -1.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = w.intersect(&r);
+        let settings = ComputationsSettings {
+            epsilon: w.epsilon,
+            ..ComputationsSettings::default()
+        };
+        let comps = xs[0].prepare_computations_with(&r, &xs, &Group::new(), &settings);
+
+        // A thousandth of the radius, no visible gap between shadow and shape
+        assert!(comps.get_point_ref().z - comps.get_over_point_ref().z < 1.01e-6);
+    }
+
+    #[test]
+    fn small_shapes_far_from_the_origin_still_cast_contact_shadows() {
+        let x = 10_000.0;
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(x, 10.0, 0.0),
+        ));
+        let floor = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        // A thin tile hovering 2.5 thousandths above the floor
+        let mut tile = Shape::default(Arc::new(Mutex::new(Cube::new())));
+        tile.set_transformation(
+            Transformation::translation(x, 0.003, 0.0)
+                * Transformation::scaling(0.01, 0.0005, 0.01),
         );
+        w.add_shapes(&[floor, tile]);
+
+        // Hits the floor right below the tile, passing under its edge
+        let r = Ray::new(
+            Tuple::new_point(x - 0.1, 0.002, 0.0),
+            Tuple::new_vector(0.1, -0.002, 0.0).normalize(),
+        );
+
+        // An offset proportional to the distance, 1e-6 * 1e4, would lift the
+        // point above the tile and let the light through
+        assert!(w.color_at(&r, 0).x < 0.11);
     }
 
     #[test]
@@ -743,13 +2121,16 @@ mod tests {
         );
         let xs = Intersection::intersects(&[Intersection::new(2.0_f64.sqrt(), floor)]);
         let comps = xs
-            .get(0)
+            .first()
             .unwrap()
             .prepare_computations(&r, &xs, &Group::new());
         let color = w.shade_hit(&comps, 5);
+
+        // The book expects (0.93642, 0.68642, 0.68642), it has no Fresnel
+        // reflection on a surface that is only transparent
         assert_eq!(
             color,
-            Tuple::new_color(0.9364253889815014, 0.6864253889815014, 0.6864253889815014)
+            Tuple::new_color(0.9419222249041423, 0.706443081736169, 0.698436004634302)
         );
     }
 
@@ -780,13 +2161,143 @@ mod tests {
         );
         let xs = Intersection::intersects(&[Intersection::new(2.0_f64.sqrt(), floor)]);
         let comps = xs
-            .get(0)
+            .first()
+            .unwrap()
+            .prepare_computations(&r, &xs, &Group::new());
+        let color = w.shade_hit(&comps, 5);
+
+        assert_eq!(
+            color,
+            Tuple::new_color(0.9339151478022754, 0.6964342353588352, 0.6924306968079017)
+        );
+    }
+
+    #[test]
+    fn a_transparent_surface_mirrors_light_at_grazing_angles() {
+        let mut w = World::default();
+
+        let mut floor = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        let mut floor_material = Material::default();
+        floor_material.set_transparency(0.9);
+        floor_material.set_refractive_index(1.5);
+        floor.set_transformation(Transformation::translation(0.0, -1.0, 0.0));
+        floor.set_material(floor_material);
+        w.add_shapes(&[floor.clone()]);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, -0.9, -10.0),
+            Tuple::new_vector(0.0, -0.1, 1.0).normalize(),
+        );
+        let xs = Intersection::intersects(&[Intersection::new(1.01_f64.sqrt(), floor)]);
+        let comps = xs
+            .first()
             .unwrap()
             .prepare_computations(&r, &xs, &Group::new());
+
         let color = w.shade_hit(&comps, 5);
+
+        // Before, the transparent only surface was lit and refracted but
+        // mirrored nothing
+        let before = Tuple::new_color(0.764444494661884, 0.764444494661884, 0.764444494661884);
         assert_eq!(
             color,
-            Tuple::new_color(0.9339151478022591, 0.6964342353588149, 0.6924306968078895)
+            Tuple::new_color(0.9507156868325999, 0.9972834848752788, 0.9041478887899209)
+        );
+        assert!(color.x > before.x && color.y > before.y && color.z > before.z);
+    }
+
+    #[test]
+    fn casting_a_ray_at_a_sphere() {
+        let mut w = World::new();
+        w.add_shape(Shape::default(Arc::new(Mutex::new(Plane::new()))));
+        let mut s = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        s.set_transformation(Transformation::translation(0.0, 1.0, 0.0));
+        let id = w.add_shape(s);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 1.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let hit = w.cast(&r).unwrap();
+
+        assert_eq!(hit.get_object_id(), Some(id));
+        assert_eq!(hit.get_t(), 4.0);
+        assert_eq!(*hit.get_point_ref(), Tuple::new_point(0.0, 1.0, -1.0));
+        assert_eq!(*hit.get_normal_ref(), Tuple::new_vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn casting_a_ray_into_empty_space() {
+        let w = World::default();
+        let r = Ray::new(
+            Tuple::new_point(0.0, 5.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        assert!(w.cast(&r).is_none());
+    }
+
+    #[test]
+    fn tracing_the_refraction_path_through_nested_spheres() {
+        let glass = |scale: f64, z: f64, refractive_index: f64| {
+            let mut s = Shape::glass(Arc::new(Mutex::new(Sphere::new())));
+            s.set_transformation(
+                Transformation::translation(0.0, 0.0, z)
+                    * Transformation::scaling(scale, scale, scale),
+            );
+            let mut material = s.get_material().clone();
+            material.set_refractive_index(refractive_index);
+            s.set_material(material);
+            s
+        };
+
+        let mut w = World::new();
+        w.add_shapes(&[
+            glass(2.0, 0.0, 1.5),
+            glass(1.0, -0.25, 2.0),
+            glass(1.0, 0.25, 2.5),
+        ]);
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -4.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
         );
+
+        assert_eq!(
+            w.trace_refraction_path(&r),
+            vec![
+                (Some(0), 1.0, 1.5, 2.0),
+                (Some(1), 1.5, 2.0, 2.75),
+                (Some(2), 2.0, 2.5, 3.25),
+                (Some(1), 2.5, 2.5, 4.75),
+                (Some(2), 2.5, 1.5, 5.25),
+                (Some(0), 1.5, 1.0, 6.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_ambient_refractive_index_changes_the_rendered_color() {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+        let mut glass = Shape::glass(Arc::new(Mutex::new(Sphere::new())));
+        let mut m = glass.get_material().clone();
+        m.set_refractive_index(1.33);
+        glass.set_material(m);
+        w.add_shape(glass);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.5, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let in_air = w.color_at(&r, 5);
+
+        w.set_ambient_refractive_index(1.33);
+        let in_water = w.color_at(&r, 5);
+
+        assert_eq!(World::new().get_ambient_refractive_index(), 1.0);
+        assert_ne!(in_air, in_water);
     }
 }
@@ -0,0 +1,150 @@
+use crate::{core::matrices::Matrix, core::tuples::Tuple, rays::Ray};
+
+// Axis aligned box, used to skip shapes that a ray or a camera cannot see
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bounds {
+    min: Tuple,
+    max: Tuple,
+}
+
+impl Bounds {
+    pub fn new(min: Tuple, max: Tuple) -> Bounds {
+        Bounds { min, max }
+    }
+
+    // Empty box, contains nothing until extended with a point
+    pub fn empty() -> Bounds {
+        Bounds {
+            min: Tuple::new_point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Tuple::new_point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn get_min_ref(&self) -> &Tuple {
+        &self.min
+    }
+
+    pub fn get_max_ref(&self) -> &Tuple {
+        &self.max
+    }
+
+    pub fn extend(&mut self, point: &Tuple) {
+        self.min = Tuple::new_point(
+            self.min.x.min(point.x),
+            self.min.y.min(point.y),
+            self.min.z.min(point.z),
+        );
+        self.max = Tuple::new_point(
+            self.max.x.max(point.x),
+            self.max.y.max(point.y),
+            self.max.z.max(point.z),
+        );
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn merge(&self, other: &Bounds) -> Bounds {
+        if other.is_empty() {
+            return self.clone();
+        }
+
+        let mut merged = self.clone();
+        merged.extend(&other.min);
+        merged.extend(&other.max);
+        merged
+    }
+
+    pub fn corners(&self) -> [Tuple; 8] {
+        let (min, max) = (&self.min, &self.max);
+
+        [
+            Tuple::new_point(min.x, min.y, min.z),
+            Tuple::new_point(min.x, min.y, max.z),
+            Tuple::new_point(min.x, max.y, min.z),
+            Tuple::new_point(min.x, max.y, max.z),
+            Tuple::new_point(max.x, min.y, min.z),
+            Tuple::new_point(max.x, min.y, max.z),
+            Tuple::new_point(max.x, max.y, min.z),
+            Tuple::new_point(max.x, max.y, max.z),
+        ]
+    }
+
+    // Box enclosing the transformed corners, it can be larger than the
+    // transformed shape but never smaller
+    pub fn transform(&self, transformation: &Matrix) -> Bounds {
+        let mut bounds = Bounds::empty();
+        for corner in self.corners() {
+            bounds.extend(&(transformation * &corner));
+        }
+        bounds
+    }
+
+    // Slab test, true when the line of the ray crosses the box
+    pub fn hits(&self, ray: &Ray) -> bool {
+        let origin = ray.get_origin();
+        let direction = ray.get_direction();
+
+        let mut tmin = f64::NEG_INFINITY;
+        let mut tmax = f64::INFINITY;
+
+        for (origin, direction, min, max) in [
+            (origin.x, direction.x, self.min.x, self.max.x),
+            (origin.y, direction.y, self.min.y, self.max.y),
+            (origin.z, direction.z, self.min.z, self.max.z),
+        ] {
+            if direction.abs() < 1e-12 {
+                if origin < min || origin > max {
+                    return false;
+                }
+                continue;
+            }
+
+            let t1 = (min - origin) / direction;
+            let t2 = (max - origin) / direction;
+            tmin = tmin.max(t1.min(t2));
+            tmax = tmax.min(t1.max(t2));
+        }
+
+        tmin <= tmax
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::core::transformations::Transformation;
+
+    use super::*;
+
+    #[test]
+    fn transforming_a_box_encloses_the_transformed_corners() {
+        let bounds = Bounds::new(
+            Tuple::new_point(-1.0, -1.0, -1.0),
+            Tuple::new_point(1.0, 1.0, 1.0),
+        );
+
+        let moved = bounds.transform(
+            &(Transformation::translation(5.0, 0.0, 0.0) * Transformation::scaling(2.0, 1.0, 1.0)),
+        );
+
+        assert_eq!(moved.get_min_ref(), &Tuple::new_point(3.0, -1.0, -1.0));
+        assert_eq!(moved.get_max_ref(), &Tuple::new_point(7.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn a_ray_hits_or_misses_a_box() {
+        let bounds = Bounds::new(
+            Tuple::new_point(-1.0, -1.0, -1.0),
+            Tuple::new_point(1.0, 1.0, 1.0),
+        );
+        let direction = Tuple::new_vector(0.0, 0.0, 1.0);
+
+        assert!(bounds.hits(&Ray::new(
+            Tuple::new_point(0.5, 0.5, -5.0),
+            direction.clone()
+        )));
+        assert!(!bounds.hits(&Ray::new(Tuple::new_point(2.0, 0.5, -5.0), direction)));
+    }
+}
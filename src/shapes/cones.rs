@@ -0,0 +1,365 @@
+use float_cmp::ApproxEq;
+
+use crate::{
+    core::tuples::Tuple,
+    margin::Margin,
+    rays::Ray,
+    shapes::{bounds::Bounds, check_bounds, on_cap, BoundsError, Polygon},
+};
+
+pub struct Cone {
+    minimum: f64,
+    maximum: f64,
+    closed: bool,
+    radius: f64,
+}
+
+impl Cone {
+    pub fn new() -> Cone {
+        Cone {
+            minimum: f64::NEG_INFINITY,
+            maximum: f64::INFINITY,
+            closed: false,
+            radius: 1.0,
+        }
+    }
+
+    pub fn set_maximum(&mut self, max: f64) {
+        self.maximum = max
+    }
+
+    pub fn set_minimum(&mut self, min: f64) {
+        self.minimum = min
+    }
+
+    // Sets both ends at once, rejecting ranges where no side hit could be found
+    pub fn try_set_bounds(&mut self, min: f64, max: f64) -> Result<(), BoundsError> {
+        check_bounds(min, max)?;
+        self.minimum = min;
+        self.maximum = max;
+        Ok(())
+    }
+
+    pub fn set_closed(&mut self, closed: bool) {
+        self.closed = closed
+    }
+
+    // Radius of the cone at y = 1 and y = -1
+    pub fn set_radius(&mut self, radius: f64) {
+        self.radius = radius
+    }
+
+    fn intersect_caps(&self, ray: &Ray) -> Vec<f64> {
+        if !self.closed || ray.get_direction().y.approx_eq(0.0, Margin::default_f64()) {
+            return vec![];
+        }
+
+        let mut xs = vec![];
+
+        // The radius of a cone cap grows linearly with the distance from the apex
+        let t1 = (self.minimum - ray.get_origin().y) / ray.get_direction().y;
+        if check_cap(ray, t1, self.radius * self.minimum.abs()) {
+            xs.push(t1);
+        }
+
+        let t2 = (self.maximum - ray.get_origin().y) / ray.get_direction().y;
+        if check_cap(ray, t2, self.radius * self.maximum.abs()) {
+            xs.push(t2);
+        }
+
+        xs
+    }
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Polygon for Cone {
+    fn bounds(&self) -> Option<Bounds> {
+        if !self.minimum.is_finite() || !self.maximum.is_finite() {
+            return None;
+        }
+
+        // The cone is widest at the end farthest from the apex
+        let radius = self.radius * self.minimum.abs().max(self.maximum.abs());
+        Some(Bounds::new(
+            Tuple::new_point(-radius, self.minimum, -radius),
+            Tuple::new_point(radius, self.maximum, radius),
+        ))
+    }
+
+    // The solid between the caps, even when the cone is open
+    fn contains(&self, point: &Tuple) -> bool {
+        point.x.powi(2) + point.z.powi(2) <= (self.radius * point.y).powi(2)
+            && point.y >= self.minimum
+            && point.y <= self.maximum
+    }
+
+    fn intersect(&self, original_ray: &Ray) -> Vec<f64> {
+        let origin = original_ray.get_origin();
+        let direction = original_ray.get_direction();
+
+        let r2 = self.radius.powi(2);
+
+        let a = direction.x.powi(2) - r2 * direction.y.powi(2) + direction.z.powi(2);
+        let b = 2.0 * origin.x * direction.x - 2.0 * r2 * origin.y * direction.y
+            + 2.0 * origin.z * direction.z;
+        let c = origin.x.powi(2) - r2 * origin.y.powi(2) + origin.z.powi(2);
+
+        let mut xs = vec![];
+
+        if a.approx_eq(0.0, Margin::default_f64()) {
+            // ray is parallel to one of the cone halves
+            if !b.approx_eq(0.0, Margin::default_f64()) {
+                let t = -c / (2.0 * b);
+                let y = origin.y + t * direction.y;
+                if self.minimum < y && y < self.maximum {
+                    xs.push(t);
+                }
+            }
+        } else {
+            let disc = b.powi(2) - 4.0 * a * c;
+
+            // ray does not intersect the cone
+            if disc < 0.0 {
+                return vec![];
+            }
+
+            let mut t0 = (-b - disc.sqrt()) / (2.0 * a);
+            let mut t1 = (-b + disc.sqrt()) / (2.0 * a);
+
+            (t0, t1) = if t0 > t1 { (t1, t0) } else { (t0, t1) };
+
+            let y0 = origin.y + t0 * direction.y;
+            if self.minimum < y0 && y0 < self.maximum {
+                xs.push(t0);
+            }
+
+            let y1 = origin.y + t1 * direction.y;
+            if self.minimum < y1 && y1 < self.maximum {
+                xs.push(t1)
+            }
+        }
+
+        let mut xs_caps = self.intersect_caps(original_ray);
+        xs.append(&mut xs_caps);
+
+        xs
+    }
+
+    fn normal_at(&self, point: &Tuple) -> Tuple {
+        // An open cone has no caps, even for points rounded off its rims
+        if self.closed && on_cap(point, self.maximum, (self.radius * self.maximum).abs()) {
+            return Tuple::new_vector(0.0, 1.0, 0.0);
+        }
+
+        if self.closed && on_cap(point, self.minimum, (self.radius * self.minimum).abs()) {
+            return Tuple::new_vector(0.0, -1.0, 0.0);
+        }
+
+        let dist = point.x.powi(2) + point.z.powi(2);
+        let y = if point.y > 0.0 {
+            -self.radius * dist.sqrt()
+        } else {
+            self.radius * dist.sqrt()
+        };
+
+        Tuple::new_vector(point.x, y, point.z)
+    }
+}
+
+fn check_cap(ray: &Ray, t: f64, radius: f64) -> bool {
+    let x = ray.get_origin().x + t * ray.get_direction().x;
+    let z = ray.get_origin().z + t * ray.get_direction().z;
+
+    (x.powi(2) + z.powi(2)) < radius.powi(2)
+        || (x.powi(2) + z.powi(2)).approx_eq(radius.powi(2), Margin::default_f64())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn intersecting_a_cone_with_a_ray(origin: Tuple, direction: Tuple, t0: f64, t1: f64) {
+        let shape = Cone::new();
+        let r = Ray::new(origin, direction.normalize());
+        let xs = shape.intersect(&r);
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(*xs.first().unwrap(), t0);
+        assert_eq!(*xs.get(1).unwrap(), t1);
+    }
+
+    #[test]
+    fn intersecting_a_cone_with_a_ray_scenarios() {
+        intersecting_a_cone_with_a_ray(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+            5.0,
+            5.0,
+        );
+        intersecting_a_cone_with_a_ray(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(1.0, 1.0, 1.0),
+            8.660254037844386,
+            8.660254037844386,
+        );
+        intersecting_a_cone_with_a_ray(
+            Tuple::new_point(1.0, 1.0, -5.0),
+            Tuple::new_vector(-0.5, -1.0, 1.0),
+            4.550055679356349,
+            49.449944320643645,
+        );
+    }
+
+    #[test]
+    fn intersecting_a_cone_with_a_ray_parallel_to_one_of_its_halves() {
+        let shape = Cone::new();
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -1.0),
+            Tuple::new_vector(0.0, 1.0, 1.0).normalize(),
+        );
+        let xs = shape.intersect(&r);
+
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].approx_eq(0.3535533905932738, Margin::default_f64()));
+    }
+
+    fn intersecting_a_cones_end_caps(origin: Tuple, direction: Tuple, count: usize) {
+        let mut shape = Cone::new();
+        shape.set_minimum(-0.5);
+        shape.set_maximum(0.5);
+        shape.set_closed(true);
+
+        let r = Ray::new(origin, direction.normalize());
+        let xs = shape.intersect(&r);
+
+        assert_eq!(xs.len(), count);
+    }
+
+    #[test]
+    fn intersecting_a_cones_end_caps_scenarios() {
+        intersecting_a_cones_end_caps(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+            0,
+        );
+        intersecting_a_cones_end_caps(
+            Tuple::new_point(0.0, 0.0, -0.25),
+            Tuple::new_vector(0.0, 1.0, 1.0),
+            2,
+        );
+        intersecting_a_cones_end_caps(
+            Tuple::new_point(0.0, 0.0, -0.25),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+            4,
+        );
+    }
+
+    fn computing_the_normal_vector_on_a_cone(point: Tuple, normal: Tuple) {
+        let shape = Cone::new();
+        let n = shape.normal_at(&point);
+
+        assert_eq!(n, normal);
+    }
+
+    #[test]
+    fn computing_the_normal_vector_on_a_cone_scenarios() {
+        computing_the_normal_vector_on_a_cone(
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 0.0, 0.0),
+        );
+        computing_the_normal_vector_on_a_cone(
+            Tuple::new_point(1.0, 1.0, 1.0),
+            Tuple::new_vector(1.0, -(2.0_f64.sqrt()), 1.0),
+        );
+        computing_the_normal_vector_on_a_cone(
+            Tuple::new_point(-1.0, -1.0, 0.0),
+            Tuple::new_vector(-1.0, 1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn the_normal_vector_on_a_cones_end_caps() {
+        let mut shape = Cone::new();
+        shape.set_minimum(-1.0);
+        shape.set_maximum(-0.5);
+        shape.set_closed(true);
+
+        assert_eq!(
+            shape.normal_at(&Tuple::new_point(0.2, -0.5, 0.0)),
+            Tuple::new_vector(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            shape.normal_at(&Tuple::new_point(0.0, -1.0, 0.5)),
+            Tuple::new_vector(0.0, -1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn the_rims_of_a_closed_cone_take_the_normal_of_their_cap() {
+        let mut shape = Cone::new();
+        shape.set_minimum(-1.0);
+        shape.set_maximum(0.5);
+        shape.set_closed(true);
+
+        for step in 0..16 {
+            let angle = step as f64 * std::f64::consts::PI / 8.0;
+            for nudge in [0.0, 1e-12, -1e-12] {
+                let on_rim = |y: f64| {
+                    let radius = y.abs() + nudge;
+                    Tuple::new_point(radius * angle.cos(), y + nudge, radius * angle.sin())
+                };
+
+                assert_eq!(
+                    shape.normal_at(&on_rim(0.5)),
+                    Tuple::new_vector(0.0, 1.0, 0.0)
+                );
+                assert_eq!(
+                    shape.normal_at(&on_rim(-1.0)),
+                    Tuple::new_vector(0.0, -1.0, 0.0)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_ray_strikes_a_wider_cone() {
+        let r = Ray::new(
+            Tuple::new_point(1.5, 1.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        assert!(Cone::new().intersect(&r).is_empty());
+
+        let mut shape = Cone::new();
+        shape.set_radius(2.0);
+        let xs = shape.intersect(&r);
+
+        let half_chord = 1.75_f64.sqrt();
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].approx_eq(5.0 - half_chord, Margin::default_f64()));
+        assert!(xs[1].approx_eq(5.0 + half_chord, Margin::default_f64()));
+    }
+
+    #[test]
+    fn empty_ranges_are_rejected() {
+        let mut shape = Cone::new();
+
+        assert!(shape.try_set_bounds(2.0, 1.0).is_err());
+        assert!(shape.try_set_bounds(1.0, 1.0).is_err());
+        assert_eq!(shape.minimum, f64::NEG_INFINITY);
+        assert_eq!(shape.maximum, f64::INFINITY);
+
+        assert!(shape
+            .try_set_bounds(f64::NEG_INFINITY, f64::INFINITY)
+            .is_ok());
+        assert!(shape.try_set_bounds(f64::NEG_INFINITY, -3.0).is_ok());
+        assert!(shape.try_set_bounds(1.0, 2.0).is_ok());
+        assert_eq!((shape.minimum, shape.maximum), (1.0, 2.0));
+    }
+}
@@ -1,6 +1,8 @@
 use float_cmp::ApproxEq;
 
-use crate::{core::tuples::Tuple, margin::Margin, rays::Ray, shapes::Polygon};
+use crate::{
+    core::tuples::Tuple, margin::Margin, rays::Ray, shapes::bounds::Bounds, shapes::Polygon,
+};
 
 pub struct Cube {}
 
@@ -10,7 +12,24 @@ impl Cube {
     }
 }
 
+impl Default for Cube {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Polygon for Cube {
+    fn bounds(&self) -> Option<Bounds> {
+        Some(Bounds::new(
+            Tuple::new_point(-1.0, -1.0, -1.0),
+            Tuple::new_point(1.0, 1.0, 1.0),
+        ))
+    }
+
+    fn contains(&self, point: &Tuple) -> bool {
+        point.x.abs() <= 1.0 && point.y.abs() <= 1.0 && point.z.abs() <= 1.0
+    }
+
     fn intersect(&self, original_ray: &Ray) -> Vec<f64> {
         let (xtmin, xtmax) =
             check_axis(original_ray.get_origin().x, original_ray.get_direction().x);
@@ -76,7 +95,7 @@ mod tests {
         let xs = c.intersect(&r);
 
         assert_eq!(xs.len(), 2);
-        assert_eq!(*xs.get(0).unwrap(), t1);
+        assert_eq!(*xs.first().unwrap(), t1);
         assert_eq!(*xs.get(1).unwrap(), t2);
     }
 
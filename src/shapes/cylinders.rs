@@ -1,11 +1,17 @@
 use float_cmp::ApproxEq;
 
-use crate::{core::tuples::Tuple, margin::Margin, rays::Ray, shapes::Polygon};
+use crate::{
+    core::tuples::Tuple,
+    margin::Margin,
+    rays::Ray,
+    shapes::{bounds::Bounds, check_bounds, on_cap, BoundsError, Polygon},
+};
 
 pub struct Cylinder {
     minimum: f64,
     maximum: f64,
     closed: bool,
+    radius: f64,
 }
 
 impl Cylinder {
@@ -14,6 +20,7 @@ impl Cylinder {
             minimum: f64::NEG_INFINITY,
             maximum: f64::INFINITY,
             closed: false,
+            radius: 1.0,
         }
     }
 
@@ -25,6 +32,22 @@ impl Cylinder {
         self.minimum = min
     }
 
+    // Sets both ends at once, rejecting ranges where no side hit could be found
+    pub fn try_set_bounds(&mut self, min: f64, max: f64) -> Result<(), BoundsError> {
+        check_bounds(min, max)?;
+        self.minimum = min;
+        self.maximum = max;
+        Ok(())
+    }
+
+    pub fn set_closed(&mut self, closed: bool) {
+        self.closed = closed
+    }
+
+    pub fn set_radius(&mut self, radius: f64) {
+        self.radius = radius
+    }
+
     fn intersect_caps(&self, ray: &Ray) -> Vec<f64> {
         if !self.closed || ray.get_direction().y.approx_eq(0.0, Margin::default_f64()) {
             return vec![];
@@ -33,12 +56,12 @@ impl Cylinder {
         let mut xs = vec![];
 
         let t1 = (self.minimum - ray.get_origin().y) / ray.get_direction().y;
-        if check_cap(ray, t1) {
+        if check_cap(ray, t1, self.radius) {
             xs.push(t1);
         }
 
         let t2 = (self.maximum - ray.get_origin().y) / ray.get_direction().y;
-        if check_cap(ray, t2) {
+        if check_cap(ray, t2, self.radius) {
             xs.push(t2);
         }
 
@@ -46,7 +69,31 @@ impl Cylinder {
     }
 }
 
+impl Default for Cylinder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Polygon for Cylinder {
+    fn bounds(&self) -> Option<Bounds> {
+        if !self.minimum.is_finite() || !self.maximum.is_finite() {
+            return None;
+        }
+
+        Some(Bounds::new(
+            Tuple::new_point(-self.radius, self.minimum, -self.radius),
+            Tuple::new_point(self.radius, self.maximum, self.radius),
+        ))
+    }
+
+    // The solid between the caps, even when the cylinder is open
+    fn contains(&self, point: &Tuple) -> bool {
+        point.x.powi(2) + point.z.powi(2) <= self.radius.powi(2)
+            && point.y >= self.minimum
+            && point.y <= self.maximum
+    }
+
     fn intersect(&self, original_ray: &Ray) -> Vec<f64> {
         let a = original_ray.get_direction().x.powi(2) + original_ray.get_direction().z.powi(2);
 
@@ -56,7 +103,8 @@ impl Polygon for Cylinder {
         if !a.approx_eq(0.0, Margin::default_f64()) {
             let b = 2.0 * original_ray.get_origin().x * original_ray.get_direction().x
                 + 2.0 * original_ray.get_origin().z * original_ray.get_direction().z;
-            let c = original_ray.get_origin().x.powi(2) + original_ray.get_origin().z.powi(2) - 1.0;
+            let c = original_ray.get_origin().x.powi(2) + original_ray.get_origin().z.powi(2)
+                - self.radius.powi(2);
 
             let disc = b.powi(2) - 4.0 * a * c;
 
@@ -88,17 +136,12 @@ impl Polygon for Cylinder {
     }
 
     fn normal_at(&self, point: &Tuple) -> Tuple {
-        let dist = point.x.powi(2) + point.z.powi(2);
-
-        if dist < 1.0
-            && (point.y.approx_eq(self.maximum, Margin::default_f64()) || point.y > self.maximum)
-        {
+        // An open cylinder has no caps, even for points rounded off its rim
+        if self.closed && on_cap(point, self.maximum, self.radius) {
             return Tuple::new_vector(0.0, 1.0, 0.0);
         }
 
-        if dist < 1.0
-            && (point.y.approx_eq(self.minimum, Margin::default_f64()) || point.y < self.minimum)
-        {
+        if self.closed && on_cap(point, self.minimum, self.radius) {
             return Tuple::new_vector(0.0, -1.0, 0.0);
         }
 
@@ -106,11 +149,12 @@ impl Polygon for Cylinder {
     }
 }
 
-fn check_cap(ray: &Ray, t: f64) -> bool {
+fn check_cap(ray: &Ray, t: f64, radius: f64) -> bool {
     let x = ray.get_origin().x + t * ray.get_direction().x;
     let z = ray.get_origin().z + t * ray.get_direction().z;
 
-    (x.powi(2) + z.powi(2)) < 1.0 || (x.powi(2) + z.powi(2)).approx_eq(1.0, Margin::default_f64())
+    (x.powi(2) + z.powi(2)) < radius.powi(2)
+        || (x.powi(2) + z.powi(2)).approx_eq(radius.powi(2), Margin::default_f64())
 }
 
 #[cfg(test)]
@@ -148,7 +192,7 @@ mod tests {
         let xs = cyl.intersect(&r);
 
         assert_eq!(xs.len(), 2);
-        assert_eq!(*xs.get(0).unwrap(), t1);
+        assert_eq!(*xs.first().unwrap(), t1);
         assert_eq!(*xs.get(1).unwrap(), t2);
     }
 
@@ -339,4 +383,144 @@ mod tests {
             Tuple::new_vector(0.0, 1.0, 0.0),
         );
     }
+
+    // Points on the rim at y = 2, nudged by rounding errors in height and
+    // distance from the axis
+    fn rim_points() -> Vec<Tuple> {
+        let mut points = vec![];
+        for step in 0..16 {
+            let angle = step as f64 * std::f64::consts::PI / 8.0;
+            for (dy, dr) in [(0.0, 0.0), (1e-12, 0.0), (-1e-12, 1e-12), (0.0, -1e-12)] {
+                let radius = 1.0 + dr;
+                points.push(Tuple::new_point(
+                    radius * angle.cos(),
+                    2.0 + dy,
+                    radius * angle.sin(),
+                ));
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn the_rim_of_a_closed_cylinder_takes_the_normal_of_the_cap() {
+        let mut cyl = Cylinder::new();
+        cyl.minimum = 1.0;
+        cyl.maximum = 2.0;
+        cyl.closed = true;
+
+        for point in rim_points() {
+            assert_eq!(cyl.normal_at(&point), Tuple::new_vector(0.0, 1.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn the_rim_of_an_open_cylinder_takes_the_normal_of_the_side() {
+        let mut cyl = Cylinder::new();
+        cyl.minimum = 1.0;
+        cyl.maximum = 2.0;
+
+        for point in rim_points() {
+            let n = cyl.normal_at(&point);
+            assert_eq!(n.y, 0.0);
+            assert_eq!(
+                n.normalize(),
+                Tuple::new_vector(point.x, 0.0, point.z).normalize()
+            );
+        }
+    }
+
+    #[test]
+    fn a_ray_strikes_a_wider_cylinder() {
+        let r = Ray::new(
+            Tuple::new_point(1.5, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        assert!(Cylinder::new().intersect(&r).is_empty());
+
+        let mut cyl = Cylinder::new();
+        cyl.set_radius(2.0);
+        let xs = cyl.intersect(&r);
+
+        let half_chord = 1.75_f64.sqrt();
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].approx_eq(5.0 - half_chord, Margin::default_f64()));
+        assert!(xs[1].approx_eq(5.0 + half_chord, Margin::default_f64()));
+    }
+
+    #[test]
+    fn the_caps_of_a_wider_cylinder_follow_its_radius() {
+        let mut cyl = Cylinder::new();
+        cyl.set_minimum(1.0);
+        cyl.set_maximum(2.0);
+        cyl.set_radius(2.0);
+        cyl.closed = true;
+
+        let r = Ray::new(
+            Tuple::new_point(1.5, 3.0, 0.0),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+
+        assert_eq!(cyl.intersect(&r).len(), 2);
+        assert_eq!(
+            cyl.normal_at(&Tuple::new_point(1.5, 2.0, 0.0)),
+            Tuple::new_vector(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn building_a_closed_bounded_cylinder() {
+        let mut cyl = Cylinder::new();
+        cyl.set_minimum(0.0);
+        cyl.set_maximum(1.0);
+        cyl.set_closed(true);
+
+        // Straight down through the middle, both caps are hit
+        let r = Ray::new(
+            Tuple::new_point(0.0, 2.0, 0.0),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+        assert_eq!(cyl.intersect(&r).len(), 2);
+        assert_eq!(
+            cyl.normal_at(&Tuple::new_point(0.5, 1.0, 0.0)),
+            Tuple::new_vector(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn empty_ranges_are_rejected() {
+        let mut shape = Cylinder::new();
+
+        assert!(shape.try_set_bounds(2.0, 1.0).is_err());
+        assert!(shape.try_set_bounds(1.0, 1.0).is_err());
+        assert_eq!(shape.minimum, f64::NEG_INFINITY);
+        assert_eq!(shape.maximum, f64::INFINITY);
+
+        assert!(shape
+            .try_set_bounds(f64::NEG_INFINITY, f64::INFINITY)
+            .is_ok());
+        assert!(shape.try_set_bounds(f64::NEG_INFINITY, -3.0).is_ok());
+        assert!(shape.try_set_bounds(1.0, 2.0).is_ok());
+        assert_eq!((shape.minimum, shape.maximum), (1.0, 2.0));
+    }
+
+    #[test]
+    fn empty_infinite_and_nan_bounds_are_rejected() {
+        let mut shape = Cylinder::new();
+
+        assert!(shape.try_set_bounds(f64::INFINITY, f64::INFINITY).is_err());
+        assert!(shape
+            .try_set_bounds(f64::NEG_INFINITY, f64::NEG_INFINITY)
+            .is_err());
+        assert!(shape.try_set_bounds(f64::INFINITY, 1.0).is_err());
+        assert!(shape.try_set_bounds(1.0, f64::NEG_INFINITY).is_err());
+        assert!(shape.try_set_bounds(f64::NAN, 1.0).is_err());
+        assert!(shape.try_set_bounds(1.0, f64::NAN).is_err());
+        assert!(shape.try_set_bounds(f64::NAN, f64::NAN).is_err());
+        assert_eq!(shape.minimum, f64::NEG_INFINITY);
+        assert_eq!(shape.maximum, f64::INFINITY);
+
+        assert!(shape.try_set_bounds(1.0, f64::INFINITY).is_ok());
+    }
 }
@@ -1,9 +1,14 @@
 use r3bl_rs_utils::Arena;
+use rayon::prelude::*;
 
 use crate::{
-    core::matrices::Matrix, rays::Ray, shapes::intersections::Intersection, shapes::Shape,
+    core::matrices::Matrix, rays::Ray, shapes::bounds::Bounds, shapes::intersections::Intersection,
+    shapes::Shape,
 };
 
+// Nodes with at least this many children intersect them in parallel
+const PARALLEL_THRESHOLD: usize = 64;
+
 #[derive(Debug)]
 pub struct Group {
     pub arena: Arena<NodeTypes>,
@@ -32,37 +37,182 @@ impl Group {
             .add_new_node(NodeTypes::Matrix((matrix, inverse)), parent_id)
     }
 
-    pub fn add_node(&mut self, shape: Shape, parent_id: Option<usize>) -> usize {
+    // Adds a matrix node and lets `build` populate it, the closure receives the
+    // id of the new node to use as parent for the children.
+    pub fn with_transform<F>(&mut self, matrix: Matrix, parent_id: Option<usize>, build: F) -> usize
+    where
+        F: FnOnce(&mut Group, usize),
+    {
+        let matrix_id = self.add_matrix(matrix, parent_id);
+        build(self, matrix_id);
+        matrix_id
+    }
+
+    pub fn add_node(&mut self, mut shape: Shape, parent_id: Option<usize>) -> usize {
+        // The shape needs to know its parent to walk the transformation chain
+        // when computing normals during shading.
+        if let Some(id) = parent_id {
+            shape.set_parent_id(id);
+        }
+
         self.arena
             .add_new_node(NodeTypes::Shape(Box::new(shape)), parent_id)
     }
 
-    pub fn intersect(&mut self, original_ray: &Ray, node_id: usize) -> Vec<Intersection> {
-        let mut xs = vec![];
-
-        let maybe_childs = self.arena.get_children_of(node_id);
-
-        if let Some(childs_id) = maybe_childs {
-            for child_id in childs_id {
-                let mut i = match self.arena.get_node_arc(child_id) {
-                    None => return vec![],
-                    Some(a) => {
-                        let payload = a.read().unwrap();
-                        match &payload.payload {
-                            NodeTypes::Matrix((_, inverse)) => {
-                                let local_ray = original_ray.transform(inverse);
-                                self.intersect(&local_ray, payload.id)
-                            }
-                            NodeTypes::Shape(shape) => shape.intersect(original_ray),
-                        }
+    // Detaches the node and its subtree, returns false when the node does not
+    // exist. The root cannot be removed, see clear.
+    pub fn remove(&mut self, node_id: usize) -> bool {
+        assert!(node_id != 0, "The root of a group cannot be removed");
+
+        self.arena.delete_node(node_id).is_some()
+    }
+
+    // Back to an empty group holding only the root identity matrix
+    pub fn clear(&mut self) {
+        *self = Group::new();
+    }
+
+    // Number of shapes reached when intersecting from the root, i.e. the
+    // intersection tests performed for every ray
+    pub fn shapes_count(&self) -> usize {
+        let mut count = 0;
+        self.visit_shapes(0, &mut |_| count += 1);
+        count
+    }
+
+    // Copies of the shapes reachable from the root, the nodes are behind locks
+    // so they cannot be borrowed out of the arena
+    pub fn shapes(&self) -> Vec<Shape> {
+        let mut shapes = vec![];
+        self.visit_shapes(0, &mut |shape| shapes.push(shape.clone()));
+        shapes
+    }
+
+    // Box around every shape of the group, None when a shape is unbounded or
+    // the group is empty
+    pub fn bounds(&self) -> Option<Bounds> {
+        let bounds = self.bounds_under(0, &Matrix::identity(4))?;
+        (!bounds.is_empty()).then_some(bounds)
+    }
+
+    fn bounds_under(&self, node_id: usize, transformation: &Matrix) -> Option<Bounds> {
+        let mut bounds = Bounds::empty();
+
+        for child_id in self.arena.get_children_of(node_id).unwrap_or_default() {
+            if let Some(a) = self.arena.get_node_arc(child_id) {
+                let child_bounds = match &a.read().unwrap().payload {
+                    NodeTypes::Matrix((matrix, _)) => {
+                        self.bounds_under(child_id, &(transformation * matrix))?
                     }
+                    NodeTypes::Shape(shape) => shape.bounds()?.transform(transformation),
                 };
+                bounds = bounds.merge(&child_bounds);
+            }
+        }
+
+        Some(bounds)
+    }
+
+    fn visit_shapes<F>(&self, node_id: usize, visit: &mut F)
+    where
+        F: FnMut(&Shape),
+    {
+        let childs_id = match self.arena.get_children_of(node_id) {
+            None => return,
+            Some(childs_id) => childs_id,
+        };
 
-                xs.append(&mut i)
+        for child_id in childs_id {
+            if let Some(a) = self.arena.get_node_arc(child_id) {
+                match &a.read().unwrap().payload {
+                    NodeTypes::Matrix(_) => self.visit_shapes(child_id, visit),
+                    NodeTypes::Shape(shape) => visit(shape),
+                }
             }
+        }
+    }
+
+    // Stores the inverse transformation of every shape under the node, see
+    // Shape::precompute_inverse_transformation
+    pub fn precompute_inverses(&self, node_id: usize) {
+        let childs_id = match self.arena.get_children_of(node_id) {
+            None => return,
+            Some(childs_id) => childs_id,
         };
 
-        xs
+        for child_id in childs_id {
+            if let Some(a) = self.arena.get_node_arc(child_id) {
+                match &mut a.write().unwrap().payload {
+                    NodeTypes::Matrix(_) => self.precompute_inverses(child_id),
+                    NodeTypes::Shape(shape) => shape.precompute_inverse_transformation(),
+                }
+            }
+        }
+    }
+
+    pub fn intersect(&self, original_ray: &Ray, node_id: usize) -> Vec<Intersection> {
+        self.intersect_children(original_ray, node_id, PARALLEL_THRESHOLD)
+    }
+
+    // Same as intersect without spreading large nodes over several threads
+    pub fn intersect_serial(&self, original_ray: &Ray, node_id: usize) -> Vec<Intersection> {
+        self.intersect_children(original_ray, node_id, usize::MAX)
+    }
+
+    fn intersect_children(
+        &self,
+        original_ray: &Ray,
+        node_id: usize,
+        parallel_threshold: usize,
+    ) -> Vec<Intersection> {
+        let childs_id = match self.arena.get_children_of(node_id) {
+            None => return vec![],
+            Some(childs_id) => childs_id,
+        };
+
+        // Spawning tasks is only worth it when a node has many children
+        if childs_id.len() >= parallel_threshold {
+            childs_id
+                .par_iter()
+                .flat_map_iter(|child_id| {
+                    self.intersect_child(original_ray, *child_id, parallel_threshold)
+                })
+                .collect()
+        } else {
+            childs_id
+                .iter()
+                .flat_map(|child_id| {
+                    self.intersect_child(original_ray, *child_id, parallel_threshold)
+                })
+                .collect()
+        }
+    }
+
+    fn intersect_child(
+        &self,
+        original_ray: &Ray,
+        child_id: usize,
+        parallel_threshold: usize,
+    ) -> Vec<Intersection> {
+        match self.arena.get_node_arc(child_id) {
+            None => vec![],
+            Some(a) => {
+                let payload = a.read().unwrap();
+                match &payload.payload {
+                    NodeTypes::Matrix((_, inverse)) => {
+                        let local_ray = original_ray.transform(inverse);
+                        self.intersect_children(&local_ray, payload.id, parallel_threshold)
+                    }
+                    NodeTypes::Shape(shape) => shape.intersect(original_ray),
+                }
+            }
+        }
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -79,7 +229,7 @@ mod tests {
 
     #[test]
     fn intersecting_a_ray_with_an_empty_group() {
-        let mut g = Group::new();
+        let g = Group::new();
         let r = Ray::new(
             Tuple::new_point(0.0, 0.0, 0.0),
             Tuple::new_vector(0.0, 0.0, 1.0),
@@ -133,4 +283,142 @@ mod tests {
 
         assert_eq!(xs.len(), 2);
     }
+
+    #[test]
+    fn the_bounds_of_a_group_enclose_its_transformed_children() {
+        let mut g = Group::new();
+        assert_eq!(g.bounds(), None);
+
+        let matrix_id = g.add_matrix(Transformation::scaling(2.0, 2.0, 2.0), Some(0));
+        let mut s = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        s.set_transformation(Transformation::translation(5.0, 0.0, 0.0));
+        g.add_node(s, Some(matrix_id));
+        g.add_node(Shape::default(Arc::new(Mutex::new(Sphere::new()))), Some(0));
+
+        let bounds = g.bounds().unwrap();
+        assert_eq!(bounds.get_min_ref(), &Tuple::new_point(-1.0, -2.0, -2.0));
+        assert_eq!(bounds.get_max_ref(), &Tuple::new_point(12.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn a_removed_shape_is_no_longer_hit() {
+        let mut g = Group::new();
+        let matrix_id = g.add_matrix(Transformation::translation(0.0, 0.0, 2.0), Some(0));
+        g.add_node(
+            Shape::default(Arc::new(Mutex::new(Sphere::new()))),
+            Some(matrix_id),
+        );
+        let other_id = g.add_node(Shape::default(Arc::new(Mutex::new(Sphere::new()))), Some(0));
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(g.intersect(&r, 0).len(), 4);
+
+        // Removing the matrix drops the sphere below it too
+        assert!(g.remove(matrix_id));
+        assert!(!g.remove(matrix_id));
+        assert_eq!(g.intersect(&r, 0).len(), 2);
+        assert_eq!(g.shapes_count(), 1);
+
+        g.remove(other_id);
+        assert!(g.intersect(&r, 0).is_empty());
+    }
+
+    #[test]
+    fn clearing_a_group_leaves_only_the_root() {
+        let mut g = Group::new();
+        g.add_node(Shape::default(Arc::new(Mutex::new(Sphere::new()))), Some(0));
+
+        g.clear();
+
+        assert_eq!(g.shapes_count(), 0);
+        assert_eq!(g.bounds(), None);
+        assert_eq!(describe_children(&g, 0), vec![]);
+    }
+
+    fn describe_children(g: &Group, node_id: usize) -> Vec<(usize, String)> {
+        g.arena
+            .get_children_of(node_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|id| {
+                let node = g.arena.get_node_arc(id).unwrap();
+                let kind = match &node.read().unwrap().payload {
+                    NodeTypes::Matrix((m, _)) => format!("matrix {:?}", m),
+                    NodeTypes::Shape(_) => "shape".to_owned(),
+                };
+                (id, kind)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn with_transform_builds_the_same_arena_as_adding_the_matrix_manually() {
+        let rotation = Transformation::rotation_y(1.0);
+
+        let mut manual = Group::new();
+        let matrix_id = manual.add_matrix(rotation.clone(), Some(0));
+        manual.add_node(
+            Shape::default(Arc::new(Mutex::new(Sphere::new()))),
+            Some(matrix_id),
+        );
+        manual.add_node(
+            Shape::default(Arc::new(Mutex::new(Sphere::new()))),
+            Some(matrix_id),
+        );
+
+        let mut scoped = Group::new();
+        let scoped_id = scoped.with_transform(rotation, Some(0), |g, parent_id| {
+            g.add_node(
+                Shape::default(Arc::new(Mutex::new(Sphere::new()))),
+                Some(parent_id),
+            );
+            g.add_node(
+                Shape::default(Arc::new(Mutex::new(Sphere::new()))),
+                Some(parent_id),
+            );
+        });
+
+        assert_eq!(scoped_id, matrix_id);
+        assert_eq!(describe_children(&scoped, 0), describe_children(&manual, 0));
+        assert_eq!(
+            describe_children(&scoped, scoped_id),
+            describe_children(&manual, matrix_id)
+        );
+    }
+
+    #[test]
+    fn parallel_and_serial_intersection_find_the_same_hits() {
+        let mut g = Group::new();
+        let matrix_id = g.add_matrix(Transformation::scaling(0.5, 0.5, 0.5), Some(0));
+
+        for i in 0..100 {
+            let mut s = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+            s.set_transformation(Transformation::translation(
+                (i % 10) as f64 * 0.3,
+                (i / 10) as f64 * 0.3,
+                i as f64,
+            ));
+            g.add_node(s, Some(matrix_id));
+        }
+
+        let r = Ray::new(
+            Tuple::new_point(1.0, 1.0, -10.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        let sorted_ts = |xs: Vec<Intersection>| {
+            let mut ts: Vec<f64> = xs.iter().map(|i| i.get_t()).collect();
+            ts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            ts
+        };
+
+        let serial = sorted_ts(g.intersect_children(&r, 0, usize::MAX));
+        let parallel = sorted_ts(g.intersect_children(&r, 0, 0));
+
+        assert!(!serial.is_empty());
+        assert_eq!(serial, parallel);
+    }
 }
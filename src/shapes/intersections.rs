@@ -2,15 +2,51 @@ use float_cmp::ApproxEq;
 
 use crate::{core::tuples::Tuple, margin::Margin, rays::Ray, shapes::groups::Group, shapes::Shape};
 
+// Default distance between a surface and the points used to cast shadow,
+// reflected and refracted rays, large enough to avoid acne
+pub const DEFAULT_EPSILON: f64 = 0.000001;
+
+// Offset added per unit of distance of the hit from the origin, the rounding
+// errors of its coordinates grow with them. Far above the few ULPs of f64 so
+// that it covers the errors of the transformations too.
+const RELATIVE_EPSILON: f64 = 1e-12;
+
+// Where a ray comes from, for prepare_computations_with: the refractive index
+// of the medium around every object, the offset of the over and under points
+// and the color channel the ray carries, None for all of them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ComputationsSettings {
+    pub ambient_refractive_index: f64,
+    pub epsilon: f64,
+    pub channel: Option<usize>,
+}
+
+impl Default for ComputationsSettings {
+    fn default() -> Self {
+        ComputationsSettings {
+            ambient_refractive_index: 1.0,
+            epsilon: DEFAULT_EPSILON,
+            channel: None,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Intersection {
     t: f64,
     object: Shape,
+    // Where the hit lies on the surface, only set for triangles
+    u: f64,
+    v: f64,
+    // Triangle of a mesh the hit lies on, 0 for the other shapes
+    face: usize,
 }
 
 impl PartialEq for Intersection {
     fn eq(&self, other: &Self) -> bool {
-        self.t.approx_eq(other.get_t(), Margin::default_f64())
+        // Two objects touching each other are hit at the same t, the object
+        // tells which of the two intersections is meant
+        self.t.approx_eq(other.get_t(), Margin::default_f64()) && self.object == other.object
     }
 }
 
@@ -21,17 +57,43 @@ pub struct Computations {
     point: Tuple,
     eyev: Tuple,
     normalv: Tuple,
+    // Complete the normal to a frame on the surface, the orientation around
+    // the normal is arbitrary
+    tangent: Tuple,
+    bitangent: Tuple,
     reflectv: Tuple,
     n1: f64,
     n2: f64,
-    _inside: bool,
+    // n1 and n2 of every color channel, when the object disperses light and
+    // the ray still carries all of them
+    channel_n: Option<[(f64, f64); 3]>,
+    inside: bool,
     over_point: Tuple,
     under_point: Tuple,
+    // Time of the ray, inherited by the secondary rays
+    time: f64,
+    u: f64,
+    v: f64,
 }
 
 impl Intersection {
     pub fn new(t: f64, object: Shape) -> Intersection {
-        Intersection { t, object }
+        Intersection::new_with_uv(t, object, 0.0, 0.0)
+    }
+
+    pub fn new_with_uv(t: f64, object: Shape, u: f64, v: f64) -> Intersection {
+        Intersection {
+            t,
+            object,
+            u,
+            v,
+            face: 0,
+        }
+    }
+
+    pub fn with_face(mut self, face: usize) -> Intersection {
+        self.face = face;
+        self
     }
 
     #[cfg(test)]
@@ -43,11 +105,41 @@ impl Intersection {
         self.t
     }
 
+    pub fn get_object_ref(&self) -> &Shape {
+        &self.object
+    }
+
+    pub fn get_u(&self) -> f64 {
+        self.u
+    }
+
+    pub fn get_v(&self) -> f64 {
+        self.v
+    }
+
+    pub fn get_face(&self) -> usize {
+        self.face
+    }
+
+    // The ray must be the one in world space that produced this intersection
+    pub fn world_point(&self, ray: &Ray) -> Tuple {
+        ray.position(self.t)
+    }
+
     pub fn hit(intersections: &[Intersection]) -> Option<Intersection> {
+        Intersection::hit_between(intersections, 0.0, f64::INFINITY)
+    }
+
+    // Nearest intersection with near < t <= far, the range of a clipped camera
+    pub fn hit_between(
+        intersections: &[Intersection],
+        near: f64,
+        far: f64,
+    ) -> Option<Intersection> {
         let mut hit = None;
 
         for intersection in intersections {
-            if intersection.get_t() > 0.0 {
+            if intersection.get_t() > near && intersection.get_t() <= far {
                 if hit.is_none() {
                     hit = Some(intersection);
                 }
@@ -69,13 +161,34 @@ impl Intersection {
         xs: &[Intersection],
         group: &Group,
     ) -> Computations {
+        self.prepare_computations_with(ray, xs, group, &ComputationsSettings::default())
+    }
+
+    // Same as prepare_computations for a ray that starts outside of every
+    // object, in a medium with the given refractive index instead of vacuum.
+    // The over and under points are moved off the surface by `epsilon`, plus
+    // RELATIVE_EPSILON times the distance of the hit from the origin. A ray
+    // carrying a single color channel, after a dispersive surface, is bent by
+    // the index of that channel in every dispersive object it crosses.
+    pub fn prepare_computations_with(
+        &self,
+        ray: &Ray,
+        xs: &[Intersection],
+        group: &Group,
+        settings: &ComputationsSettings,
+    ) -> Computations {
+        let ComputationsSettings {
+            ambient_refractive_index,
+            epsilon,
+            channel,
+        } = *settings;
         let t = self.t;
         let object = self.object.clone();
 
         let point = ray.position(t);
         let eyev = -ray.get_direction();
 
-        let mut normalv = object.normal_at(&point, Some(group));
+        let mut normalv = object.normal_at_hit(&point, self, Some(group));
 
         let mut inside = false;
 
@@ -84,56 +197,79 @@ impl Intersection {
             normalv = -normalv
         }
 
-        let reflectv = ray.get_direction().reflect(&normalv);
-
-        let over_point = &point + &(&normalv * Computations::get_epsilon());
-        let under_point = &point - &(&normalv * Computations::get_epsilon());
-
-        let mut containers: Vec<Shape> = vec![];
-
-        let mut n1 = 1.0;
-        let mut n2 = 1.0;
-
-        for i in xs {
-            if self == i && !containers.is_empty() {
-                n1 = containers
-                    .last()
-                    .unwrap()
-                    .get_material()
-                    .get_refractive_index();
-            }
-
-            if containers.contains(&i.object) {
-                containers.retain(|element| &i.object != element);
-            } else {
-                containers.push(i.object.clone())
+        // Offset along the geometric normal, a normal map must not move the
+        // points off the surface. The rounding errors grow with the distance
+        // from the origin and so does the offset.
+        let distance = (point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt();
+        let offset = epsilon + distance * RELATIVE_EPSILON;
+        let over_point = &point + &(&normalv * offset);
+        let under_point = &point - &(&normalv * offset);
+
+        let (mut tangent, mut bitangent) = normalv.orthonormal_basis();
+        if let Some(normal_map) = object.get_material().get_normal_map_ref() {
+            // The tangent space of the map follows its uv directions, dP/du
+            // goes to world space and is made orthogonal to the normal
+            let object_point = object.world_to_object(&point, Some(group));
+            let object_tangent = normal_map.tangent_at(&object_point);
+            let world_tangent = object.vector_to_world(&object_tangent, Some(group));
+            if let Some(uv_tangent) = orthogonal_tangent(&world_tangent, &normalv) {
+                tangent = uv_tangent;
+                bitangent = tangent.cross(&normalv);
             }
 
-            if self == i {
-                if !containers.is_empty() {
-                    n2 = containers
-                        .last()
-                        .unwrap()
-                        .get_material()
-                        .get_refractive_index();
+            normalv = normal_map.perturb(&object_point, &normalv, &tangent, &bitangent);
+            (tangent, bitangent) = match orthogonal_tangent(&tangent, &normalv) {
+                Some(tangent) => {
+                    let bitangent = tangent.cross(&normalv);
+                    (tangent, bitangent)
                 }
-
-                break;
-            }
+                None => normalv.orthonormal_basis(),
+            };
         }
 
+        let reflectv = ray.get_direction().reflect(&normalv);
+
+        let boundary = |channel: Option<usize>| {
+            let mut n = (ambient_refractive_index, ambient_refractive_index);
+            walk_boundaries(xs, ambient_refractive_index, channel, |i, from, to| {
+                if self == i {
+                    n = (from, to);
+                    return false;
+                }
+                true
+            });
+            n
+        };
+
+        let (n1, n2) = boundary(channel);
+        let dispersive = object
+            .get_material()
+            .get_channel_refractive_index()
+            .is_some();
+        let channel_n = if dispersive && channel.is_none() {
+            Some([0, 1, 2].map(|channel| boundary(Some(channel))))
+        } else {
+            None
+        };
+
         Computations {
             _t: t,
             object,
             point,
             eyev,
             normalv,
+            tangent,
+            bitangent,
             reflectv,
             n1,
             n2,
-            _inside: inside,
+            channel_n,
+            inside,
             over_point,
             under_point,
+            time: ray.get_time(),
+            u: self.u,
+            v: self.v,
         }
     }
 }
@@ -155,8 +291,12 @@ impl Computations {
         &self.normalv
     }
 
-    fn get_epsilon() -> f64 {
-        0.000001
+    pub fn get_tangent_ref(&self) -> &Tuple {
+        &self.tangent
+    }
+
+    pub fn get_bitangent_ref(&self) -> &Tuple {
+        &self.bitangent
     }
 
     pub fn get_over_point_ref(&self) -> &Tuple {
@@ -171,6 +311,24 @@ impl Computations {
         &self.reflectv
     }
 
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    // Coordinates of the hit on the surface, to look up a texture
+    pub fn get_u(&self) -> f64 {
+        self.u
+    }
+
+    pub fn get_v(&self) -> f64 {
+        self.v
+    }
+
+    // True when the eye is inside of the object, the normal is then flipped
+    pub fn get_inside(&self) -> bool {
+        self.inside
+    }
+
     pub fn get_n1(&self) -> f64 {
         self.n1
     }
@@ -179,6 +337,11 @@ impl Computations {
         self.n2
     }
 
+    // n1 and n2 of each color channel, None when a single ray refracts them all
+    pub fn get_channel_n(&self) -> Option<[(f64, f64); 3]> {
+        self.channel_n
+    }
+
     pub fn schlick(&self) -> f64 {
         let mut cos = self.eyev.dot(&self.normalv);
 
@@ -199,14 +362,64 @@ impl Computations {
     }
 }
 
+// Visits the intersections in order with the refractive index of the medium
+// the ray leaves and of the one it enters, keeping track of the objects the
+// ray is inside of. The indices are those of the color channel, when one is
+// picked. The visit stops when `visit` returns false.
+pub(crate) fn walk_boundaries<F>(
+    xs: &[Intersection],
+    ambient_refractive_index: f64,
+    channel: Option<usize>,
+    mut visit: F,
+) where
+    F: FnMut(&Intersection, f64, f64) -> bool,
+{
+    let mut containers: Vec<Shape> = vec![];
+    let innermost = |containers: &Vec<Shape>| {
+        containers
+            .last()
+            .map_or(ambient_refractive_index, |object| {
+                object.get_material().get_refractive_index_for(channel)
+            })
+    };
+
+    for i in xs {
+        let n1 = innermost(&containers);
+
+        if containers.contains(&i.object) {
+            containers.retain(|element| &i.object != element);
+        } else {
+            containers.push(i.object.clone())
+        }
+
+        if !visit(i, n1, innermost(&containers)) {
+            break;
+        }
+    }
+}
+
+// Part of the vector orthogonal to the unit normal, normalized, None when the
+// vector is (almost) parallel to the normal
+fn orthogonal_tangent(vector: &Tuple, normal: &Tuple) -> Option<Tuple> {
+    let tangent = vector - &(normal * vector.dot(normal));
+    if tangent.magnitude() < 1e-9 * vector.magnitude() {
+        return None;
+    }
+
+    Some(tangent.normalize())
+}
+
 #[cfg(test)]
 mod tests {
 
     use std::sync::{Arc, Mutex};
 
+    use image::{Rgb, RgbImage};
+
     use crate::{
-        core::transformations::Transformation, core::tuples::Tuple, rays::Ray,
-        shapes::planes::Plane, shapes::spheres::Sphere, shapes::Shape,
+        core::transformations::Transformation, core::tuples::Tuple,
+        materials::normal_maps::NormalMap, materials::uv::UvMapping, materials::Material,
+        rays::Ray, shapes::planes::Plane, shapes::spheres::Sphere, shapes::Shape,
     };
 
     use super::*;
@@ -222,6 +435,21 @@ mod tests {
         assert!(intersection.get_t() == t);
     }
 
+    #[test]
+    fn the_world_point_of_an_intersection() {
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let s = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+
+        let xs = s.intersect(&r);
+        let hit = Intersection::hit(&xs).unwrap();
+
+        assert_eq!(hit.get_t(), 4.0);
+        assert_eq!(hit.world_point(&r), Tuple::new_point(0.0, 0.0, -1.0));
+    }
+
     #[test]
     fn aggregate_intersections() {
         let sphere = Sphere::new();
@@ -233,7 +461,7 @@ mod tests {
         let xs = Intersection::intersects(&[i1, i2]);
 
         assert!(xs.len() == 2);
-        assert!(xs.get(0).unwrap().t == 1.0);
+        assert!(xs.first().unwrap().t == 1.0);
         assert!(xs.get(1).unwrap().t == 2.0);
     }
 
@@ -273,7 +501,7 @@ mod tests {
 
         let xs = Intersection::intersects(&[i1, i2]);
 
-        assert!(Intersection::hit(&xs) == None);
+        assert!(Intersection::hit(&xs).is_none());
     }
 
     #[test]
@@ -311,6 +539,90 @@ mod tests {
         assert!(comps.normalv == Tuple::new_vector(0.0, 0.0, -1.0));
     }
 
+    fn normal_on_a_normal_mapped_plane(texel: [u8; 3]) -> Computations {
+        let mut plane = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        let mut material = Material::default();
+        material.set_normal_map(NormalMap::new(
+            RgbImage::from_pixel(2, 2, Rgb(texel)),
+            UvMapping::Planar,
+        ));
+        plane.set_material(material);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 1.0, 0.0),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+        let i = Intersection::new(1.0, plane);
+
+        i.prepare_computations(&r, &[], &Group::new())
+    }
+
+    #[test]
+    fn a_normal_map_bends_the_shading_normal_only() {
+        let flat = normal_on_a_normal_mapped_plane([128, 128, 255]);
+        assert_eq!(flat.normalv, Tuple::new_vector(0.0, 1.0, 0.0));
+
+        let tilted = normal_on_a_normal_mapped_plane([255, 128, 255]);
+        assert!(tilted.normalv.y < 0.8);
+        assert!((tilted.normalv.magnitude() - 1.0).abs() < 1e-12);
+        // The offset points still follow the geometry
+        assert_eq!(tilted.over_point.x, 0.0);
+        assert_eq!(tilted.over_point.z, 0.0);
+    }
+
+    #[test]
+    fn a_texel_tilted_along_u_bends_the_normal_along_u() {
+        let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        sphere.set_transformation(Transformation::scaling(2.0, 2.0, 2.0));
+        let mut material = Material::default();
+        material.set_normal_map(NormalMap::new(
+            RgbImage::from_pixel(2, 2, Rgb([255, 128, 255])),
+            UvMapping::Spherical,
+        ));
+        sphere.set_material(material);
+
+        // Around x, u grows towards +z on both sides of z = 0
+        for z in [-0.002, 0.002] {
+            let r = Ray::new(
+                Tuple::new_point(4.0, 0.0, z),
+                Tuple::new_vector(-1.0, 0.0, 0.0),
+            );
+            let i = Intersection::new(4.0 - (4.0 - z * z).sqrt(), sphere.clone());
+            let comps = i.prepare_computations(&r, &[], &Group::new());
+
+            let half = std::f64::consts::FRAC_1_SQRT_2;
+            assert!((comps.normalv.x - half).abs() < 1e-3);
+            assert!((comps.normalv.z - half).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn the_surface_frame_is_orthonormal() {
+        let sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        let directions = [
+            Tuple::new_vector(0.0, 0.0, 1.0),
+            Tuple::new_vector(0.0, 0.0, -1.0),
+            Tuple::new_vector(0.3, -0.8, 0.2).normalize(),
+            Tuple::new_vector(-1.0, 1.0, -0.001).normalize(),
+        ];
+
+        for direction in directions {
+            let r = Ray::new(Tuple::new_point(0.0, 0.0, 0.0), direction);
+            let i = Intersection::new(1.0, sphere.clone());
+            let comps = i.prepare_computations(&r, &[], &Group::new());
+
+            let normal = comps.get_normalv_ref();
+            let tangent = comps.get_tangent_ref();
+            let bitangent = comps.get_bitangent_ref();
+
+            assert!(tangent.dot(normal).abs() < 1e-12);
+            assert!(bitangent.dot(normal).abs() < 1e-12);
+            assert!(tangent.dot(bitangent).abs() < 1e-12);
+            assert!((tangent.magnitude() - 1.0).abs() < 1e-12);
+            assert!((bitangent.magnitude() - 1.0).abs() < 1e-12);
+        }
+    }
+
     #[test]
     fn the_hit_when_an_intersection_occours_on_the_outside() {
         let r = Ray::new(
@@ -323,7 +635,7 @@ mod tests {
         let i = Intersection::new(4.0, s);
 
         let comps = i.prepare_computations(&r, &[], &Group::new());
-        assert!(!comps._inside);
+        assert!(!comps.inside);
     }
 
     #[test]
@@ -341,7 +653,7 @@ mod tests {
 
         assert!(comps.point == Tuple::new_point(0.0, 0.0, 1.0));
         assert!(comps.eyev == Tuple::new_vector(0.0, 0.0, -1.0));
-        assert!(comps._inside);
+        assert!(comps.inside);
         assert!(comps.normalv == Tuple::new_vector(0.0, 0.0, -1.0));
     }
 
@@ -359,10 +671,28 @@ mod tests {
         let i = Intersection::new(5.0, s);
         let comps = i.prepare_computations(&r, &[], &Group::new());
 
-        assert!(comps.over_point.z < -Computations::get_epsilon() / 2.0);
+        assert!(comps.over_point.z < -DEFAULT_EPSILON / 2.0);
         assert!(comps.point.z > comps.over_point.z);
     }
 
+    #[test]
+    fn the_offset_grows_with_the_distance_from_the_origin() {
+        let r = Ray::new(
+            Tuple::new_point(1000.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let mut s = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        s.set_transformation(Transformation::translation(1000.0, 0.0, 0.0));
+
+        let i = Intersection::new(4.0, s);
+        let comps = i.prepare_computations(&r, &[], &Group::new());
+
+        let offset = comps.point.z - comps.over_point.z;
+        let expected = DEFAULT_EPSILON + 1000.0_f64.hypot(1.0) * RELATIVE_EPSILON;
+        assert!((offset - expected).abs() < 1e-12);
+        assert!((comps.under_point.z - comps.point.z - expected).abs() < 1e-12);
+    }
+
     #[test]
     fn precomputing_the_reflection_vector() {
         let plane = Plane::new();
@@ -394,7 +724,7 @@ mod tests {
         s.set_transformation(transform);
 
         let i = Intersection::new(5.0, s);
-        let xs = Intersection::intersects(&[i.clone()]);
+        let xs = Intersection::intersects(std::slice::from_ref(&i));
 
         let comps = i.prepare_computations(&r, &xs, &Group::new());
         assert!(comps.under_point.z > 0.0);
@@ -453,11 +783,36 @@ mod tests {
         let xs = Intersection::intersects(&[Intersection::new(1.8589, shape.clone())]);
 
         let comps: Computations = xs
-            .get(0)
+            .first()
             .unwrap()
             .prepare_computations(&r, &xs, &Group::new());
         let reflectance = comps.schlick();
 
         assert!(reflectance.approx_eq(0.48873081012212183, Margin::default_f64()));
     }
+
+    #[test]
+    fn the_ambient_medium_seeds_n1_and_n2() {
+        let shape = Shape::glass(Arc::new(Mutex::new(Sphere::new())));
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = Intersection::intersects(&[
+            Intersection::new(4.0, shape.clone()),
+            Intersection::new(6.0, shape),
+        ]);
+
+        let settings = ComputationsSettings {
+            ambient_refractive_index: 1.33,
+            ..ComputationsSettings::default()
+        };
+        let entering = xs[0].prepare_computations_with(&r, &xs, &Group::new(), &settings);
+        let leaving = xs[1].prepare_computations_with(&r, &xs, &Group::new(), &settings);
+
+        assert_eq!(entering.get_n1(), 1.33);
+        assert_eq!(entering.get_n2(), 1.5);
+        assert_eq!(leaving.get_n1(), 1.5);
+        assert_eq!(leaving.get_n2(), 1.33);
+    }
 }
@@ -0,0 +1,266 @@
+use crate::{
+    core::tuples::Tuple, rays::Ray, shapes::bounds::Bounds,
+    shapes::smooth_triangles::SmoothTriangle, shapes::triangles::Triangle, shapes::Polygon,
+};
+
+enum MeshTriangle {
+    Flat(Triangle),
+    Smooth(SmoothTriangle),
+}
+
+impl MeshTriangle {
+    fn get_triangle_ref(&self) -> &Triangle {
+        match self {
+            MeshTriangle::Flat(triangle) => triangle,
+            MeshTriangle::Smooth(triangle) => triangle.get_triangle_ref(),
+        }
+    }
+
+    fn normal_at(&self, point: &Tuple) -> Tuple {
+        match self {
+            MeshTriangle::Flat(triangle) => triangle.normal_at(point),
+            MeshTriangle::Smooth(triangle) => triangle.normal_at(point),
+        }
+    }
+
+    fn normal_at_face(&self, point: &Tuple, u: f64, v: f64) -> Tuple {
+        match self {
+            MeshTriangle::Flat(triangle) => triangle.normal_at(point),
+            MeshTriangle::Smooth(triangle) => triangle.normal_at_face(point, 0, u, v),
+        }
+    }
+}
+
+// Many triangles behind a single shape, so that a whole model shares one
+// transformation and one lock instead of paying for a shape per face.
+pub struct Mesh {
+    triangles: Vec<MeshTriangle>,
+    bounds: Bounds,
+}
+
+impl Mesh {
+    pub fn new() -> Mesh {
+        Mesh {
+            triangles: vec![],
+            bounds: Bounds::empty(),
+        }
+    }
+
+    pub fn add_triangle(&mut self, p1: Tuple, p2: Tuple, p3: Tuple) {
+        self.extend_bounds(&[&p1, &p2, &p3]);
+        self.triangles
+            .push(MeshTriangle::Flat(Triangle::new(p1, p2, p3)));
+    }
+
+    pub fn add_smooth_triangle(
+        &mut self,
+        p1: Tuple,
+        p2: Tuple,
+        p3: Tuple,
+        n1: Tuple,
+        n2: Tuple,
+        n3: Tuple,
+    ) {
+        self.extend_bounds(&[&p1, &p2, &p3]);
+        self.triangles
+            .push(MeshTriangle::Smooth(SmoothTriangle::new(
+                p1, p2, p3, n1, n2, n3,
+            )));
+    }
+
+    pub fn triangles_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    fn extend_bounds(&mut self, points: &[&Tuple]) {
+        for point in points {
+            self.bounds.extend(point);
+        }
+    }
+}
+
+impl Default for Mesh {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Polygon for Mesh {
+    fn intersect(&self, original_ray: &Ray) -> Vec<f64> {
+        self.intersect_faces(original_ray)
+            .into_iter()
+            .map(|(t, _, _, _)| t)
+            .collect()
+    }
+
+    fn intersect_uv(&self, original_ray: &Ray) -> Vec<(f64, f64, f64)> {
+        self.intersect_faces(original_ray)
+            .into_iter()
+            .map(|(t, u, v, _)| (t, u, v))
+            .collect()
+    }
+
+    // The (u, v) of a hit are the ones of the triangle it lies on, the face
+    // is the index of that triangle
+    fn intersect_faces(&self, original_ray: &Ray) -> Vec<(f64, f64, f64, usize)> {
+        if self.triangles.is_empty() || !self.bounds.hits(original_ray) {
+            return vec![];
+        }
+
+        let mut xs: Vec<(f64, f64, f64, usize)> = self
+            .triangles
+            .iter()
+            .enumerate()
+            .flat_map(|(face, triangle)| {
+                triangle
+                    .get_triangle_ref()
+                    .intersect_uv(original_ray)
+                    .into_iter()
+                    .map(move |(t, u, v)| (t, u, v, face))
+            })
+            .collect();
+
+        xs.sort_by(|a, b| a.0.total_cmp(&b.0));
+        xs
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        if self.triangles.is_empty() {
+            return None;
+        }
+
+        Some(self.bounds.clone())
+    }
+
+    fn normal_at_face(&self, point: &Tuple, face: usize, u: f64, v: f64) -> Tuple {
+        match self.triangles.get(face) {
+            Some(triangle) => triangle.normal_at_face(point, u, v),
+            None => self.normal_at(point),
+        }
+    }
+
+    // Without the hit, the normal comes from the triangle the point lies on
+    fn normal_at(&self, point: &Tuple) -> Tuple {
+        let nearest = self
+            .triangles
+            .iter()
+            .filter_map(|triangle| {
+                triangle
+                    .get_triangle_ref()
+                    .distance_to(point)
+                    .map(|distance| (distance, triangle))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        match nearest {
+            Some((_, triangle)) => triangle.normal_at(point),
+            None => self.triangles[0].normal_at(point),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::{Arc, Mutex};
+
+    use crate::shapes::Shape;
+
+    use super::*;
+
+    // Two parallel triangles, the second one two units behind the first
+    fn two_triangles() -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.add_triangle(
+            Tuple::new_point(0.0, 1.0, 2.0),
+            Tuple::new_point(-1.0, 0.0, 2.0),
+            Tuple::new_point(1.0, 0.0, 2.0),
+        );
+        mesh.add_triangle(
+            Tuple::new_point(0.0, 1.0, 0.0),
+            Tuple::new_point(1.0, 0.0, 0.0),
+            Tuple::new_point(-1.0, 0.0, 0.0),
+        );
+        mesh
+    }
+
+    #[test]
+    fn a_mesh_returns_the_hits_of_every_triangle_nearest_first() {
+        let mesh = two_triangles();
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.5, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        assert_eq!(mesh.triangles_count(), 2);
+        assert_eq!(mesh.intersect(&r), vec![5.0, 7.0]);
+    }
+
+    #[test]
+    fn a_ray_missing_the_bounding_box_misses_the_mesh() {
+        let mesh = two_triangles();
+        let r = Ray::new(
+            Tuple::new_point(0.0, 5.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        assert!(!mesh.bounds.hits(&r));
+        assert!(mesh.intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn the_normal_of_a_mesh_comes_from_the_triangle_that_was_hit() {
+        let mesh = two_triangles();
+
+        assert_eq!(
+            mesh.normal_at(&Tuple::new_point(0.0, 0.5, 0.0)),
+            Tuple::new_vector(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            mesh.normal_at(&Tuple::new_point(0.0, 0.5, 2.0)),
+            Tuple::new_vector(0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn a_hit_on_a_mesh_carries_the_index_of_its_triangle() {
+        let shape = Shape::default(Arc::new(Mutex::new(two_triangles())));
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.5, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        let xs = shape.intersect(&r);
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!((xs[0].get_t(), xs[0].get_face()), (5.0, 1));
+        assert_eq!((xs[1].get_t(), xs[1].get_face()), (7.0, 0));
+        assert_eq!(
+            shape.normal_at_hit(&r.position(5.0), &xs[0], None),
+            Tuple::new_vector(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            shape.normal_at_hit(&r.position(7.0), &xs[1], None),
+            Tuple::new_vector(0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn the_normal_of_a_smooth_face_is_interpolated_at_the_hit() {
+        let mut mesh = two_triangles();
+        mesh.add_smooth_triangle(
+            Tuple::new_point(0.0, 1.0, 4.0),
+            Tuple::new_point(-1.0, 0.0, 4.0),
+            Tuple::new_point(1.0, 0.0, 4.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+            Tuple::new_vector(-1.0, 0.0, 0.0),
+            Tuple::new_vector(1.0, 0.0, 0.0),
+        );
+
+        // The face and the (u, v) pick the normal, not the point
+        assert_eq!(
+            mesh.normal_at_face(&Tuple::new_point(0.0, 0.5, 0.0), 2, 0.45, 0.25),
+            Tuple::new_vector(-0.2, 0.3, 0.0)
+        );
+    }
+}
@@ -1,33 +1,129 @@
+pub mod bounds;
+pub mod cones;
 pub mod cubes;
 pub mod cylinders;
 pub mod groups;
 pub mod intersections;
+pub mod meshes;
+pub mod obj;
 pub mod objects;
 pub mod planes;
+pub mod smooth_triangles;
 pub mod spheres;
+pub mod tori;
 pub mod triangles;
 
 use std::{
-    fmt::Debug,
+    cell::Cell,
+    cmp::Ordering,
+    fmt::{self, Debug},
     sync::{Arc, Mutex},
 };
 
 use crate::{
     core::matrices::Matrix,
+    core::transformations::Animated,
     core::tuples::Tuple,
     materials::Material,
     rays::Ray,
+    shapes::bounds::Bounds,
     shapes::groups::{Group, NodeTypes},
     shapes::intersections::Intersection,
 };
 
+thread_local! {
+    // Shapes intersected with a ray on this thread, see intersection_tests
+    static INTERSECTION_TESTS: Cell<usize> = const { Cell::new(0) };
+}
+
+// Running count of the shapes intersected with a ray on the calling thread,
+// the difference between two calls is the cost of what ran in between
+pub fn intersection_tests() -> usize {
+    INTERSECTION_TESTS.with(|tests| tests.get())
+}
+
 #[cfg(test)]
 use mockall::{automock, predicate::*};
 
+// Returned when the y range of a cylinder or cone is empty, which would make
+// the shape invisible
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundsError {
+    minimum: f64,
+    maximum: f64,
+}
+
+impl fmt::Display for BoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "minimum {} must be lower than maximum {}",
+            self.minimum, self.maximum
+        )
+    }
+}
+
+impl std::error::Error for BoundsError {}
+
+// An infinite bound stands for an open end of the shape, the range still has to
+// be non empty. NaN bounds are never valid.
+pub(crate) fn check_bounds(minimum: f64, maximum: f64) -> Result<(), BoundsError> {
+    if minimum.partial_cmp(&maximum) != Some(Ordering::Less) {
+        return Err(BoundsError { minimum, maximum });
+    }
+
+    Ok(())
+}
+
+// Tolerance used by cylinders and cones to tell their caps from their side
+pub(crate) const CAP_EPSILON: f64 = 1e-9;
+
+// Whether a point lies on the cap at height `bound`, a disk of radius
+// `cap_radius`. The rim belongs to the cap, so that the normal does not
+// flicker between cap and side along the edge.
+pub(crate) fn on_cap(point: &Tuple, bound: f64, cap_radius: f64) -> bool {
+    (point.y - bound).abs() <= CAP_EPSILON && point.x.hypot(point.z) <= cap_radius + CAP_EPSILON
+}
+
 #[cfg_attr(test, automock)]
 pub trait Polygon {
     fn intersect(&self, original_ray: &Ray) -> Vec<f64>;
     fn normal_at(&self, point: &Tuple) -> Tuple;
+
+    // Same as intersect, with the (u, v) coordinates of every hit on the
+    // surface. Only triangles have them, the other shapes report (0, 0).
+    fn intersect_uv(&self, original_ray: &Ray) -> Vec<(f64, f64, f64)> {
+        self.intersect(original_ray)
+            .into_iter()
+            .map(|t| (t, 0.0, 0.0))
+            .collect()
+    }
+
+    // Same as intersect_uv, with the index of the face of every hit. Only
+    // meshes have several faces, the other shapes report face 0.
+    fn intersect_faces(&self, original_ray: &Ray) -> Vec<(f64, f64, f64, usize)> {
+        self.intersect_uv(original_ray)
+            .into_iter()
+            .map(|(t, u, v)| (t, u, v, 0))
+            .collect()
+    }
+
+    // Normal at a point hit on the given face at (u, v), so that shapes made
+    // of triangles do not have to search for the one the point lies on
+    fn normal_at_face(&self, point: &Tuple, _face: usize, _u: f64, _v: f64) -> Tuple {
+        self.normal_at(point)
+    }
+
+    // Box enclosing the shape in object space, None for unbounded shapes
+    fn bounds(&self) -> Option<Bounds> {
+        None
+    }
+
+    // Whether the point, in object space, lies inside the solid. Surfaces
+    // without an inside, like planes and triangles, contain nothing.
+    fn contains(&self, _point: &Tuple) -> bool {
+        false
+    }
 }
 
 impl Debug for dyn Polygon + Send + Sync {
@@ -40,14 +136,18 @@ impl Debug for dyn Polygon + Send + Sync {
 pub struct Shape {
     parent_id: Option<usize>,
     polygon: Arc<Mutex<dyn Polygon + Send + Sync>>,
-    pub material: Material,
+    material: Material,
     transformation: Matrix,
     inverse_transformation: Option<Matrix>,
+    motion: Option<Animated>,
+    // Shared by the clones of a shape, each instance gets its own so that
+    // instances of the same polygon are different shapes
+    instance: Arc<()>,
 }
 
 impl PartialEq for Shape {
     fn eq(&self, other: &Self) -> bool {
-        Arc::ptr_eq(&self.polygon, &other.polygon)
+        Arc::ptr_eq(&self.polygon, &other.polygon) && Arc::ptr_eq(&self.instance, &other.instance)
     }
 }
 
@@ -59,6 +159,8 @@ impl Shape {
             material: Material::default(),
             transformation: Matrix::identity(4),
             inverse_transformation: None,
+            motion: None,
+            instance: Arc::new(()),
         }
     }
 
@@ -74,6 +176,22 @@ impl Shape {
             material,
             transformation: Matrix::identity(4),
             inverse_transformation: None,
+            motion: None,
+            instance: Arc::new(()),
+        }
+    }
+
+    // New shape sharing the polygon of this one, e.g. to place the same mesh
+    // many times without copying its triangles
+    pub fn instance(&self, transformation: Matrix, material: Material) -> Shape {
+        Shape {
+            parent_id: None,
+            polygon: self.polygon.clone(),
+            material,
+            transformation,
+            inverse_transformation: None,
+            motion: None,
+            instance: Arc::new(()),
         }
     }
 
@@ -96,6 +214,52 @@ impl Shape {
         self.inverse_transformation = Some(self.transformation.invert());
     }
 
+    pub fn set_motion(&mut self, motion: Animated) {
+        self.motion = Some(motion);
+    }
+
+    // Transformation of the shape at the given time of the frame
+    pub fn transform_at(&self, time: f64) -> Matrix {
+        match &self.motion {
+            None => self.transformation.clone(),
+            Some(motion) => &motion.transform_at(time) * &self.transformation,
+        }
+    }
+
+    // Box enclosing the shape in the space of its parent, over the whole
+    // frame for a moving shape
+    pub fn bounds(&self) -> Option<Bounds> {
+        let bounds = self.polygon.lock().unwrap().bounds()?;
+
+        let start = bounds.transform(&self.transform_at(0.0));
+        Some(start.merge(&bounds.transform(&self.transform_at(1.0))))
+    }
+
+    // Copy of a moving shape standing still where it is at the given time, so
+    // that the intersections can be shaded without knowing the time
+    fn frozen_at(&self, time: f64) -> Shape {
+        let transformation = self.transform_at(time);
+
+        Shape {
+            inverse_transformation: Some(transformation.invert()),
+            transformation,
+            motion: None,
+            ..self.clone()
+        }
+    }
+
+    // Whether the point is inside the solid, at the given time of the frame
+    // for a moving shape. The point goes through the transformations of the
+    // groups above the shape, as for normal_at.
+    pub fn contains(&self, world_point: &Tuple, g: Option<&Group>, time: f64) -> bool {
+        if self.motion.is_some() {
+            return self.frozen_at(time).contains(world_point, g, time);
+        }
+
+        let object_point = self.world_to_object(world_point, g);
+        self.polygon.lock().unwrap().contains(&object_point)
+    }
+
     pub fn get_material(&self) -> &Material {
         &self.material
     }
@@ -104,18 +268,28 @@ impl Shape {
         self.material = material
     }
 
+    // Tweaks the material in place, without cloning it out and setting it back
+    pub fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
     pub fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
+        if self.motion.is_some() {
+            return self.frozen_at(ray.get_time()).intersect(ray);
+        }
+
         let inverse_transformation = match &self.inverse_transformation {
             Some(matrix) => matrix.clone(),
             None => self.transformation.invert(),
         };
         let local_ray = ray.transform(&inverse_transformation);
         let polygon = self.polygon.lock().unwrap();
-        let intersections_t = polygon.intersect(&local_ray);
+        let hits = polygon.intersect_faces(&local_ray);
+        INTERSECTION_TESTS.with(|tests| tests.set(tests.get() + 1));
 
         let mut intersections = vec![];
-        for t in intersections_t {
-            intersections.push(Intersection::new(t, self.clone()))
+        for (t, u, v, face) in hits {
+            intersections.push(Intersection::new_with_uv(t, self.clone(), u, v).with_face(face))
         }
 
         intersections
@@ -128,93 +302,84 @@ impl Shape {
         self.normal_to_world(&local_normal, g)
     }
 
-    fn world_to_object(&self, world_point: &Tuple, g: Option<&Group>) -> Tuple {
-        let inverse_transformation = match &self.inverse_transformation {
-            Some(matrix) => matrix.clone(),
-            None => self.transformation.invert(),
-        };
-
-        if g.is_none() {
-            return &inverse_transformation * world_point;
-        }
-
-        let mut object_point = world_point.clone();
-        let mut parent_id = self.parent_id;
-
-        let mut matrices_chain = vec![inverse_transformation];
+    // Same as normal_at for a point of the given hit on this shape
+    pub fn normal_at_hit(&self, point: &Tuple, hit: &Intersection, g: Option<&Group>) -> Tuple {
+        let local_point = self.world_to_object(point, g);
+        let polygon = self.polygon.lock().unwrap();
+        let local_normal =
+            polygon.normal_at_face(&local_point, hit.get_face(), hit.get_u(), hit.get_v());
+        self.normal_to_world(&local_normal, g)
+    }
 
-        while parent_id.is_some() {
-            let a = g.unwrap().arena.get_node_arc(parent_id.unwrap()).unwrap();
-            let b = &a.read().unwrap().payload;
+    pub(crate) fn world_to_object(&self, world_point: &Tuple, g: Option<&Group>) -> Tuple {
+        &self.world_inverse_transformation(g) * world_point
+    }
 
-            let parent_matrix = match b {
-                NodeTypes::Matrix((_, inverse)) => inverse.clone(),
-                NodeTypes::Shape(shape) => shape.get_inverse_transformation(),
-            };
-            matrices_chain.push(parent_matrix.clone());
+    // A direction along the surface, such as a tangent, goes through the
+    // transformation itself, unlike the normal
+    pub(crate) fn vector_to_world(&self, object_vector: &Tuple, g: Option<&Group>) -> Tuple {
+        let mut world_vector = &self.world_inverse_transformation(g).invert() * object_vector;
+        world_vector.w = 0.0;
 
-            parent_id = g.unwrap().arena.get_parent_of(parent_id.unwrap());
-        }
+        world_vector
+    }
 
-        for matrix in matrices_chain.iter().rev() {
-            object_point = matrix * &object_point;
-        }
+    // The normal goes through the transpose of the inverse of the whole chain
+    // of transformations at once, normalizing only at the end. Mirroring needs
+    // no correction: the inverse flips the normal along with the surface.
+    fn normal_to_world(&self, object_normal: &Tuple, g: Option<&Group>) -> Tuple {
+        let mut world_normal = &self.world_inverse_transformation(g).transpose() * object_normal;
+        world_normal.w = 0.0;
 
-        object_point
+        world_normal.normalize()
     }
 
-    fn normal_to_world(&self, object_normal: &Tuple, g: Option<&Group>) -> Tuple {
-        let inverse_transformation = match &self.inverse_transformation {
+    // Inverse of the transformation from object space to world space, composed
+    // from the shape inverse and the inverses of all its ancestors in the group.
+    fn world_inverse_transformation(&self, g: Option<&Group>) -> Matrix {
+        let mut inverse_transformation = match &self.inverse_transformation {
             Some(matrix) => matrix.clone(),
             None => self.transformation.invert(),
         };
 
-        if g.is_none() {
-            let mut world_normal = &inverse_transformation.transpose() * object_normal;
-            world_normal.w = 0.0;
-
-            return world_normal.normalize();
-        }
+        let g = match g {
+            None => return inverse_transformation,
+            Some(g) => g,
+        };
 
-        let mut world_normal = object_normal.clone();
         let mut parent_id = self.parent_id;
 
-        let mut matrices_chain = vec![inverse_transformation];
-
-        while parent_id.is_some() {
-            let a = g.unwrap().arena.get_node_arc(parent_id.unwrap()).unwrap();
+        while let Some(id) = parent_id {
+            let a = g.arena.get_node_arc(id).unwrap();
             let b = &a.read().unwrap().payload;
 
             let parent_matrix = match b {
                 NodeTypes::Matrix((_, inverse)) => inverse.clone(),
                 NodeTypes::Shape(shape) => shape.get_inverse_transformation(),
             };
-            matrices_chain.push(parent_matrix);
-
-            parent_id = g.unwrap().arena.get_parent_of(parent_id.unwrap());
-        }
+            inverse_transformation = &inverse_transformation * &parent_matrix;
 
-        for matrix in matrices_chain.iter() {
-            world_normal = &matrix.transpose() * &world_normal;
-            world_normal.w = 0.0;
-            world_normal = world_normal.normalize();
+            parent_id = g.arena.get_parent_of(id);
         }
 
-        world_normal
+        inverse_transformation
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use std::f64::consts::PI;
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
 
     use float_cmp::ApproxEq;
 
     use crate::{
         core::transformations::Transformation,
         margin::Margin,
+        shapes::cubes::Cube,
+        shapes::cylinders::Cylinder,
         shapes::groups::{Group, NodeTypes},
+        shapes::planes::Plane,
         shapes::spheres::Sphere,
     };
 
@@ -228,7 +393,7 @@ mod tests {
             Tuple::new_point(0.0, 0.0, -2.5),
             Tuple::new_vector(0.0, 0.0, 0.5),
         );
-        mock.expect_intersect()
+        mock.expect_intersect_faces()
             .with(mockall::predicate::eq(expected_local_ray))
             .once()
             .returning(|_| vec![]);
@@ -242,7 +407,7 @@ mod tests {
         shape.set_transformation(Transformation::scaling(2.0, 2.0, 2.0));
 
         let xs = shape.intersect(&r);
-        assert!(xs.len() == 0);
+        assert!(xs.is_empty());
     }
 
     #[test]
@@ -253,7 +418,7 @@ mod tests {
             Tuple::new_point(-5.0, 0.0, -5.0),
             Tuple::new_vector(0.0, 0.0, 1.0),
         );
-        mock.expect_intersect()
+        mock.expect_intersect_faces()
             .with(mockall::predicate::eq(expected_local_ray))
             .once()
             .returning(|_| vec![]);
@@ -267,7 +432,7 @@ mod tests {
         shape.set_transformation(Transformation::translation(5.0, 0.0, 0.0));
 
         let xs = shape.intersect(&r);
-        assert!(xs.len() == 0);
+        assert!(xs.is_empty());
     }
 
     #[test]
@@ -280,9 +445,12 @@ mod tests {
         let mut shape = Shape::default(Arc::new(Mutex::new(mock)));
         shape.set_transformation(Transformation::translation(0.0, 1.0, 0.0));
 
-        let n = shape.normal_at(&Tuple::new_point(0.0, 1.70711, -0.70711), None);
+        let n = shape.normal_at(
+            &Tuple::new_point(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+            None,
+        );
 
-        assert!(n == Tuple::new_vector(0.0, 0.7071067811865475, -0.7071067811865476));
+        assert!(n == Tuple::new_vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
     }
 
     #[test]
@@ -305,6 +473,45 @@ mod tests {
         assert!(n == Tuple::new_vector(0.0, 0.9701425001453319, -0.24253562503633294));
     }
 
+    #[test]
+    fn a_mirrored_sphere_keeps_its_normals_outwards() {
+        let mut shape = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        shape.set_transformation(Transformation::scaling(-2.0, 1.0, 1.0));
+
+        let normal = |x: f64, y: f64, z: f64| shape.normal_at(&Tuple::new_point(x, y, z), None);
+
+        assert_eq!(normal(2.0, 0.0, 0.0), Tuple::new_vector(1.0, 0.0, 0.0));
+        assert_eq!(normal(-2.0, 0.0, 0.0), Tuple::new_vector(-1.0, 0.0, 0.0));
+        assert_eq!(normal(0.0, 1.0, 0.0), Tuple::new_vector(0.0, 1.0, 0.0));
+
+        // Mirrored by the group instead, around a sphere moved to x = -2
+        let mut group = Group::new();
+        let mirror = group.add_matrix(Transformation::scaling(-1.0, 1.0, 1.0), Some(0));
+        let mut child = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        child.set_transformation(Transformation::translation(2.0, 0.0, 0.0));
+        let child_id = group.add_node(child, Some(mirror));
+        let child = match &group
+            .arena
+            .get_node_arc(child_id)
+            .unwrap()
+            .read()
+            .unwrap()
+            .payload
+        {
+            NodeTypes::Shape(shape) => shape.as_ref().clone(),
+            NodeTypes::Matrix(_) => unreachable!(),
+        };
+
+        assert_eq!(
+            child.normal_at(&Tuple::new_point(-3.0, 0.0, 0.0), Some(&group)),
+            Tuple::new_vector(-1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            child.normal_at(&Tuple::new_point(-1.0, 0.0, 0.0), Some(&group)),
+            Tuple::new_vector(1.0, 0.0, 0.0)
+        );
+    }
+
     #[test]
     fn a_helper_for_producing_a_shape_with_a_glassy_material() {
         let mock = MockPolygon::default();
@@ -370,7 +577,7 @@ mod tests {
     fn finding_n1_and_n2_at_various_intersections_0() {
         let (r, xs) = n1_n2_scenario();
         let comps = xs
-            .get(0)
+            .first()
             .unwrap()
             .prepare_computations(&r, &xs, &Group::new());
 
@@ -448,7 +655,6 @@ mod tests {
 
         let m1_id = g.add_matrix(m1, None);
         let m2_id = g.add_matrix(m2, Some(m1_id));
-        s.set_parent_id(m2_id);
         let s_id = g.add_node(s, Some(m2_id));
 
         let a = g.arena.get_node_arc(s_id).unwrap();
@@ -473,7 +679,6 @@ mod tests {
 
         let m1_id = g.add_matrix(m1, None);
         let m2_id = g.add_matrix(m2, Some(m1_id));
-        s.set_parent_id(m2_id);
         let s_id = g.add_node(s, Some(m2_id));
 
         let a = g.arena.get_node_arc(s_id).unwrap();
@@ -512,7 +717,6 @@ mod tests {
 
         let m1_id = g.add_matrix(m1, None);
         let m2_id = g.add_matrix(m2, Some(m1_id));
-        s.set_parent_id(m2_id);
         let s_id = g.add_node(s, Some(m2_id));
 
         let a = g.arena.get_node_arc(s_id).unwrap();
@@ -533,4 +737,133 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn the_normal_under_a_non_uniform_parent_scale_is_the_ellipsoid_normal() {
+        let mut g = Group::new();
+        let m_id = g.add_matrix(Transformation::scaling(2.0, 0.5, 1.0), Some(0));
+        let mut s = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        s.set_transformation(Transformation::rotation_y(PI / 3.0));
+        let s_id = g.add_node(s, Some(m_id));
+
+        let a = g.arena.get_node_arc(s_id).unwrap();
+        let b = &a.read().unwrap().payload;
+
+        let shape = match b {
+            NodeTypes::Shape(shape) => shape,
+            NodeTypes::Matrix(_) => panic!(),
+        };
+
+        // On the ellipsoid x^2 / 4 + y^2 / 0.25 + z^2 = 1 the normal is
+        // proportional to (x / 4, y / 0.25, z)
+        let k = 1.0 / 3.0_f64.sqrt();
+        let n = shape.normal_at(&Tuple::new_point(2.0 * k, 0.5 * k, k), Some(&g));
+        let expected = Tuple::new_vector(0.5, 2.0, 1.0).normalize();
+
+        assert!(n.x.approx_eq(expected.x, (1e-12, 4)));
+        assert!(n.y.approx_eq(expected.y, (1e-12, 4)));
+        assert!(n.z.approx_eq(expected.z, (1e-12, 4)));
+    }
+
+    #[test]
+    fn points_inside_a_translated_sphere() {
+        let mut shape = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        shape.set_transformation(Transformation::translation(5.0, 0.0, 0.0));
+
+        assert!(shape.contains(&Tuple::new_point(5.0, 0.0, 0.0), None, 0.0));
+        assert!(shape.contains(&Tuple::new_point(5.5, 0.5, 0.0), None, 0.0));
+        assert!(shape.contains(&Tuple::new_point(4.0, 0.0, 0.0), None, 0.0));
+        assert!(!shape.contains(&Tuple::new_point(0.0, 0.0, 0.0), None, 0.0));
+        assert!(!shape.contains(&Tuple::new_point(5.8, 0.8, 0.0), None, 0.0));
+    }
+
+    #[test]
+    fn points_inside_a_sphere_in_a_translated_group() {
+        let mut g = Group::new();
+        let m_id = g.add_matrix(Transformation::translation(5.0, 0.0, 0.0), None);
+        let s_id = g.add_node(
+            Shape::default(Arc::new(Mutex::new(Sphere::new()))),
+            Some(m_id),
+        );
+
+        let a = g.arena.get_node_arc(s_id).unwrap();
+        let b = &a.read().unwrap().payload;
+        let shape = match b {
+            NodeTypes::Shape(shape) => shape,
+            NodeTypes::Matrix(_) => panic!(),
+        };
+
+        assert!(shape.contains(&Tuple::new_point(5.5, 0.5, 0.0), Some(&g), 0.0));
+        assert!(!shape.contains(&Tuple::new_point(0.0, 0.0, 0.0), Some(&g), 0.0));
+    }
+
+    #[test]
+    fn points_inside_a_moving_sphere() {
+        let mut shape = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        shape.set_motion(Animated::translation(4.0, 0.0, 0.0));
+
+        assert!(shape.contains(&Tuple::new_point(0.0, 0.0, 0.0), None, 0.0));
+        assert!(!shape.contains(&Tuple::new_point(0.0, 0.0, 0.0), None, 1.0));
+        assert!(shape.contains(&Tuple::new_point(4.0, 0.0, 0.0), None, 1.0));
+    }
+
+    #[test]
+    fn points_inside_a_scaled_cube() {
+        let mut shape = Shape::default(Arc::new(Mutex::new(Cube::new())));
+        shape.set_transformation(Transformation::scaling(2.0, 1.0, 0.5));
+
+        assert!(shape.contains(&Tuple::new_point(1.9, 0.9, 0.4), None, 0.0));
+        assert!(shape.contains(&Tuple::new_point(-2.0, 0.0, 0.0), None, 0.0));
+        assert!(!shape.contains(&Tuple::new_point(0.0, 0.0, 0.6), None, 0.0));
+        assert!(!shape.contains(&Tuple::new_point(0.0, 1.1, 0.0), None, 0.0));
+    }
+
+    #[test]
+    fn points_inside_a_capped_cylinder() {
+        let mut cylinder = Cylinder::new();
+        cylinder.set_minimum(0.0);
+        cylinder.set_maximum(2.0);
+
+        assert!(cylinder.contains(&Tuple::new_point(0.5, 1.0, 0.5)));
+        assert!(!cylinder.contains(&Tuple::new_point(0.9, 1.0, 0.9)));
+        assert!(!cylinder.contains(&Tuple::new_point(0.0, 2.5, 0.0)));
+        assert!(!Plane::new().contains(&Tuple::new_point(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn instances_of_a_sphere_are_hit_where_they_are_placed() {
+        let sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        let left = sphere.instance(
+            Transformation::translation(-2.0, 0.0, 0.0),
+            Material::matte(Tuple::new_color(1.0, 0.0, 0.0)),
+        );
+        let right = sphere.instance(
+            Transformation::translation(2.0, 0.0, 0.0),
+            Material::matte(Tuple::new_color(0.0, 0.0, 1.0)),
+        );
+
+        assert!(Arc::ptr_eq(&left.polygon, &right.polygon));
+        assert!(left != right);
+
+        let mut group = Group::new();
+        group.add_node(left.clone(), Some(0));
+        group.add_node(right.clone(), Some(0));
+
+        let r = Ray::new(
+            Tuple::new_point(-10.0, 0.0, 0.0),
+            Tuple::new_vector(1.0, 0.0, 0.0),
+        );
+        let mut xs = group.intersect(&r, 0);
+        xs.sort_by(|a, b| a.get_t().total_cmp(&b.get_t()));
+
+        let hits: Vec<(f64, bool)> = xs
+            .iter()
+            .map(|i| (i.get_t(), *i.get_object_ref() == left))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![(7.0, true), (9.0, true), (11.0, false), (13.0, false)]
+        );
+        assert!(*xs[2].get_object_ref() == right);
+    }
 }
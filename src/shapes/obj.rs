@@ -0,0 +1,411 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    core::tuples::Tuple, shapes::groups::Group, shapes::meshes::Mesh,
+    shapes::smooth_triangles::SmoothTriangle, shapes::triangles::Triangle, shapes::Shape,
+};
+
+// A triangle of the model, each corner holds the index of its vertex and,
+// when known, the index of its normal. Indexes start from 0.
+type Face = [(usize, Option<usize>); 3];
+
+pub struct ObjParser {
+    ignored: usize,
+    vertices: Vec<Tuple>,
+    normals: Vec<Tuple>,
+    faces: Vec<Face>,
+}
+
+// Parses the vertices (v), normals (vn) and faces (f) of a Wavefront OBJ file,
+// polygons are split into triangles. Any other line is ignored, and so are the
+// faces using a vertex or a normal not defined above them.
+// With `smooth_normals`, a model without vn lines gets a normal per vertex
+// computed as the area weighted average of the normals of its faces.
+pub fn parse_obj(content: &str, smooth_normals: bool) -> ObjParser {
+    let mut parser = ObjParser {
+        ignored: 0,
+        vertices: vec![],
+        normals: vec![],
+        faces: vec![],
+    };
+
+    for line in content.lines() {
+        let mut tokens = line.split_whitespace();
+
+        let parsed = match tokens.next() {
+            Some("v") => parse_tuple(tokens, Tuple::new_point)
+                .map(|vertex| parser.vertices.push(vertex))
+                .is_some(),
+            Some("vn") => parse_tuple(tokens, Tuple::new_vector)
+                .map(|normal| parser.normals.push(normal))
+                .is_some(),
+            Some("f") => parse_face(tokens, parser.vertices.len(), parser.normals.len())
+                .map(|corners| parser.add_polygon(&corners))
+                .is_some(),
+            _ => false,
+        };
+
+        if !parsed {
+            parser.ignored += 1;
+        }
+    }
+
+    if smooth_normals && parser.normals.is_empty() {
+        parser.compute_vertex_normals();
+    }
+
+    parser
+}
+
+impl ObjParser {
+    pub fn get_ignored(&self) -> usize {
+        self.ignored
+    }
+
+    pub fn get_vertices_ref(&self) -> &Vec<Tuple> {
+        &self.vertices
+    }
+
+    pub fn get_normals_ref(&self) -> &Vec<Tuple> {
+        &self.normals
+    }
+
+    pub fn triangles_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    // Every face becomes a child of the root of the returned group. Faces with
+    // a normal on every corner are smooth, the others are flat.
+    pub fn to_group(&self) -> Group {
+        let mut group = Group::new();
+
+        for face in &self.faces {
+            let [(v1, n1), (v2, n2), (v3, n3)] = *face;
+            let p1 = self.vertices[v1].clone();
+            let p2 = self.vertices[v2].clone();
+            let p3 = self.vertices[v3].clone();
+
+            let shape = match (n1, n2, n3) {
+                (Some(n1), Some(n2), Some(n3)) => {
+                    Shape::default(Arc::new(Mutex::new(SmoothTriangle::new(
+                        p1,
+                        p2,
+                        p3,
+                        self.normals[n1].clone(),
+                        self.normals[n2].clone(),
+                        self.normals[n3].clone(),
+                    ))))
+                }
+                _ => Shape::default(Arc::new(Mutex::new(Triangle::new(p1, p2, p3)))),
+            };
+
+            group.add_node(shape, Some(0));
+        }
+
+        group
+    }
+
+    // Same faces as to_group, held by a single shape
+    pub fn to_mesh(&self) -> Mesh {
+        let mut mesh = Mesh::new();
+
+        for face in &self.faces {
+            let [(v1, n1), (v2, n2), (v3, n3)] = *face;
+            let p1 = self.vertices[v1].clone();
+            let p2 = self.vertices[v2].clone();
+            let p3 = self.vertices[v3].clone();
+
+            match (n1, n2, n3) {
+                (Some(n1), Some(n2), Some(n3)) => mesh.add_smooth_triangle(
+                    p1,
+                    p2,
+                    p3,
+                    self.normals[n1].clone(),
+                    self.normals[n2].clone(),
+                    self.normals[n3].clone(),
+                ),
+                _ => mesh.add_triangle(p1, p2, p3),
+            }
+        }
+
+        mesh
+    }
+
+    fn add_polygon(&mut self, corners: &[(usize, Option<usize>)]) {
+        // Fan triangulation, valid for convex polygons
+        for i in 1..corners.len() - 1 {
+            self.faces.push([corners[0], corners[i], corners[i + 1]]);
+        }
+    }
+
+    // Vertices in no face, or only in faces without an area, have no normal to
+    // average: they get a zero normal and their faces stay flat.
+    fn compute_vertex_normals(&mut self) {
+        let mut normals = vec![Tuple::new_vector(0.0, 0.0, 0.0); self.vertices.len()];
+
+        for face in &self.faces {
+            let p1 = &self.vertices[face[0].0];
+            let e1 = &self.vertices[face[1].0] - p1;
+            let e2 = &self.vertices[face[2].0] - p1;
+
+            // The length of the cross product is twice the area of the face,
+            // so summing it weights every face by its area.
+            let weighted_normal = e2.cross(&e1);
+
+            for corner in face {
+                normals[corner.0] = &normals[corner.0] + &weighted_normal;
+            }
+        }
+
+        let has_normal = |normal: &Tuple| normal.magnitude() > 0.0;
+        for face in self.faces.iter_mut() {
+            if face.iter().all(|corner| has_normal(&normals[corner.0])) {
+                for corner in face.iter_mut() {
+                    corner.1 = Some(corner.0);
+                }
+            }
+        }
+
+        self.normals = normals
+            .into_iter()
+            .map(|normal| {
+                if has_normal(&normal) {
+                    normal.normalize()
+                } else {
+                    normal
+                }
+            })
+            .collect();
+    }
+}
+
+fn parse_tuple<'a, I, F>(tokens: I, constructor: F) -> Option<Tuple>
+where
+    I: Iterator<Item = &'a str>,
+    F: Fn(f64, f64, f64) -> Tuple,
+{
+    let values: Vec<f64> = tokens
+        .take(3)
+        .map(|token| token.parse::<f64>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    if values.len() != 3 {
+        return None;
+    }
+
+    Some(constructor(values[0], values[1], values[2]))
+}
+
+// Corners are written as v, v/vt, v/vt/vn or v//vn with 1-based indexes, that
+// must be among the `vertices` and `normals` read so far
+fn parse_face<'a, I>(
+    tokens: I,
+    vertices: usize,
+    normals: usize,
+) -> Option<Vec<(usize, Option<usize>)>>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let index = |token: &str, count: usize| {
+        let index = token.parse::<usize>().ok()?.checked_sub(1)?;
+        (index < count).then_some(index)
+    };
+
+    let corners = tokens
+        .map(|token| {
+            let mut indexes = token.split('/');
+            let vertex = index(indexes.next()?, vertices)?;
+            let normal = match indexes.nth(1) {
+                Some(token) if !token.is_empty() => Some(index(token, normals)?),
+                _ => None,
+            };
+
+            Some((vertex, normal))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    if corners.len() < 3 {
+        return None;
+    }
+
+    Some(corners)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{rays::Ray, scenarios::lights::PointLight, scenarios::world::World};
+
+    use super::*;
+
+    #[test]
+    fn ignoring_unrecognized_lines() {
+        let gibberish = "There was a young lady named Bright\n\
+                         who traveled much faster than light.\n\
+                         She set out one day\n\
+                         in a relative way,\n\
+                         and came back the previous night.";
+
+        let parser = parse_obj(gibberish, false);
+
+        assert_eq!(parser.get_ignored(), 5);
+    }
+
+    #[test]
+    fn parsing_vertices_and_triangulating_polygons() {
+        let file = "v -1 1 0\n\
+                    v -1.0000 0.5000 0.0000\n\
+                    v 1 0 0\n\
+                    v 1 1 0\n\
+                    v 0 2 0\n\
+                    f 1 2 3\n\
+                    f 1 2 3 4 5";
+
+        let parser = parse_obj(file, false);
+
+        assert_eq!(parser.get_ignored(), 0);
+        assert_eq!(
+            parser.get_vertices_ref()[1],
+            Tuple::new_point(-1.0, 0.5, 0.0)
+        );
+        assert_eq!(parser.triangles_count(), 4);
+        assert_eq!(parser.faces[3], [(0, None), (3, None), (4, None)]);
+    }
+
+    #[test]
+    fn faces_with_normals() {
+        let file = "v 0 1 0\n\
+                    v -1 0 0\n\
+                    v 1 0 0\n\
+                    vn -1 0 0\n\
+                    vn 1 0 0\n\
+                    vn 0 1 0\n\
+                    f 1//3 2//1 3//2\n\
+                    f 1/0/3 2/102/1 3/14/2";
+
+        let parser = parse_obj(file, false);
+
+        assert_eq!(parser.get_normals_ref().len(), 3);
+        assert_eq!(parser.faces[0], [(0, Some(2)), (1, Some(0)), (2, Some(1))]);
+        assert_eq!(parser.faces[0], parser.faces[1]);
+    }
+
+    // Two triangles folded along the edge between (0, 1, 0) and (0, -1, 0)
+    const FOLDED_QUAD: &str = "v 0 1 0\n\
+                               v -1 0 1\n\
+                               v 0 -1 0\n\
+                               v 1 0 1\n\
+                               f 1 2 3\n\
+                               f 1 3 4";
+
+    #[test]
+    fn shared_vertices_receive_the_average_of_the_face_normals() {
+        let parser = parse_obj(FOLDED_QUAD, true);
+        let normals = parser.get_normals_ref();
+
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        assert_eq!(normals[0], Tuple::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(normals[2], Tuple::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(normals[1], Tuple::new_vector(-half, 0.0, -half));
+        assert_eq!(normals[3], Tuple::new_vector(half, 0.0, -half));
+    }
+
+    #[test]
+    fn faces_with_an_undefined_vertex_or_normal_are_ignored() {
+        let file = "v 0 1 0\n\
+                    v -1 0 0\n\
+                    v 1 0 0\n\
+                    vn 0 0 -1\n\
+                    f 1 2 4\n\
+                    f 1//1 2//2 3//1\n\
+                    f 1 2 3\n\
+                    v 0 0 1";
+
+        let parser = parse_obj(file, false);
+
+        assert_eq!(parser.get_ignored(), 2);
+        assert_eq!(parser.triangles_count(), 1);
+        parser.to_group();
+        parser.to_mesh();
+    }
+
+    #[test]
+    fn vertices_without_an_area_to_average_get_no_normal() {
+        // The fourth vertex is in no face, the second face has no area
+        let file = "v 0 1 0\n\
+                    v -1 0 0\n\
+                    v 1 0 0\n\
+                    v 5 5 5\n\
+                    v 2 0 0\n\
+                    v 3 0 0\n\
+                    f 1 2 3\n\
+                    f 3 5 6";
+
+        let parser = parse_obj(file, true);
+        let normals = parser.get_normals_ref();
+
+        assert!(normals
+            .iter()
+            .all(|n| !n.x.is_nan() && !n.y.is_nan() && !n.z.is_nan()));
+        assert_eq!(normals[0], Tuple::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(normals[3], Tuple::new_vector(0.0, 0.0, 0.0));
+        assert_eq!(parser.faces[0], [(0, Some(0)), (1, Some(1)), (2, Some(2))]);
+        assert_eq!(parser.faces[1], [(2, None), (4, None), (5, None)]);
+    }
+
+    #[test]
+    fn models_are_not_smoothed_unless_asked() {
+        let parser = parse_obj(FOLDED_QUAD, false);
+
+        assert!(parser.get_normals_ref().is_empty());
+        assert_eq!(parser.faces[0], [(0, None), (1, None), (2, None)]);
+    }
+
+    fn color_difference_across_the_fold(smooth_normals: bool) -> f64 {
+        let mut w = World::new();
+        w.set_light(PointLight::new(
+            Tuple::white(),
+            Tuple::new_point(-10.0, 10.0, -10.0),
+        ));
+        w.add_group(parse_obj(FOLDED_QUAD, smooth_normals).to_group());
+
+        let direction = Tuple::new_vector(0.0, 0.0, 1.0);
+        let left = w.color_at(
+            &Ray::new(Tuple::new_point(-0.01, 0.0, -5.0), direction.clone()),
+            5,
+        );
+        let right = w.color_at(&Ray::new(Tuple::new_point(0.01, 0.0, -5.0), direction), 5);
+
+        (left - right).magnitude()
+    }
+
+    #[test]
+    fn a_model_renders_the_same_as_a_mesh_or_as_a_group() {
+        let parser = parse_obj(FOLDED_QUAD, true);
+        let light = PointLight::new(Tuple::white(), Tuple::new_point(-10.0, 10.0, -10.0));
+
+        let mut grouped = World::new();
+        grouped.set_light(light.clone());
+        grouped.add_group(parser.to_group());
+
+        let mut meshed = World::new();
+        meshed.set_light(light);
+        meshed.add_shape(Shape::default(Arc::new(Mutex::new(parser.to_mesh()))));
+
+        let r = Ray::new(
+            Tuple::new_point(-0.3, 0.1, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(meshed.color_at(&r, 5), grouped.color_at(&r, 5));
+    }
+
+    #[test]
+    fn smoothed_models_render_without_a_crease() {
+        let flat = color_difference_across_the_fold(false);
+        let smooth = color_difference_across_the_fold(true);
+
+        assert!(flat > 0.1);
+        assert!(smooth < 0.05);
+    }
+}
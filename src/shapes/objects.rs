@@ -1,4 +1,7 @@
-use crate::{rays::Ray, shapes::groups::Group, shapes::intersections::Intersection, shapes::Shape};
+use crate::{
+    rays::Ray, shapes::bounds::Bounds, shapes::groups::Group, shapes::intersections::Intersection,
+    shapes::Shape,
+};
 
 #[derive(Debug)]
 pub enum Objects {
@@ -7,10 +10,25 @@ pub enum Objects {
 }
 
 impl Objects {
-    pub fn intersect(&mut self, ray: &Ray) -> Vec<Intersection> {
+    pub fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
         match self {
             Objects::Group(g) => g.intersect(ray, 0),
             Objects::Shape(s) => s.intersect(ray),
         }
     }
+
+    // Same as intersect without spreading large groups over several threads
+    pub fn intersect_serial(&self, ray: &Ray) -> Vec<Intersection> {
+        match self {
+            Objects::Group(g) => g.intersect_serial(ray, 0),
+            Objects::Shape(s) => s.intersect(ray),
+        }
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        match self {
+            Objects::Group(g) => g.bounds(),
+            Objects::Shape(s) => s.bounds(),
+        }
+    }
 }
@@ -1,10 +1,26 @@
 use crate::{core::tuples::Tuple, rays::Ray, shapes::Polygon};
 
-pub struct Plane {}
+pub struct Plane {
+    single_sided: bool,
+}
 
 impl Plane {
     pub fn new() -> Plane {
-        Plane {}
+        Plane {
+            single_sided: false,
+        }
+    }
+
+    // A single sided plane is only seen from above, rays coming from below
+    // pass through it, e.g. for floors of closed scenes
+    pub fn set_single_sided(&mut self, single_sided: bool) {
+        self.single_sided = single_sided
+    }
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -14,6 +30,11 @@ impl Polygon for Plane {
             return vec![];
         }
 
+        // The normal points towards +y, a ray going up reaches the back side
+        if self.single_sided && original_ray.get_direction().y > 0.0 {
+            return vec![];
+        }
+
         let t = -original_ray.get_origin().y / original_ray.get_direction().y;
         vec![t]
     }
@@ -80,7 +101,7 @@ mod tests {
         let xs = p.intersect(&r);
 
         assert!(xs.len() == 1);
-        assert!(xs.get(0).unwrap().approx_eq(1.0, Margin::default_f64()));
+        assert!(xs.first().unwrap().approx_eq(1.0, Margin::default_f64()));
     }
 
     #[test]
@@ -93,6 +114,24 @@ mod tests {
         let xs = p.intersect(&r);
 
         assert!(xs.len() == 1);
-        assert!(xs.get(0).unwrap().approx_eq(1.0, Margin::default_f64()));
+        assert!(xs.first().unwrap().approx_eq(1.0, Margin::default_f64()));
+    }
+
+    #[test]
+    fn a_single_sided_plane_is_only_seen_from_above() {
+        let mut p = Plane::new();
+        p.set_single_sided(true);
+
+        let from_above = Ray::new(
+            Tuple::new_point(0.0, 1.0, 0.0),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+        let from_below = Ray::new(
+            Tuple::new_point(0.0, -1.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+
+        assert_eq!(p.intersect(&from_above), vec![1.0]);
+        assert!(p.intersect(&from_below).is_empty());
     }
 }
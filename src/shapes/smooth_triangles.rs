@@ -0,0 +1,99 @@
+use crate::{
+    core::tuples::Tuple, rays::Ray, shapes::bounds::Bounds, shapes::triangles::Triangle,
+    shapes::Polygon,
+};
+
+// Triangle whose normal is interpolated between the normals of its vertices,
+// used to render meshes without visible facets.
+pub struct SmoothTriangle {
+    triangle: Triangle,
+    n1: Tuple,
+    n2: Tuple,
+    n3: Tuple,
+}
+
+impl SmoothTriangle {
+    pub fn new(p1: Tuple, p2: Tuple, p3: Tuple, n1: Tuple, n2: Tuple, n3: Tuple) -> SmoothTriangle {
+        SmoothTriangle {
+            triangle: Triangle::new(p1, p2, p3),
+            n1,
+            n2,
+            n3,
+        }
+    }
+
+    pub(crate) fn get_triangle_ref(&self) -> &Triangle {
+        &self.triangle
+    }
+
+    // Normal interpolated at the barycentric weights u and v of p2 and p3
+    fn interpolated_normal(&self, u: f64, v: f64) -> Tuple {
+        &self.n2 * u + &self.n3 * v + &self.n1 * (1.0 - u - v)
+    }
+}
+
+impl Polygon for SmoothTriangle {
+    fn bounds(&self) -> Option<Bounds> {
+        self.triangle.bounds()
+    }
+
+    fn intersect(&self, original_ray: &Ray) -> Vec<f64> {
+        self.triangle.intersect(original_ray)
+    }
+
+    fn intersect_uv(&self, original_ray: &Ray) -> Vec<(f64, f64, f64)> {
+        self.triangle.intersect_uv(original_ray)
+    }
+
+    fn normal_at(&self, point: &Tuple) -> Tuple {
+        let (u, v) = self.triangle.barycentric(point);
+
+        self.interpolated_normal(u, v)
+    }
+
+    fn normal_at_face(&self, _point: &Tuple, _face: usize, u: f64, v: f64) -> Tuple {
+        self.interpolated_normal(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn smooth_triangle() -> SmoothTriangle {
+        SmoothTriangle::new(
+            Tuple::new_point(0.0, 1.0, 0.0),
+            Tuple::new_point(-1.0, 0.0, 0.0),
+            Tuple::new_point(1.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+            Tuple::new_vector(-1.0, 0.0, 0.0),
+            Tuple::new_vector(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn a_smooth_triangle_is_intersected_like_a_triangle() {
+        let tri = smooth_triangle();
+        let r = Ray::new(
+            Tuple::new_point(-0.2, 0.3, -2.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        assert_eq!(tri.intersect(&r), vec![2.0]);
+    }
+
+    #[test]
+    fn a_smooth_triangle_interpolates_the_normal() {
+        let tri = smooth_triangle();
+
+        // u = 0.45 and v = 0.25 as in the intersection of the book example
+        let n = tri.normal_at(&Tuple::new_point(-0.2, 0.3, 0.0));
+
+        assert_eq!(n, Tuple::new_vector(-0.2, 0.3, 0.0));
+        assert_eq!(
+            tri.normal_at(&Tuple::new_point(0.0, 1.0, 0.0)),
+            Tuple::new_vector(0.0, 1.0, 0.0)
+        );
+    }
+}
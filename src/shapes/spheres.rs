@@ -1,6 +1,8 @@
 use float_cmp::ApproxEq;
 
-use crate::{core::tuples::Tuple, margin::Margin, rays::Ray, shapes::Polygon};
+use crate::{
+    core::tuples::Tuple, margin::Margin, rays::Ray, shapes::bounds::Bounds, shapes::Polygon,
+};
 
 #[derive(Clone, Debug)]
 pub struct Sphere {
@@ -15,15 +17,35 @@ impl Sphere {
             radius: 1.0,
         }
     }
+
+    pub fn with(center: Tuple, radius: f64) -> Sphere {
+        Sphere { center, radius }
+    }
+}
+
+impl Default for Sphere {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Polygon for Sphere {
+    fn bounds(&self) -> Option<Bounds> {
+        let extent = Tuple::new_vector(self.radius, self.radius, self.radius);
+
+        Some(Bounds::new(&self.center - &extent, &self.center + &extent))
+    }
+
+    fn contains(&self, point: &Tuple) -> bool {
+        (point - &self.center).magnitude() <= self.radius
+    }
+
     fn intersect(&self, ray: &Ray) -> Vec<f64> {
         let sphere_to_ray = &ray.get_origin() - &self.center;
 
         let a = ray.get_direction().dot(&ray.get_direction());
         let b = 2.0 * ray.get_direction().dot(&sphere_to_ray);
-        let c = sphere_to_ray.dot(&sphere_to_ray) - 1.0;
+        let c = sphere_to_ray.dot(&sphere_to_ray) - self.radius.powi(2);
 
         let discriminant = b.powi(2) - 4.0 * a * c;
 
@@ -52,7 +74,7 @@ impl PartialEq for Sphere {
 mod tests {
 
     use std::{
-        f64::consts::PI,
+        f64::consts::{FRAC_1_SQRT_2, PI},
         sync::{Arc, Mutex},
     };
 
@@ -62,6 +84,32 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn a_ray_intersects_a_sphere_with_a_custom_radius() {
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let sphere = Sphere::with(Tuple::new_point(0.0, 0.0, 0.0), 2.0);
+
+        assert_eq!(sphere.intersect(&r), vec![3.0, 7.0]);
+    }
+
+    #[test]
+    fn a_ray_intersects_a_sphere_with_a_custom_center() {
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let sphere = Sphere::with(Tuple::new_point(0.0, 0.0, 2.0), 0.5);
+
+        assert_eq!(sphere.intersect(&r), vec![6.5, 7.5]);
+        assert_eq!(
+            sphere.normal_at(&Tuple::new_point(0.0, 0.0, 1.5)),
+            Tuple::new_vector(0.0, 0.0, -0.5)
+        );
+    }
+
     #[test]
     fn ray_intersect_spheres_in_two_points() {
         let r = Ray::new(
@@ -74,7 +122,7 @@ mod tests {
         let xs = s.intersect(&r);
 
         assert!(xs.len() == 2);
-        assert!(xs.get(0).unwrap().get_t() == 4.0);
+        assert!(xs.first().unwrap().get_t() == 4.0);
         assert!(xs.get(1).unwrap().get_t() == 6.0);
     }
 
@@ -90,7 +138,7 @@ mod tests {
         let xs = s.intersect(&r);
 
         assert!(xs.len() == 2);
-        assert!(xs.get(0).unwrap().get_t() == 5.0);
+        assert!(xs.first().unwrap().get_t() == 5.0);
         assert!(xs.get(1).unwrap().get_t() == 5.0);
     }
 
@@ -105,7 +153,7 @@ mod tests {
 
         let xs = s.intersect(&r);
 
-        assert!(xs.len() == 0);
+        assert!(xs.is_empty());
     }
 
     #[test]
@@ -120,7 +168,7 @@ mod tests {
         let xs = s.intersect(&r);
 
         assert!(xs.len() == 2);
-        assert!(xs.get(0).unwrap().get_t() == -1.0);
+        assert!(xs.first().unwrap().get_t() == -1.0);
         assert!(xs.get(1).unwrap().get_t() == 1.0);
     }
 
@@ -136,7 +184,7 @@ mod tests {
         let xs = s.intersect(&r);
 
         assert!(xs.len() == 2);
-        assert!(xs.get(0).unwrap().get_t() == -6.0);
+        assert!(xs.first().unwrap().get_t() == -6.0);
         assert!(xs.get(1).unwrap().get_t() == -4.0);
     }
 
@@ -155,7 +203,7 @@ mod tests {
         let xs = s.intersect(&r);
 
         assert!(xs.len() == 2);
-        assert!(xs.get(0).unwrap().get_t() == 3.0);
+        assert!(xs.first().unwrap().get_t() == 3.0);
         assert!(xs.get(1).unwrap().get_t() == 7.0);
     }
 
@@ -173,7 +221,7 @@ mod tests {
 
         let xs = s.intersect(&r);
 
-        assert!(xs.len() == 0);
+        assert!(xs.is_empty());
     }
 
     #[test]
@@ -211,9 +259,12 @@ mod tests {
         let mut s = Shape::default(Arc::new(Mutex::new(sphere)));
 
         s.set_transformation(Transformation::translation(0.0, 1.0, 0.0));
-        let n = s.normal_at(&Tuple::new_point(0.0, 1.70711, -0.70711), None);
+        let n = s.normal_at(
+            &Tuple::new_point(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+            None,
+        );
 
-        assert!(n == Tuple::new_vector(0.0, 0.7071067811865475, -0.7071067811865476))
+        assert!(n == Tuple::new_vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2))
     }
 
     #[test]
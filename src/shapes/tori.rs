@@ -0,0 +1,265 @@
+use float_cmp::ApproxEq;
+
+use crate::{
+    core::tuples::Tuple, margin::Margin, rays::Ray, shapes::bounds::Bounds, shapes::Polygon,
+};
+
+// Torus lying on the xz plane and centered at the origin, `major_radius` is the
+// distance from the center to the middle of the tube and `minor_radius` is the
+// radius of the tube.
+pub struct Torus {
+    major_radius: f64,
+    minor_radius: f64,
+}
+
+impl Torus {
+    pub fn new(major_radius: f64, minor_radius: f64) -> Torus {
+        Torus {
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl Default for Torus {
+    fn default() -> Self {
+        Self::new(1.0, 0.25)
+    }
+}
+
+impl Polygon for Torus {
+    fn bounds(&self) -> Option<Bounds> {
+        let outer = self.major_radius + self.minor_radius;
+
+        Some(Bounds::new(
+            Tuple::new_point(-outer, -self.minor_radius, -outer),
+            Tuple::new_point(outer, self.minor_radius, outer),
+        ))
+    }
+
+    fn contains(&self, point: &Tuple) -> bool {
+        let from_axis = (point.x.powi(2) + point.z.powi(2)).sqrt();
+
+        (from_axis - self.major_radius).powi(2) + point.y.powi(2) <= self.minor_radius.powi(2)
+    }
+
+    fn intersect(&self, original_ray: &Ray) -> Vec<f64> {
+        let origin = original_ray.get_origin();
+        let direction = original_ray.get_direction();
+
+        let r2 = self.major_radius.powi(2);
+
+        // Substituting the ray in (x² + y² + z² + R² - r²)² - 4R²(x² + z²) = 0
+        let a = direction.dot(&direction);
+        let f = origin.x * direction.x + origin.y * direction.y + origin.z * direction.z;
+        let k =
+            origin.x.powi(2) + origin.y.powi(2) + origin.z.powi(2) + r2 - self.minor_radius.powi(2);
+
+        let c4 = a.powi(2);
+        let c3 = 4.0 * a * f;
+        let c2 =
+            4.0 * f.powi(2) + 2.0 * a * k - 4.0 * r2 * (direction.x.powi(2) + direction.z.powi(2));
+        let c1 = 4.0 * f * k - 8.0 * r2 * (origin.x * direction.x + origin.z * direction.z);
+        let c0 = k.powi(2) - 4.0 * r2 * (origin.x.powi(2) + origin.z.powi(2));
+
+        real_roots(&[c0, c1, c2, c3, c4])
+    }
+
+    fn normal_at(&self, point: &Tuple) -> Tuple {
+        let s = point.x.powi(2) + point.y.powi(2) + point.z.powi(2) + self.major_radius.powi(2)
+            - self.minor_radius.powi(2);
+        let tube = s - 2.0 * self.major_radius.powi(2);
+
+        Tuple::new_vector(point.x * tube, point.y * s, point.z * tube).normalize()
+    }
+}
+
+// Coefficients go from the constant term up to the highest power
+fn evaluate(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().rev().fold(0.0, |acc, c| acc * x + c)
+}
+
+// Real roots in ascending order. The roots of the derivative split the real
+// line in intervals where the polynomial is monotonic, each interval holds at
+// most one root which is then found by bisection.
+fn real_roots(coefficients: &[f64]) -> Vec<f64> {
+    let mut coefficients = coefficients.to_vec();
+    while coefficients
+        .last()
+        .is_some_and(|c| c.approx_eq(0.0, Margin::default_f64()))
+    {
+        coefficients.pop();
+    }
+
+    match coefficients.len() {
+        0 | 1 => return vec![],
+        2 => return vec![-coefficients[0] / coefficients[1]],
+        3 => return quadratic_roots(coefficients[2], coefficients[1], coefficients[0]),
+        _ => {}
+    }
+
+    let leading = coefficients[coefficients.len() - 1];
+    let bound = 1.0
+        + coefficients[..coefficients.len() - 1]
+            .iter()
+            .map(|c| (c / leading).abs())
+            .fold(0.0, f64::max);
+
+    let derivative: Vec<f64> = coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(power, c)| power as f64 * c)
+        .collect();
+
+    let mut limits = vec![-bound];
+    limits.extend(real_roots(&derivative));
+    limits.push(bound);
+
+    let mut roots: Vec<f64> = vec![];
+    for window in limits.windows(2) {
+        let root = match bisect(&coefficients, window[0], window[1]) {
+            Some(root) => root,
+            None => continue,
+        };
+
+        // A root on a critical point is found by both intervals around it
+        if roots
+            .last()
+            .is_some_and(|last| (root - last).abs() < 1e-9 * bound)
+        {
+            continue;
+        }
+        roots.push(root);
+    }
+
+    roots
+}
+
+fn quadratic_roots(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let discriminant = b.powi(2) - 4.0 * a * c;
+
+    if discriminant < 0.0 {
+        return vec![];
+    }
+
+    let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
+    let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+
+    if t1 < t2 {
+        vec![t1, t2]
+    } else {
+        vec![t2, t1]
+    }
+}
+
+fn bisect(coefficients: &[f64], mut low: f64, mut high: f64) -> Option<f64> {
+    let mut low_value = evaluate(coefficients, low);
+    let high_value = evaluate(coefficients, high);
+
+    if low_value == 0.0 {
+        return Some(low);
+    }
+    if high_value == 0.0 {
+        return Some(high);
+    }
+    if low_value.signum() == high_value.signum() {
+        return None;
+    }
+
+    for _ in 0..200 {
+        let middle = (low + high) / 2.0;
+        if middle <= low || middle >= high {
+            break;
+        }
+
+        let middle_value = evaluate(coefficients, middle);
+        if middle_value == 0.0 {
+            return Some(middle);
+        }
+
+        if middle_value.signum() == low_value.signum() {
+            low = middle;
+            low_value = middle_value;
+        } else {
+            high = middle;
+        }
+    }
+
+    Some((low + high) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn assert_roots(actual: Vec<f64>, expected: Vec<f64>) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn finding_the_real_roots_of_a_quartic() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            real_roots(&[24.0, -50.0, 35.0, -10.0, 1.0]),
+            vec![1.0, 2.0, 3.0, 4.0],
+        );
+        // x⁴ + 1 has no real roots
+        assert_roots(real_roots(&[1.0, 0.0, 0.0, 0.0, 1.0]), vec![]);
+    }
+
+    #[test]
+    fn a_ray_through_the_hole_misses_the_torus() {
+        let torus = Torus::new(1.0, 0.25);
+        let r = Ray::new(
+            Tuple::new_point(0.0, 5.0, 0.0),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+
+        assert!(torus.intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn a_ray_crossing_both_sides_of_the_tube_hits_four_times() {
+        let torus = Torus::new(1.0, 0.25);
+        let r = Ray::new(
+            Tuple::new_point(-5.0, 0.0, 0.0),
+            Tuple::new_vector(1.0, 0.0, 0.0),
+        );
+
+        assert_roots(torus.intersect(&r), vec![3.75, 4.25, 5.75, 6.25]);
+    }
+
+    #[test]
+    fn a_ray_crossing_one_side_of_the_tube_hits_twice() {
+        let torus = Torus::new(1.0, 0.25);
+        let r = Ray::new(
+            Tuple::new_point(1.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        assert_roots(torus.intersect(&r), vec![4.25, 5.75]);
+    }
+
+    #[test]
+    fn the_normal_on_the_outer_equator_of_a_torus() {
+        let torus = Torus::new(1.0, 0.25);
+
+        assert_eq!(
+            torus.normal_at(&Tuple::new_point(1.25, 0.0, 0.0)),
+            Tuple::new_vector(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            torus.normal_at(&Tuple::new_point(0.0, 0.0, -1.25)),
+            Tuple::new_vector(0.0, 0.0, -1.0)
+        );
+        assert_eq!(
+            torus.normal_at(&Tuple::new_point(1.0, 0.25, 0.0)),
+            Tuple::new_vector(0.0, 1.0, 0.0)
+        );
+    }
+}
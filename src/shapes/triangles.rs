@@ -1,6 +1,8 @@
 use float_cmp::ApproxEq;
 
-use crate::{core::tuples::Tuple, margin::Margin, rays::Ray, shapes::Polygon};
+use crate::{
+    core::tuples::Tuple, margin::Margin, rays::Ray, shapes::bounds::Bounds, shapes::Polygon,
+};
 
 pub struct Triangle {
     p1: Tuple,
@@ -26,15 +28,46 @@ impl Triangle {
             normal,
         }
     }
-}
 
-impl Polygon for Triangle {
-    fn intersect(&self, original_ray: &Ray) -> Vec<f64> {
+    // Barycentric weights of p2 and p3 for the projection of the point on the
+    // plane of the triangle, the same as the (u, v) of a hit
+    pub(crate) fn barycentric(&self, point: &Tuple) -> (f64, f64) {
+        let p1_to_point = point - &self.p1;
+
+        let d00 = self.e1.dot(&self.e1);
+        let d01 = self.e1.dot(&self.e2);
+        let d11 = self.e2.dot(&self.e2);
+        let d20 = p1_to_point.dot(&self.e1);
+        let d21 = p1_to_point.dot(&self.e2);
+        let denom = d00 * d11 - d01 * d01;
+
+        let u = (d11 * d20 - d01 * d21) / denom;
+        let v = (d00 * d21 - d01 * d20) / denom;
+
+        (u, v)
+    }
+
+    // Distance of the point from the plane of the triangle, None when the point
+    // does not project inside the triangle
+    pub(crate) fn distance_to(&self, point: &Tuple) -> Option<f64> {
+        let (u, v) = self.barycentric(point);
+
+        let tolerance = 1e-9;
+        if u < -tolerance || v < -tolerance || u + v > 1.0 + tolerance {
+            return None;
+        }
+
+        Some((point - &self.p1).dot(&self.normal).abs())
+    }
+
+    // Möller–Trumbore, the hit as (t, u, v) where u and v are the barycentric
+    // weights of p2 and p3
+    fn solve(&self, original_ray: &Ray) -> Option<(f64, f64, f64)> {
         let dir_cross_e2 = original_ray.get_direction().cross(&self.e2);
         let det = self.e1.dot(&dir_cross_e2);
 
         if det.abs().approx_eq(0.0, Margin::default_f64()) {
-            return vec![];
+            return None;
         };
 
         let f = 1.0 / det;
@@ -42,17 +75,39 @@ impl Polygon for Triangle {
         let u = f * p1_to_origin.dot(&dir_cross_e2);
 
         if !(0.0..1.0).contains(&u) {
-            return vec![];
+            return None;
         }
 
         let origin_cross_e1 = p1_to_origin.cross(&self.e1);
         let v = f * original_ray.get_direction().dot(&origin_cross_e1);
 
         if v < 0.0 || (u + v) > 1.0 {
-            return vec![];
+            return None;
         }
 
-        vec![f * self.e2.dot(&origin_cross_e1)]
+        Some((f * self.e2.dot(&origin_cross_e1), u, v))
+    }
+}
+
+impl Polygon for Triangle {
+    fn bounds(&self) -> Option<Bounds> {
+        let mut bounds = Bounds::empty();
+        for point in [&self.p1, &self.p2, &self.p3] {
+            bounds.extend(point);
+        }
+
+        Some(bounds)
+    }
+
+    fn intersect(&self, original_ray: &Ray) -> Vec<f64> {
+        self.solve(original_ray)
+            .map(|(t, _, _)| t)
+            .into_iter()
+            .collect()
+    }
+
+    fn intersect_uv(&self, original_ray: &Ray) -> Vec<(f64, f64, f64)> {
+        self.solve(original_ray).into_iter().collect()
     }
 
     fn normal_at(&self, _point: &Tuple) -> Tuple {
@@ -62,6 +117,10 @@ impl Polygon for Triangle {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::shapes::{groups::Group, spheres::Sphere, Shape};
+
     use super::*;
 
     #[test]
@@ -176,4 +235,42 @@ mod tests {
         assert_eq!(xs.len(), 1);
         assert_eq!(xs[0], 2.0);
     }
+
+    #[test]
+    fn a_hit_on_a_shape_carries_the_barycentric_coordinates() {
+        let triangle = Triangle::new(
+            Tuple::new_point(0.0, 1.0, 0.0),
+            Tuple::new_point(-1.0, 0.0, 0.0),
+            Tuple::new_point(1.0, 0.0, 0.0),
+        );
+        let shape = Shape::default(Arc::new(Mutex::new(triangle)));
+        let r = Ray::new(
+            Tuple::new_point(-0.2, 0.3, -2.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        let xs = shape.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].get_u().approx_eq(0.45, Margin::default_f64()));
+        assert!(xs[0].get_v().approx_eq(0.25, Margin::default_f64()));
+
+        let comps = xs[0].prepare_computations(&r, &xs, &Group::new());
+        assert_eq!(
+            (comps.get_u(), comps.get_v()),
+            (xs[0].get_u(), xs[0].get_v())
+        );
+    }
+
+    #[test]
+    fn hits_on_other_shapes_have_zero_uv() {
+        let shape = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        for i in shape.intersect(&r) {
+            assert_eq!((i.get_u(), i.get_v()), (0.0, 0.0));
+        }
+    }
 }
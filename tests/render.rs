@@ -0,0 +1,141 @@
+use std::{
+    f64::consts::PI,
+    sync::{Arc, Mutex},
+};
+
+use ray_tracer_rs::{
+    camera::{Camera, RenderSettings},
+    canvas::Canvas,
+    core::{transformations::Transformation, tuples::Tuple},
+    materials::Material,
+    scenarios::{
+        lights::{Light, PointLight},
+        world::World,
+    },
+    shapes::{groups::Group, planes::Plane, spheres::Sphere, Shape},
+};
+
+#[test]
+fn rendering_a_world_through_the_public_api() {
+    let mut w = World::new();
+    w.set_light(PointLight::new(
+        Tuple::white(),
+        Tuple::new_point(-10.0, 10.0, -10.0),
+    ));
+
+    let mut outer = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+    let mut m = Material::default();
+    m.set_color(Tuple::new_color(0.8, 1.0, 0.6));
+    m.set_diffuse(0.7);
+    m.set_specular(0.2);
+    outer.set_material(m);
+
+    let mut inner = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+    inner.set_transformation(Transformation::scaling(0.5, 0.5, 0.5));
+
+    w.add_shapes(&[outer, inner]);
+
+    let mut c = Camera::new(11, 11, PI / 2.0);
+    c.set_transform(Transformation::view_transform(
+        Tuple::new_point(0.0, 0.0, -5.0),
+        Tuple::new_point(0.0, 0.0, 0.0),
+        Tuple::new_vector(0.0, 1.0, 0.0),
+    ));
+    let image: Canvas = c.render(&mut w, &RenderSettings::default());
+
+    assert_eq!(
+        image.pixel_at(5, 5),
+        Tuple::new_color(
+            0.38066119308103435,
+            0.47582649135129296,
+            0.28549589481077575
+        )
+    );
+}
+
+// Light infinitely far away, every point is lit from the same direction
+#[derive(Debug)]
+struct Sun {
+    direction: Tuple,
+}
+
+impl Light for Sun {
+    fn get_intensity(&self) -> Tuple {
+        Tuple::white()
+    }
+
+    fn intensity_at(&self, _point: &Tuple) -> Tuple {
+        Tuple::white()
+    }
+
+    fn direction_from(&self, _point: &Tuple) -> Tuple {
+        self.direction.clone()
+    }
+}
+
+#[test]
+fn rendering_a_world_lit_by_a_custom_light() {
+    let mut w = World::default();
+    w.set_light(Sun {
+        direction: Tuple::new_vector(0.0, 0.0, -1.0),
+    });
+
+    let mut c = Camera::new(1, 1, PI / 2.0);
+    c.set_transform(Transformation::view_transform(
+        Tuple::new_point(0.0, 0.0, -5.0),
+        Tuple::new_point(0.0, 0.0, 0.0),
+        Tuple::new_vector(0.0, 1.0, 0.0),
+    ));
+    let image = c.render(&mut w, &RenderSettings::default());
+
+    // Ambient and diffuse at full strength plus the whole specular highlight
+    assert_eq!(image.pixel_at(0, 0), Tuple::new_color(0.84, 1.0, 0.68));
+}
+
+fn sphere_at(x: f64, y: f64, z: f64) -> Shape {
+    let mut sphere = Shape::default(Arc::new(Mutex::new(Sphere::new())));
+    sphere.set_transformation(Transformation::translation(x, y, z));
+    sphere
+}
+
+#[test]
+fn a_world_from_the_builder_renders_like_one_assembled_by_hand() {
+    let light = PointLight::new(Tuple::white(), Tuple::new_point(-10.0, 10.0, -10.0));
+    let floor = || {
+        let mut floor = Shape::default(Arc::new(Mutex::new(Plane::new())));
+        floor.set_transformation(Transformation::translation(0.0, -1.0, 0.0));
+        floor
+    };
+    let group = || {
+        let mut group = Group::new();
+        group.add_node(sphere_at(1.5, 0.0, 1.0), Some(0));
+        group
+    };
+    let (top, bottom) = (Tuple::new_color(0.2, 0.4, 0.9), Tuple::white());
+
+    let mut by_hand = World::new();
+    by_hand.set_light(light.clone());
+    by_hand.add_shapes(&[floor(), sphere_at(-1.0, 0.0, 0.0)]);
+    by_hand.add_group(group());
+    by_hand.set_sky_gradient(top.clone(), bottom.clone());
+
+    let mut built = World::builder()
+        .light(light)
+        .shape(floor())
+        .shape(sphere_at(-1.0, 0.0, 0.0))
+        .group(group())
+        .background(top, bottom)
+        .build();
+
+    let mut c = Camera::new(16, 8, PI / 2.0);
+    c.set_transform(Transformation::view_transform(
+        Tuple::new_point(0.0, 1.5, -5.0),
+        Tuple::new_point(0.0, 0.0, 0.0),
+        Tuple::new_vector(0.0, 1.0, 0.0),
+    ));
+
+    assert_eq!(
+        c.render(&mut built, &RenderSettings::default()).to_ppm(),
+        c.render(&mut by_hand, &RenderSettings::default()).to_ppm()
+    );
+}
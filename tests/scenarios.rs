@@ -0,0 +1,101 @@
+use std::f64::consts::PI;
+
+use ray_tracer_rs::{
+    camera::{Camera, RenderSettings},
+    canvas::Canvas,
+    core::tuples::Tuple,
+    scenarios::{lights::PointLight, Scenario},
+};
+
+// Colors that shading changes are allowed to move without failing the test
+const TOLERANCE: f64 = 1e-4;
+
+// Pixel coordinates and the expected color
+type ReferencePixel = (usize, usize, [f64; 3]);
+
+// A few pixels of every scenario rendered by `render_scenario`, regenerate them
+// when a change to the shading is intended.
+const REFERENCES: &[(&str, &[ReferencePixel])] = &[
+    (
+        "Cone Lamp",
+        &[
+            (16, 8, [0.572336, 0.486486, 0.228934]),
+            (8, 6, [0.039314, 0.039314, 0.045866]),
+            (16, 13, [0.234251, 0.228251, 0.242627]),
+            (4, 14, [0.210780, 0.210780, 0.245910]),
+        ],
+    ),
+    (
+        "Hexagon",
+        &[
+            (16, 8, [0.098840, 0.098840, 0.123550]),
+            (8, 6, [0.025458, 0.025458, 0.031822]),
+            (16, 13, [0.165313, 0.137313, 0.169642]),
+            (4, 14, [0.141589, 0.141589, 0.176986]),
+        ],
+    ),
+    (
+        "Three Spheres",
+        &[
+            (16, 8, [0.010000, 0.100000, 0.050000]),
+            (8, 6, [0.0, 0.0, 0.0]),
+            (16, 13, [0.647505, 0.647505, 0.647505]),
+            (4, 14, [0.702601, 0.702601, 0.702601]),
+        ],
+    ),
+    (
+        "Transparent Cube",
+        &[
+            (16, 8, [0.067704, 0.572077, 0.291869]),
+            (8, 6, [0.0, 0.0, 0.0]),
+            (16, 13, [0.0, 0.0, 0.0]),
+            (4, 14, [0.702601, 0.702601, 0.702601]),
+        ],
+    ),
+];
+
+// Every scenario is seen with the same light and camera, so that the
+// references only depend on the scenario and on the renderer
+fn render_scenario(name: &str) -> Canvas {
+    let mut scenario = Scenario::get(name).unwrap();
+    scenario.get_world().set_light(PointLight::new(
+        Tuple::white(),
+        Tuple::new_point(-10.0, 10.0, -10.0),
+    ));
+
+    let mut camera = Camera::new(32, 16, PI / 2.0);
+    camera.look_at(
+        Tuple::new_point(0.0, 1.5, -5.0),
+        Tuple::new_point(0.0, 1.0, 0.0),
+        Tuple::new_vector(0.0, 1.0, 0.0),
+    );
+
+    camera.render(scenario.get_world(), &RenderSettings::default())
+}
+
+#[test]
+fn every_scenario_has_reference_pixels() {
+    for name in Scenario::list() {
+        assert!(
+            REFERENCES.iter().any(|(reference, _)| *reference == name),
+            "no reference pixels for scenario {name}"
+        );
+    }
+}
+
+#[test]
+fn scenarios_render_the_reference_pixels() {
+    for (name, pixels) in REFERENCES {
+        let image = render_scenario(name);
+
+        for (x, y, [r, g, b]) in pixels.iter() {
+            let actual = image.pixel_at(*x, *y);
+            let expected = Tuple::new_color(*r, *g, *b);
+
+            assert!(
+                (&actual - &expected).magnitude() < TOLERANCE,
+                "{name} at ({x}, {y}): expected {expected:?}, got {actual:?}"
+            );
+        }
+    }
+}